@@ -0,0 +1,30 @@
+//! Feeds arbitrary bytes to the lexer, which must never panic on them.
+//!
+//! Run with `cargo +nightly fuzz run lexer` from the repository root.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use serqlane::{Lexer, TokenKind};
+
+fuzz_target!(|data: &[u8]| {
+    let source = String::from_utf8_lossy(data);
+
+    let (tokens, _) = Lexer::tokenize(&source);
+    assert_eq!(tokens.last().map(|t| t.kind()), Some(TokenKind::Eof));
+
+    // Tokens are in order and cover valid slices of the source.
+    let mut end = 0;
+    for token in &tokens {
+        let span = token.span();
+        assert!(end <= span.start().offset());
+        assert!(
+            source
+                .get(span.start().offset() as usize..span.end().offset() as usize)
+                .is_some()
+        );
+        end = span.end().offset();
+    }
+
+    for _ in Lexer::new(&source).without_auto_semicolons() {}
+});
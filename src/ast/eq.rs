@@ -0,0 +1,580 @@
+//! Compares ASTs for structural equality.
+//!
+//! Two trees are considered equal when they describe the same program,
+//! even if they were parsed from differently formatted sources. Spans
+//! are therefore ignored. Since an [`Ident`] is nothing but a span,
+//! names are compared by their text instead, which requires the source
+//! each tree was parsed from.
+
+use super::{
+    Ast, Attribute, Ident, Item, ItemKind, Pattern, Spanned, TypeNode, Visibility,
+    expr::{
+        ArithmeticLogicalOperator, BooleanOperator, ComparisonOperator, CompoundAssignmentOperator,
+        ExprId, Expression, ExpressionKind, Exprs, FieldInit, Literal, NegationOperator,
+        OperatorExpression,
+    },
+    stmt::{
+        Block, Const, Function, FunctionArg, Module, StatementKind, Struct, StructField,
+        StructFields, TypeAlias,
+    },
+};
+
+/// The sources two compared trees were parsed from, and the arenas
+/// their expressions are stored in.
+#[derive(Clone, Copy, Debug)]
+pub struct Sources<'a> {
+    pub lhs: &'a str,
+    pub rhs: &'a str,
+    pub lhs_exprs: &'a Exprs,
+    pub rhs_exprs: &'a Exprs,
+}
+
+/// Equality that ignores [`crate::diagnostic::SourceSpan`]s.
+pub trait SpanlessEq {
+    fn spanless_eq(&self, other: &Self, sources: Sources) -> bool;
+}
+
+/// Checks whether two trees describe the same program.
+pub fn ast_eq(lhs: &Ast, lhs_source: &str, rhs: &Ast, rhs_source: &str) -> bool {
+    let sources = Sources {
+        lhs: lhs_source,
+        rhs: rhs_source,
+        lhs_exprs: &lhs.exprs,
+        rhs_exprs: &rhs.exprs,
+    };
+    lhs.items.spanless_eq(&rhs.items, sources)
+}
+
+impl<T: SpanlessEq + ?Sized> SpanlessEq for Box<T> {
+    fn spanless_eq(&self, other: &Self, sources: Sources) -> bool {
+        (**self).spanless_eq(other, sources)
+    }
+}
+
+impl<T: SpanlessEq> SpanlessEq for [T] {
+    fn spanless_eq(&self, other: &Self, sources: Sources) -> bool {
+        self.len() == other.len()
+            && self
+                .iter()
+                .zip(other)
+                .all(|(a, b)| a.spanless_eq(b, sources))
+    }
+}
+
+impl<T: SpanlessEq> SpanlessEq for Spanned<T> {
+    fn spanless_eq(&self, other: &Self, sources: Sources) -> bool {
+        self.node.spanless_eq(&other.node, sources)
+    }
+}
+
+impl<T: SpanlessEq> SpanlessEq for Option<T> {
+    fn spanless_eq(&self, other: &Self, sources: Sources) -> bool {
+        match (self, other) {
+            (Some(a), Some(b)) => a.spanless_eq(b, sources),
+            (None, None) => true,
+            _ => false,
+        }
+    }
+}
+
+// Types without spans in them can use their regular equality.
+macro_rules! spanless_eq_via_partial_eq {
+    ($($ty:ty),* $(,)?) => {
+        $(
+            impl SpanlessEq for $ty {
+                fn spanless_eq(&self, other: &Self, _: Sources) -> bool {
+                    self == other
+                }
+            }
+        )*
+    };
+}
+
+spanless_eq_via_partial_eq!(
+    Visibility,
+    Literal,
+    ArithmeticLogicalOperator,
+    ComparisonOperator,
+    CompoundAssignmentOperator,
+    BooleanOperator,
+    NegationOperator,
+);
+
+impl SpanlessEq for Ident {
+    fn spanless_eq(&self, other: &Self, sources: Sources) -> bool {
+        sources.lhs[self.span] == sources.rhs[other.span]
+    }
+}
+
+impl SpanlessEq for Pattern {
+    fn spanless_eq(&self, other: &Self, sources: Sources) -> bool {
+        match (self, other) {
+            (Self::Ident(a), Self::Ident(b)) => a.spanless_eq(b, sources),
+            (Self::Wildcard(_), Self::Wildcard(_)) => true,
+            _ => false,
+        }
+    }
+}
+
+// Expressions at different places in their arenas can still be equal.
+impl SpanlessEq for ExprId {
+    fn spanless_eq(&self, other: &Self, sources: Sources) -> bool {
+        sources.lhs_exprs[*self].spanless_eq(&sources.rhs_exprs[*other], sources)
+    }
+}
+
+impl SpanlessEq for TypeNode {
+    fn spanless_eq(&self, other: &Self, sources: Sources) -> bool {
+        match (self, other) {
+            (
+                Self::Named {
+                    name: a_name,
+                    args: a_args,
+                },
+                Self::Named {
+                    name: b_name,
+                    args: b_args,
+                },
+            ) => a_name.spanless_eq(b_name, sources) && a_args.spanless_eq(b_args, sources),
+            (Self::Reference(a), Self::Reference(b))
+            | (Self::Pointer(a), Self::Pointer(b))
+            | (Self::Slice(a), Self::Slice(b)) => a.spanless_eq(b, sources),
+            (
+                Self::Array {
+                    elem: a_elem,
+                    len: a_len,
+                },
+                Self::Array {
+                    elem: b_elem,
+                    len: b_len,
+                },
+            ) => a_elem.spanless_eq(b_elem, sources) && a_len.spanless_eq(b_len, sources),
+            (Self::Tuple(a), Self::Tuple(b)) => a.spanless_eq(b, sources),
+            _ => false,
+        }
+    }
+}
+
+impl SpanlessEq for Attribute {
+    fn spanless_eq(&self, other: &Self, sources: Sources) -> bool {
+        self.name.spanless_eq(&other.name, sources) && self.args.spanless_eq(&other.args, sources)
+    }
+}
+
+impl SpanlessEq for Item {
+    fn spanless_eq(&self, other: &Self, sources: Sources) -> bool {
+        if self.docs != other.docs || !self.attrs.spanless_eq(&other.attrs, sources) {
+            return false;
+        }
+        match (&self.kind, &other.kind) {
+            (ItemKind::Function(a), ItemKind::Function(b)) => a.spanless_eq(b, sources),
+            (ItemKind::Struct(a), ItemKind::Struct(b)) => a.spanless_eq(b, sources),
+            (ItemKind::Const(a), ItemKind::Const(b)) => a.spanless_eq(b, sources),
+            (ItemKind::TypeAlias(a), ItemKind::TypeAlias(b)) => a.spanless_eq(b, sources),
+            (ItemKind::Module(a), ItemKind::Module(b)) => a.spanless_eq(b, sources),
+            _ => false,
+        }
+    }
+}
+
+impl SpanlessEq for Function {
+    fn spanless_eq(&self, other: &Self, sources: Sources) -> bool {
+        self.visibility == other.visibility
+            && self.name.spanless_eq(&other.name, sources)
+            && self.generics.spanless_eq(&other.generics, sources)
+            && self.args.spanless_eq(&other.args, sources)
+            && self.ret.spanless_eq(&other.ret, sources)
+            && self.block.spanless_eq(&other.block, sources)
+    }
+}
+
+impl SpanlessEq for FunctionArg {
+    fn spanless_eq(&self, other: &Self, sources: Sources) -> bool {
+        self.pattern.spanless_eq(&other.pattern, sources)
+            && self.typ.spanless_eq(&other.typ, sources)
+    }
+}
+
+impl SpanlessEq for Struct {
+    fn spanless_eq(&self, other: &Self, sources: Sources) -> bool {
+        self.visibility == other.visibility
+            && self.name.spanless_eq(&other.name, sources)
+            && match (&self.fields, &other.fields) {
+                (StructFields::Named(a), StructFields::Named(b)) => a.spanless_eq(b, sources),
+                (StructFields::Tuple(a), StructFields::Tuple(b)) => a.spanless_eq(b, sources),
+                _ => false,
+            }
+    }
+}
+
+impl SpanlessEq for StructField {
+    fn spanless_eq(&self, other: &Self, sources: Sources) -> bool {
+        self.name.spanless_eq(&other.name, sources) && self.typ.spanless_eq(&other.typ, sources)
+    }
+}
+
+impl SpanlessEq for Const {
+    fn spanless_eq(&self, other: &Self, sources: Sources) -> bool {
+        self.visibility == other.visibility
+            && self.name.spanless_eq(&other.name, sources)
+            && self.typ.spanless_eq(&other.typ, sources)
+            && self.expr.spanless_eq(&other.expr, sources)
+    }
+}
+
+impl SpanlessEq for TypeAlias {
+    fn spanless_eq(&self, other: &Self, sources: Sources) -> bool {
+        self.visibility == other.visibility
+            && self.name.spanless_eq(&other.name, sources)
+            && self.generics.spanless_eq(&other.generics, sources)
+            && self.typ.spanless_eq(&other.typ, sources)
+    }
+}
+
+impl SpanlessEq for Module {
+    fn spanless_eq(&self, other: &Self, sources: Sources) -> bool {
+        self.visibility == other.visibility
+            && self.name.spanless_eq(&other.name, sources)
+            && self.items.spanless_eq(&other.items, sources)
+    }
+}
+
+impl SpanlessEq for StatementKind {
+    fn spanless_eq(&self, other: &Self, sources: Sources) -> bool {
+        match (self, other) {
+            (StatementKind::Item(a), StatementKind::Item(b)) => a.spanless_eq(b, sources),
+            (
+                StatementKind::Variable {
+                    pattern: a_pattern,
+                    expr: a_expr,
+                    mutable: a_mutable,
+                },
+                StatementKind::Variable {
+                    pattern: b_pattern,
+                    expr: b_expr,
+                    mutable: b_mutable,
+                },
+            ) => {
+                a_mutable == b_mutable
+                    && a_pattern.spanless_eq(b_pattern, sources)
+                    && a_expr.spanless_eq(b_expr, sources)
+            }
+            (StatementKind::Expression(a), StatementKind::Expression(b)) => {
+                a.spanless_eq(b, sources)
+            }
+            (
+                StatementKind::For {
+                    label: a_label,
+                    binding: a_binding,
+                    iter: a_iter,
+                    body: a_body,
+                },
+                StatementKind::For {
+                    label: b_label,
+                    binding: b_binding,
+                    iter: b_iter,
+                    body: b_body,
+                },
+            ) => {
+                a_label.spanless_eq(b_label, sources)
+                    && a_binding.spanless_eq(b_binding, sources)
+                    && a_iter.spanless_eq(b_iter, sources)
+                    && a_body.spanless_eq(b_body, sources)
+            }
+            _ => false,
+        }
+    }
+}
+
+impl SpanlessEq for Block {
+    fn spanless_eq(&self, other: &Self, sources: Sources) -> bool {
+        self.stmts.spanless_eq(&other.stmts, sources) && self.tail.spanless_eq(&other.tail, sources)
+    }
+}
+
+impl SpanlessEq for FieldInit {
+    fn spanless_eq(&self, other: &Self, sources: Sources) -> bool {
+        self.name.spanless_eq(&other.name, sources) && self.expr.spanless_eq(&other.expr, sources)
+    }
+}
+
+impl SpanlessEq for Expression {
+    fn spanless_eq(&self, other: &Self, sources: Sources) -> bool {
+        use ExpressionKind as E;
+        match (&self.kind, &other.kind) {
+            (E::Ident(a), E::Ident(b)) => a.spanless_eq(b, sources),
+            (E::Array(a), E::Array(b)) | (E::Tuple(a), E::Tuple(b)) => a.spanless_eq(b, sources),
+            (
+                E::ArrayRepeat {
+                    value: a_value,
+                    len: a_len,
+                },
+                E::ArrayRepeat {
+                    value: b_value,
+                    len: b_len,
+                },
+            ) => a_value.spanless_eq(b_value, sources) && a_len.spanless_eq(b_len, sources),
+            (
+                E::Block {
+                    label: a_label,
+                    block: a_block,
+                },
+                E::Block {
+                    label: b_label,
+                    block: b_block,
+                },
+            ) => a_label.spanless_eq(b_label, sources) && a_block.spanless_eq(b_block, sources),
+            (
+                E::Loop {
+                    label: a_label,
+                    body: a_body,
+                },
+                E::Loop {
+                    label: b_label,
+                    body: b_body,
+                },
+            ) => a_label.spanless_eq(b_label, sources) && a_body.spanless_eq(b_body, sources),
+            (
+                E::Index {
+                    cont: a_cont,
+                    idx: a_idx,
+                },
+                E::Index {
+                    cont: b_cont,
+                    idx: b_idx,
+                },
+            ) => a_cont.spanless_eq(b_cont, sources) && a_idx.spanless_eq(b_idx, sources),
+            (
+                E::Call {
+                    func: a_func,
+                    params: a_params,
+                },
+                E::Call {
+                    func: b_func,
+                    params: b_params,
+                },
+            ) => a_func.spanless_eq(b_func, sources) && a_params.spanless_eq(b_params, sources),
+            (
+                E::Field {
+                    expr: a_expr,
+                    name: a_name,
+                },
+                E::Field {
+                    expr: b_expr,
+                    name: b_name,
+                },
+            ) => a_expr.spanless_eq(b_expr, sources) && a_name.spanless_eq(b_name, sources),
+            (
+                E::MethodCall {
+                    receiver: a_receiver,
+                    method: a_method,
+                    args: a_args,
+                },
+                E::MethodCall {
+                    receiver: b_receiver,
+                    method: b_method,
+                    args: b_args,
+                },
+            ) => {
+                a_receiver.spanless_eq(b_receiver, sources)
+                    && a_method.spanless_eq(b_method, sources)
+                    && a_args.spanless_eq(b_args, sources)
+            }
+            (
+                E::Cast {
+                    expr: a_expr,
+                    typ: a_typ,
+                },
+                E::Cast {
+                    expr: b_expr,
+                    typ: b_typ,
+                },
+            ) => a_expr.spanless_eq(b_expr, sources) && a_typ.spanless_eq(b_typ, sources),
+            (
+                E::If {
+                    cond: a_cond,
+                    then_block: a_then,
+                    else_block: a_else,
+                },
+                E::If {
+                    cond: b_cond,
+                    then_block: b_then,
+                    else_block: b_else,
+                },
+            ) => {
+                a_cond.spanless_eq(b_cond, sources)
+                    && a_then.spanless_eq(b_then, sources)
+                    && a_else.spanless_eq(b_else, sources)
+            }
+            (E::Literal(a), E::Literal(b)) => a == b,
+            (E::Operator(a), E::Operator(b)) => a.spanless_eq(b, sources),
+            (
+                E::Range {
+                    start: a_start,
+                    end: a_end,
+                    inclusive: a_inclusive,
+                },
+                E::Range {
+                    start: b_start,
+                    end: b_end,
+                    inclusive: b_inclusive,
+                },
+            ) => {
+                a_inclusive == b_inclusive
+                    && a_start.spanless_eq(b_start, sources)
+                    && a_end.spanless_eq(b_end, sources)
+            }
+            (
+                E::StructLiteral {
+                    name: a_name,
+                    fields: a_fields,
+                },
+                E::StructLiteral {
+                    name: b_name,
+                    fields: b_fields,
+                },
+            ) => a_name.spanless_eq(b_name, sources) && a_fields.spanless_eq(b_fields, sources),
+            (E::Return(a), E::Return(b)) => a.spanless_eq(b, sources),
+            (
+                E::Break {
+                    label: a_label,
+                    value: a_value,
+                },
+                E::Break {
+                    label: b_label,
+                    value: b_value,
+                },
+            ) => a_label.spanless_eq(b_label, sources) && a_value.spanless_eq(b_value, sources),
+            (E::Continue { label: a }, E::Continue { label: b }) => a.spanless_eq(b, sources),
+            (E::Error, E::Error) => true,
+            _ => false,
+        }
+    }
+}
+
+impl SpanlessEq for OperatorExpression {
+    fn spanless_eq(&self, other: &Self, sources: Sources) -> bool {
+        use OperatorExpression as O;
+        match (self, other) {
+            (
+                O::ArithmeticLogical {
+                    lhs: a_lhs,
+                    op: a_op,
+                    rhs: a_rhs,
+                },
+                O::ArithmeticLogical {
+                    lhs: b_lhs,
+                    op: b_op,
+                    rhs: b_rhs,
+                },
+            ) => {
+                a_op == b_op
+                    && a_lhs.spanless_eq(b_lhs, sources)
+                    && a_rhs.spanless_eq(b_rhs, sources)
+            }
+            (
+                O::Comparison {
+                    lhs: a_lhs,
+                    op: a_op,
+                    rhs: a_rhs,
+                },
+                O::Comparison {
+                    lhs: b_lhs,
+                    op: b_op,
+                    rhs: b_rhs,
+                },
+            ) => {
+                a_op == b_op
+                    && a_lhs.spanless_eq(b_lhs, sources)
+                    && a_rhs.spanless_eq(b_rhs, sources)
+            }
+            (
+                O::CompoundAssignment {
+                    lhs: a_lhs,
+                    op: a_op,
+                    rhs: a_rhs,
+                },
+                O::CompoundAssignment {
+                    lhs: b_lhs,
+                    op: b_op,
+                    rhs: b_rhs,
+                },
+            ) => {
+                a_op == b_op
+                    && a_lhs.spanless_eq(b_lhs, sources)
+                    && a_rhs.spanless_eq(b_rhs, sources)
+            }
+            (
+                O::Boolean {
+                    lhs: a_lhs,
+                    op: a_op,
+                    rhs: a_rhs,
+                },
+                O::Boolean {
+                    lhs: b_lhs,
+                    op: b_op,
+                    rhs: b_rhs,
+                },
+            ) => {
+                a_op == b_op
+                    && a_lhs.spanless_eq(b_lhs, sources)
+                    && a_rhs.spanless_eq(b_rhs, sources)
+            }
+            (
+                O::Negation {
+                    op: a_op,
+                    expr: a_expr,
+                },
+                O::Negation {
+                    op: b_op,
+                    expr: b_expr,
+                },
+            ) => a_op == b_op && a_expr.spanless_eq(b_expr, sources),
+            (
+                O::Assignment {
+                    lhs: a_lhs,
+                    rhs: a_rhs,
+                },
+                O::Assignment {
+                    lhs: b_lhs,
+                    rhs: b_rhs,
+                },
+            ) => a_lhs.spanless_eq(b_lhs, sources) && a_rhs.spanless_eq(b_rhs, sources),
+            (O::AddressOf { expr: a }, O::AddressOf { expr: b })
+            | (O::Dereference { expr: a }, O::Dereference { expr: b })
+            | (O::Increment { expr: a }, O::Increment { expr: b })
+            | (O::Decrement { expr: a }, O::Decrement { expr: b }) => a.spanless_eq(b, sources),
+            _ => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Parser, ast::pretty::pretty_print};
+
+    // Parses both sources, which must be free of errors, and compares
+    // the trees.
+    fn same(lhs: &str, rhs: &str) -> bool {
+        let (lhs_ast, diagnostics) = Parser::new(lhs).parse();
+        assert!(diagnostics.is_empty(), "{lhs}: {diagnostics:?}");
+        let (rhs_ast, diagnostics) = Parser::new(rhs).parse();
+        assert!(diagnostics.is_empty(), "{rhs}: {diagnostics:?}");
+        ast_eq(&lhs_ast, lhs, &rhs_ast, rhs)
+    }
+
+    #[test]
+    fn ignores_spans() {
+        let source = "fn add(a: int, b: int): int {\n    let c = a+b*2\n    c\n}";
+        let (ast, _) = Parser::new(source).parse();
+        let printed = pretty_print(source, &ast);
+        assert_ne!(printed, source);
+        assert!(same(source, &printed));
+
+        assert!(same("fn f() { g(1) }", "\n\nfn  f( ){g( 1 )}"));
+        assert!(!same("fn f() { g(1) }", "fn f() { h(1) }"));
+        assert!(!same("fn f() { g(1) }", "fn f() { g(2) }"));
+        assert!(!same("fn f() { g(1) }", "fn f() { g(1); }"));
+    }
+}
@@ -1,88 +1,390 @@
 //! Defines structures for describing expressions.
+//!
+//! Expressions don't own their operands. Every expression of a program
+//! is stored in one [`Exprs`] arena instead, and refers to its operands
+//! by their [`ExprId`] in there. This saves an allocation per node and
+//! keeps the nodes close together in memory.
 
-use crate::lexer::TokenKind;
+use std::{
+    mem,
+    ops::{Index, IndexMut},
+};
 
-use super::{Ident, stmt::Statement};
+use crate::{diagnostic::SourceSpan, lexer::TokenKind};
+
+use super::{Ident, Spanned, TypeNode, stmt::Block, ty::Type};
+
+/// Refers to an [`Expression`] in an [`Exprs`] arena.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct ExprId(u32);
+
+/// The arena that owns all expressions of a program.
+///
+/// Expressions are only ever added. One that is replaced by a rewrite,
+/// like an operand that was folded into a literal, stays in the arena
+/// without anything referring to it.
+#[derive(Clone, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Exprs {
+    exprs: Vec<Expression>,
+}
+
+impl Exprs {
+    /// Creates a new, empty arena.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds an expression to the arena and returns its id.
+    pub fn alloc(&mut self, expr: Expression) -> ExprId {
+        let id = ExprId(self.exprs.len() as u32);
+        self.exprs.push(expr);
+        id
+    }
+
+    /// Gets the number of expressions in the arena.
+    pub fn len(&self) -> usize {
+        self.exprs.len()
+    }
+
+    /// Whether the arena holds no expressions.
+    pub fn is_empty(&self) -> bool {
+        self.exprs.is_empty()
+    }
+
+    // Drops the expressions allocated after the first `len`, for a
+    // parse that is undone.
+    pub(crate) fn truncate(&mut self, len: usize) {
+        self.exprs.truncate(len);
+    }
+
+    /// Calls `f` with the kind of an expression and the arena, which
+    /// allows changing both at the same time, e.g. to rewrite the
+    /// operands of an expression while walking the tree.
+    ///
+    /// The expression is an [`ExpressionKind::Error`] while `f` runs.
+    pub fn with_kind<R>(
+        &mut self,
+        id: ExprId,
+        f: impl FnOnce(&mut Self, &mut ExpressionKind) -> R,
+    ) -> R {
+        let mut kind = mem::replace(&mut self[id].kind, ExpressionKind::Error);
+        let result = f(self, &mut kind);
+        self[id].kind = kind;
+        result
+    }
+}
+
+impl Index<ExprId> for Exprs {
+    type Output = Expression;
+
+    fn index(&self, id: ExprId) -> &Expression {
+        &self.exprs[id.0 as usize]
+    }
+}
+
+impl IndexMut<ExprId> for Exprs {
+    fn index_mut(&mut self, id: ExprId) -> &mut Expression {
+        &mut self.exprs[id.0 as usize]
+    }
+}
 
 #[derive(Clone, Debug)]
-pub enum Expression {
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Expression {
+    pub kind: ExpressionKind,
+    // Covers the whole expression, including all subexpressions.
+    pub span: SourceSpan,
+    // Filled in by type checking.
+    pub ty: Option<Type>,
+}
+
+impl Expression {
+    pub fn new(kind: ExpressionKind, span: SourceSpan) -> Self {
+        Self {
+            kind,
+            span,
+            ty: None,
+        }
+    }
+
+    pub fn span(&self) -> SourceSpan {
+        self.span
+    }
+
+    /// Whether this expression ends in a block of its own, like `if` and
+    /// `loop`. Such an expression needs no `;` to end a statement.
+    pub fn is_block_like(&self) -> bool {
+        matches!(
+            self.kind,
+            ExpressionKind::Block { .. } | ExpressionKind::If { .. } | ExpressionKind::Loop { .. }
+        )
+    }
+}
+
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub enum ExpressionKind {
     Ident(Ident),
-    Block(Box<[Statement]>),
+    // `[1, 2, 3]`
+    Array(Box<[ExprId]>),
+    // `[0; 5]`
+    ArrayRepeat {
+        value: ExprId,
+        len: ExprId,
+    },
+    // `{ ... }`, or `'b: { ... }` with a label that `break` can leave
+    // it by.
+    Block {
+        label: Option<Ident>,
+        block: Block,
+    },
+    // `a[i]`, or `m[i, j]` with several indices
     Index {
-        cont: Box<Expression>,
-        idx: Box<Expression>,
+        cont: ExprId,
+        idx: Box<[ExprId]>,
     },
     Call {
-        func: Box<Expression>,
-        params: Box<[Expression]>,
+        func: ExprId,
+        params: Box<[ExprId]>,
+    },
+    // `a.b`
+    Field {
+        expr: ExprId,
+        name: Ident,
+    },
+    // `a.b(c)`, which is never parsed as a call of the field `a.b`.
+    MethodCall {
+        receiver: ExprId,
+        method: Ident,
+        args: Box<[ExprId]>,
+    },
+    // `x as u8`
+    Cast {
+        expr: ExprId,
+        typ: Spanned<TypeNode>,
+    },
+    // `else if` chains nest another `If` in `else_block`.
+    If {
+        cond: ExprId,
+        then_block: Block,
+        else_block: Option<ExprId>,
     },
     Literal(Literal),
+    // `loop { ... }`, which only ends through `break`, or `'a: loop { ... }`
+    // with a label that `break` and `continue` can target it by.
+    Loop {
+        label: Option<Ident>,
+        body: Block,
+    },
     Operator(OperatorExpression),
-    Return(Box<Expression>),
+    // `a..b`, `a..=b`, and `a..`, `..b`, `..=b` and `..` without either
+    // bound.
+    Range {
+        start: Option<ExprId>,
+        end: Option<ExprId>,
+        inclusive: bool,
+    },
+    // `Point { x: 1, y: 2 }`
+    StructLiteral {
+        name: Ident,
+        fields: Box<[FieldInit]>,
+    },
+    // `(1, 2)` and `(1,)`. The empty tuple `()` is the unit literal.
+    Tuple(Box<[ExprId]>),
+    Return(Option<ExprId>),
+    // `break`, `break value`, `break 'b` and `break 'b value`.
+    Break {
+        label: Option<Ident>,
+        value: Option<ExprId>,
+    },
+    // `continue` and `continue 'a`.
+    Continue {
+        label: Option<Ident>,
+    },
+    // Placeholder for an expression that failed to parse.
+    Error,
 }
 
+// `name: expr` in a struct literal.
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct FieldInit {
+    pub name: Ident,
+    pub expr: ExprId,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub enum Literal {
-    Int(u64),
+    Int {
+        value: u64,
+        // Set when the literal spells out its type, as in `255u8`.
+        typ: Option<IntType>,
+    },
+    Float(f64),
     Bool(bool),
+    // The contents between the quotes, with escape sequences already
+    // replaced.
+    Str(String),
+    // `'a'`, with its escape sequence already replaced.
+    Char(char),
+    // `()`, the only value of the unit type.
+    Unit,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub enum IntType {
+    U8,
+    U16,
+    U32,
+    U64,
+    Usize,
+    I8,
+    I16,
+    I32,
+    I64,
+    Isize,
+}
+
+impl IntType {
+    /// Parses an integer literal suffix such as `u8` or `isize`.
+    pub fn from_suffix(suffix: &str) -> Option<Self> {
+        Some(match suffix {
+            "u8" => Self::U8,
+            "u16" => Self::U16,
+            "u32" => Self::U32,
+            "u64" => Self::U64,
+            "usize" => Self::Usize,
+            "i8" => Self::I8,
+            "i16" => Self::I16,
+            "i32" => Self::I32,
+            "i64" => Self::I64,
+            "isize" => Self::Isize,
+            _ => return None,
+        })
+    }
+
+    /// Gets the suffix spelling of this type.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::U8 => "u8",
+            Self::U16 => "u16",
+            Self::U32 => "u32",
+            Self::U64 => "u64",
+            Self::Usize => "usize",
+            Self::I8 => "i8",
+            Self::I16 => "i16",
+            Self::I32 => "i32",
+            Self::I64 => "i64",
+            Self::Isize => "isize",
+        }
+    }
+
+    /// Gets the largest value a literal of this type may have.
+    ///
+    /// Pointer-sized types are assumed to be 64 bits wide.
+    pub fn max(self) -> u64 {
+        match self {
+            Self::U8 => u8::MAX as u64,
+            Self::U16 => u16::MAX as u64,
+            Self::U32 => u32::MAX as u64,
+            Self::U64 | Self::Usize => u64::MAX,
+            Self::I8 => i8::MAX as u64,
+            Self::I16 => i16::MAX as u64,
+            Self::I32 => i32::MAX as u64,
+            Self::I64 | Self::Isize => i64::MAX as u64,
+        }
+    }
+
+    /// Gets the width of this type in bits.
+    ///
+    /// Pointer-sized types are assumed to be 64 bits wide.
+    pub fn bits(self) -> u32 {
+        match self {
+            Self::U8 | Self::I8 => 8,
+            Self::U16 | Self::I16 => 16,
+            Self::U32 | Self::I32 => 32,
+            Self::U64 | Self::Usize | Self::I64 | Self::Isize => 64,
+        }
+    }
+
+    pub fn is_signed(self) -> bool {
+        matches!(
+            self,
+            Self::I8 | Self::I16 | Self::I32 | Self::I64 | Self::Isize
+        )
+    }
 }
 
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub enum OperatorExpression {
     // `a + b`, `1 << 3`
     ArithmeticLogical {
-        lhs: Box<Expression>,
+        lhs: ExprId,
         op: ArithmeticLogicalOperator,
-        rhs: Box<Expression>,
+        rhs: ExprId,
     },
 
     // `a <= b`, `(5 + 1) < (5 * 2)`
     Comparison {
-        lhs: Box<Expression>,
+        lhs: ExprId,
         op: ComparisonOperator,
-        rhs: Box<Expression>,
+        rhs: ExprId,
     },
 
     // `a += 5`
     CompoundAssignment {
-        lhs: Box<Expression>,
+        lhs: ExprId,
         op: CompoundAssignmentOperator,
-        rhs: Box<Expression>,
+        rhs: ExprId,
     },
 
     // `a && b`
     Boolean {
-        lhs: Box<Expression>,
+        lhs: ExprId,
         op: BooleanOperator,
-        rhs: Box<Expression>,
+        rhs: ExprId,
     },
 
     // `-(5 + 2)`, `!(a && b)`
     Negation {
         op: NegationOperator,
-        expr: Box<Expression>,
+        expr: ExprId,
     },
 
     // `a = 5 + 2`
     Assignment {
-        lhs: Box<Expression>,
-        rhs: Box<Expression>,
+        lhs: ExprId,
+        rhs: ExprId,
     },
 
     // `&a`
     AddressOf {
-        expr: Box<Expression>,
+        expr: ExprId,
     },
 
     // `*ptr`
     Dereference {
-        expr: Box<Expression>,
+        expr: ExprId,
+    },
+
+    // `a++`
+    Increment {
+        expr: ExprId,
+    },
+
+    // `a--`
+    Decrement {
+        expr: ExprId,
     },
 }
 
 impl OperatorExpression {
-    pub fn prefix(op: TokenKind, expr: Expression) -> Self {
-        let expr = Box::new(expr);
+    pub fn prefix(op: TokenKind, expr: ExprId) -> Self {
         match op {
             TokenKind::Minus | TokenKind::Bang | TokenKind::Tilde => Self::Negation {
                 op: NegationOperator::from(op),
@@ -94,13 +396,20 @@ impl OperatorExpression {
         }
     }
 
-    pub fn infix(lhs: Expression, op: TokenKind, rhs: Expression) -> Self {
-        let lhs = Box::new(lhs);
-        let rhs = Box::new(rhs);
+    pub fn postfix(expr: ExprId, op: TokenKind) -> Self {
+        match op {
+            TokenKind::PlusPlus => Self::Increment { expr },
+            TokenKind::MinusMinus => Self::Decrement { expr },
+            _ => unreachable!(),
+        }
+    }
+
+    pub fn infix(lhs: ExprId, op: TokenKind, rhs: ExprId) -> Self {
         match op {
             TokenKind::Plus
             | TokenKind::Minus
             | TokenKind::Star
+            | TokenKind::StarStar
             | TokenKind::Slash
             | TokenKind::Percent
             | TokenKind::And
@@ -131,12 +440,15 @@ impl OperatorExpression {
             TokenKind::PlusEq
             | TokenKind::MinusEq
             | TokenKind::StarEq
+            | TokenKind::StarStarEq
             | TokenKind::SlashEq
             | TokenKind::PercentEq
             | TokenKind::ShlEq
             | TokenKind::ShrEq
             | TokenKind::AndEq
             | TokenKind::OrEq
+            | TokenKind::AndAndEq
+            | TokenKind::OrOrEq
             | TokenKind::CaretEq => Self::CompoundAssignment {
                 lhs,
                 op: CompoundAssignmentOperator::from(op),
@@ -148,12 +460,14 @@ impl OperatorExpression {
 }
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub enum ArithmeticLogicalOperator {
     Plus,
     Minus,
     Multiply,
     Divide,
     Modulo,
+    Pow,
     And,
     Or,
     Xor,
@@ -169,6 +483,7 @@ impl From<TokenKind> for ArithmeticLogicalOperator {
             TokenKind::Star => Self::Multiply,
             TokenKind::Slash => Self::Divide,
             TokenKind::Percent => Self::Modulo,
+            TokenKind::StarStar => Self::Pow,
             TokenKind::And => Self::And,
             TokenKind::Or => Self::Or,
             TokenKind::Caret => Self::Xor,
@@ -179,7 +494,33 @@ impl From<TokenKind> for ArithmeticLogicalOperator {
     }
 }
 
+impl From<ArithmeticLogicalOperator> for TokenKind {
+    fn from(op: ArithmeticLogicalOperator) -> Self {
+        match op {
+            ArithmeticLogicalOperator::Plus => Self::Plus,
+            ArithmeticLogicalOperator::Minus => Self::Minus,
+            ArithmeticLogicalOperator::Multiply => Self::Star,
+            ArithmeticLogicalOperator::Divide => Self::Slash,
+            ArithmeticLogicalOperator::Modulo => Self::Percent,
+            ArithmeticLogicalOperator::Pow => Self::StarStar,
+            ArithmeticLogicalOperator::And => Self::And,
+            ArithmeticLogicalOperator::Or => Self::Or,
+            ArithmeticLogicalOperator::Xor => Self::Caret,
+            ArithmeticLogicalOperator::Shl => Self::Shl,
+            ArithmeticLogicalOperator::Shr => Self::Shr,
+        }
+    }
+}
+
+impl ArithmeticLogicalOperator {
+    /// Gets the operator as it is spelled in source code.
+    pub fn as_str(self) -> &'static str {
+        TokenKind::from(self).as_str()
+    }
+}
+
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub enum ComparisonOperator {
     Eq,
     NotEq,
@@ -203,18 +544,44 @@ impl From<TokenKind> for ComparisonOperator {
     }
 }
 
+impl From<ComparisonOperator> for TokenKind {
+    fn from(op: ComparisonOperator) -> Self {
+        match op {
+            ComparisonOperator::Eq => Self::EqEq,
+            ComparisonOperator::NotEq => Self::BangEq,
+            ComparisonOperator::Lt => Self::Lt,
+            ComparisonOperator::LtEq => Self::LtEq,
+            ComparisonOperator::Gt => Self::Gt,
+            ComparisonOperator::GtEq => Self::GtEq,
+        }
+    }
+}
+
+impl ComparisonOperator {
+    /// Gets the operator as it is spelled in source code.
+    pub fn as_str(self) -> &'static str {
+        TokenKind::from(self).as_str()
+    }
+}
+
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub enum CompoundAssignmentOperator {
     Plus,
     Minus,
     Multiply,
     Divide,
     Modulo,
+    Pow,
     And,
     Or,
     Xor,
     Shl,
     Shr,
+    // `&&=` and `||=`, which only evaluate their right operand when it
+    // decides the result, like `&&` and `||`.
+    LogicalAnd,
+    LogicalOr,
 }
 
 impl From<TokenKind> for CompoundAssignmentOperator {
@@ -225,17 +592,54 @@ impl From<TokenKind> for CompoundAssignmentOperator {
             TokenKind::StarEq => Self::Multiply,
             TokenKind::SlashEq => Self::Divide,
             TokenKind::PercentEq => Self::Modulo,
+            TokenKind::StarStarEq => Self::Pow,
             TokenKind::ShlEq => Self::Shl,
             TokenKind::ShrEq => Self::Shr,
             TokenKind::AndEq => Self::And,
             TokenKind::OrEq => Self::Or,
             TokenKind::CaretEq => Self::Xor,
+            TokenKind::AndAndEq => Self::LogicalAnd,
+            TokenKind::OrOrEq => Self::LogicalOr,
             _ => unreachable!(),
         }
     }
 }
 
+impl From<CompoundAssignmentOperator> for TokenKind {
+    fn from(op: CompoundAssignmentOperator) -> Self {
+        match op {
+            CompoundAssignmentOperator::Plus => Self::PlusEq,
+            CompoundAssignmentOperator::Minus => Self::MinusEq,
+            CompoundAssignmentOperator::Multiply => Self::StarEq,
+            CompoundAssignmentOperator::Divide => Self::SlashEq,
+            CompoundAssignmentOperator::Modulo => Self::PercentEq,
+            CompoundAssignmentOperator::Pow => Self::StarStarEq,
+            CompoundAssignmentOperator::Shl => Self::ShlEq,
+            CompoundAssignmentOperator::Shr => Self::ShrEq,
+            CompoundAssignmentOperator::And => Self::AndEq,
+            CompoundAssignmentOperator::Or => Self::OrEq,
+            CompoundAssignmentOperator::Xor => Self::CaretEq,
+            CompoundAssignmentOperator::LogicalAnd => Self::AndAndEq,
+            CompoundAssignmentOperator::LogicalOr => Self::OrOrEq,
+        }
+    }
+}
+
+impl CompoundAssignmentOperator {
+    /// Gets the operator as it is spelled in source code.
+    pub fn as_str(self) -> &'static str {
+        TokenKind::from(self).as_str()
+    }
+
+    /// Whether the right operand is only evaluated if the value of the
+    /// left one doesn't already decide the result, as for `&&=`.
+    pub fn is_short_circuit(self) -> bool {
+        matches!(self, Self::LogicalAnd | Self::LogicalOr)
+    }
+}
+
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub enum BooleanOperator {
     And,
     Or,
@@ -251,7 +655,24 @@ impl From<TokenKind> for BooleanOperator {
     }
 }
 
+impl From<BooleanOperator> for TokenKind {
+    fn from(op: BooleanOperator) -> Self {
+        match op {
+            BooleanOperator::And => Self::AndAnd,
+            BooleanOperator::Or => Self::OrOr,
+        }
+    }
+}
+
+impl BooleanOperator {
+    /// Gets the operator as it is spelled in source code.
+    pub fn as_str(self) -> &'static str {
+        TokenKind::from(self).as_str()
+    }
+}
+
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub enum NegationOperator {
     Negation,
     LogicalNot,
@@ -268,3 +689,55 @@ impl From<TokenKind> for NegationOperator {
         }
     }
 }
+
+impl From<NegationOperator> for TokenKind {
+    fn from(op: NegationOperator) -> Self {
+        match op {
+            NegationOperator::Negation => Self::Minus,
+            NegationOperator::LogicalNot => Self::Bang,
+            NegationOperator::BitwiseNot => Self::Tilde,
+        }
+    }
+}
+
+impl NegationOperator {
+    /// Gets the operator as it is spelled in source code.
+    pub fn as_str(self) -> &'static str {
+        TokenKind::from(self).as_str()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Parser;
+
+    #[test]
+    fn literal_kinds() {
+        let sources = ["42", "1.5", "true", "\"s\"", "'c'", "()"];
+        let kinds: Vec<_> = sources
+            .iter()
+            .map(|source| {
+                let (exprs, id, diagnostics) = Parser::new(source).parse_expr();
+                assert!(diagnostics.is_empty(), "{source}: {diagnostics:?}");
+                let ExpressionKind::Literal(literal) = &exprs[id].kind else {
+                    panic!("expected a literal: {source}");
+                };
+                assert_eq!(literal.clone(), *literal);
+                match literal {
+                    Literal::Int {
+                        value: 42,
+                        typ: None,
+                    } => "int",
+                    Literal::Float(1.5) => "float",
+                    Literal::Bool(true) => "bool",
+                    Literal::Str(s) if s == "s" => "str",
+                    Literal::Char('c') => "char",
+                    Literal::Unit => "unit",
+                    _ => panic!("unexpected literal {literal:?}"),
+                }
+            })
+            .collect();
+        assert_eq!(kinds, ["int", "float", "bool", "str", "char", "unit"]);
+    }
+}
@@ -8,29 +8,284 @@
 //! ergonomics and convenience when manipulating the AST. We will
 //! reuse that same AST in the subsequent passes by filling optional
 //! fields as we go (e.g. with type information from type inference).
+//!
+//! The one exception to a plain tree are expressions, which are stored
+//! in an arena of the [`Ast`] and refer to each other by id. See the
+//! [`expr`] module for why.
+
+use std::ops::Deref;
 
 use crate::{
     diagnostic::SourceSpan,
     lexer::{Token, TokenKind},
 };
 
+pub mod eq;
+
 pub mod expr;
 
+pub mod pretty;
+
 pub mod stmt;
 
+pub mod symbol;
+
+pub mod ty;
+
+pub mod visit;
+
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct Ident {
     pub span: SourceSpan,
+    // The interned name, filled in by name resolution.
+    pub symbol: Option<symbol::Symbol>,
+    // Filled in by name resolution, both where a name is declared and
+    // where it is used.
+    pub decl: Option<DeclId>,
+}
+
+impl Ident {
+    pub fn new(span: SourceSpan) -> Self {
+        Self {
+            span,
+            symbol: None,
+            decl: None,
+        }
+    }
 }
 
 impl From<Token> for Ident {
     fn from(t: Token) -> Self {
         debug_assert_eq!(t.kind(), TokenKind::Identifier);
-        Self { span: t.span() }
+        Self::new(t.span())
     }
 }
 
+/// A node of the tree together with the span it covers, for nodes that
+/// don't keep a span of their own. It dereferences to the node.
 #[derive(Clone, Debug)]
-pub enum Item {
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Spanned<T> {
+    pub node: T,
+    pub span: SourceSpan,
+}
+
+impl<T> Spanned<T> {
+    pub fn new(node: T, span: SourceSpan) -> Self {
+        Self { node, span }
+    }
+
+    pub fn span(&self) -> SourceSpan {
+        self.span
+    }
+}
+
+impl<T> Deref for Spanned<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.node
+    }
+}
+
+// What a value is bound to by `let`, `for` or a function parameter.
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub enum Pattern {
+    Ident(Ident),
+    // `_`, which discards the value.
+    Wildcard(SourceSpan),
+}
+
+impl Pattern {
+    // Gets the name that is bound, if any.
+    pub fn ident(&self) -> Option<&Ident> {
+        match self {
+            Self::Ident(ident) => Some(ident),
+            Self::Wildcard(_) => None,
+        }
+    }
+
+    pub fn span(&self) -> SourceSpan {
+        match self {
+            Self::Ident(ident) => ident.span,
+            Self::Wildcard(span) => *span,
+        }
+    }
+}
+
+// Refers to a declaration in the table built by name resolution.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct DeclId(pub u32);
+
+// A type as written in the source, e.g. in a parameter list.
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub enum TypeNode {
+    // `int`, `Vec<int>`
+    Named {
+        name: Ident,
+        args: Box<[Spanned<TypeNode>]>,
+    },
+    // `&T`
+    Reference(Box<Spanned<TypeNode>>),
+    // `*T`
+    Pointer(Box<Spanned<TypeNode>>),
+    // `[T]`
+    Slice(Box<Spanned<TypeNode>>),
+    // `[T; 4]`
+    Array {
+        elem: Box<Spanned<TypeNode>>,
+        len: expr::ExprId,
+    },
+    // `(int, bool)`, `(int,)` and the unit type `()`
+    Tuple(Box<[Spanned<TypeNode>]>),
+}
+
+/// A parsed program.
+#[derive(Clone, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Ast {
+    pub items: Vec<Item>,
+    // Every expression within `items`.
+    pub exprs: expr::Exprs,
+}
+
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Item {
+    pub kind: ItemKind,
+    pub attrs: Box<[Attribute]>,
+    // The text of the `///` doc comments in front of the item, without
+    // the slashes and joined by newlines.
+    pub docs: Option<String>,
+    // Covers the whole item, including its attributes and a leading
+    // `pub`.
+    pub span: SourceSpan,
+}
+
+impl Item {
+    pub fn new(kind: ItemKind, span: SourceSpan) -> Self {
+        Self {
+            kind,
+            attrs: Box::default(),
+            docs: None,
+            span,
+        }
+    }
+
+    pub fn with_attrs(mut self, attrs: impl Into<Box<[Attribute]>>) -> Self {
+        self.attrs = attrs.into();
+        self
+    }
+
+    pub fn with_docs(mut self, docs: Option<String>) -> Self {
+        self.docs = docs;
+        self
+    }
+
+    pub fn span(&self) -> SourceSpan {
+        self.span
+    }
+}
+
+// `#[name]` or `#[name(args)]` in front of an item. The arguments are
+// neither resolved nor type checked.
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Attribute {
+    pub name: Ident,
+    pub args: Option<Box<[expr::ExprId]>>,
+    // Covers everything from `#` to `]`.
+    pub span: SourceSpan,
+}
+
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub enum ItemKind {
     Function(stmt::Function),
+    Struct(stmt::Struct),
+    Const(stmt::Const),
+    TypeAlias(stmt::TypeAlias),
+    Module(stmt::Module),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub enum Visibility {
+    // Marked with `pub`.
+    Public,
+    Private,
+}
+
+#[cfg(all(test, feature = "serde"))]
+mod tests {
+    use crate::Parser;
+
+    #[test]
+    fn json_snapshot() {
+        let (ast, diagnostics) = Parser::new("fn f() { 1 }").parse();
+        assert!(diagnostics.is_empty(), "{diagnostics:?}");
+        let json = serde_json::to_string_pretty(&ast).unwrap();
+        assert_eq!(
+            json,
+            r#"{
+  "items": [
+    {
+      "kind": {
+        "Function": {
+          "visibility": "Private",
+          "name": {
+            "span": {
+              "start": 3,
+              "end": 4
+            },
+            "symbol": null,
+            "decl": null
+          },
+          "generics": [],
+          "args": [],
+          "ret": null,
+          "block": {
+            "stmts": [],
+            "tail": 0,
+            "span": {
+              "start": 7,
+              "end": 12
+            }
+          }
+        }
+      },
+      "attrs": [],
+      "docs": null,
+      "span": {
+        "start": 0,
+        "end": 12
+      }
+    }
+  ],
+  "exprs": {
+    "exprs": [
+      {
+        "kind": {
+          "Literal": {
+            "Int": {
+              "value": 1,
+              "typ": null
+            }
+          }
+        },
+        "span": {
+          "start": 9,
+          "end": 10
+        },
+        "ty": null
+      }
+    ]
+  }
+}"#,
+        );
+    }
 }
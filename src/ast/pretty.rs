@@ -0,0 +1,1109 @@
+//! Turns an AST back into Serqlane source code.
+//!
+//! The output is normalized rather than a copy of the original text:
+//! comments and redundant parentheses are gone, and every block is laid
+//! out on its own lines. Parentheses are re-inserted wherever the
+//! precedence of the parser would otherwise group an expression
+//! differently, so parsing the output yields the same tree again. The
+//! only redundant ones that are printed go around `&`, `|` and `^` in
+//! a comparison, which [`crate::sema::check_precedence`] asks for.
+//!
+//! Since the AST only stores spans for names, printing needs the source
+//! the tree was parsed from.
+//!
+//! [`format`] builds a source formatter on top of this. It additionally
+//! keeps comments, single blank lines between statements, and the
+//! spelling of literals.
+
+use std::{collections::VecDeque, mem};
+
+use super::{
+    Ast, Attribute, Ident, Item, ItemKind, Pattern, TypeNode, Visibility,
+    expr::{
+        ArithmeticLogicalOperator, ExprId, Expression, ExpressionKind, Exprs, Literal,
+        OperatorExpression,
+    },
+    stmt::{
+        Block, Const, Function, Module, Statement, StatementKind, Struct, StructFields, TypeAlias,
+    },
+    visit::{self, Visitor},
+};
+use crate::{
+    diagnostic::SourceSpan,
+    lexer::{TokenKind, escape_string},
+    parser::{infix_binding_power, postfix_binding_power, prefix_binding_power},
+};
+
+/// Prints `ast` as source code, using `source` to look up names.
+pub fn pretty_print(source: &str, ast: &Ast) -> String {
+    let mut printer = Printer::new(source, &ast.exprs, &[], false);
+    for (i, item) in ast.items.iter().enumerate() {
+        if i > 0 {
+            printer.out.push('\n');
+        }
+        printer.item(item);
+        printer.out.push('\n');
+    }
+    printer.out
+}
+
+/// Formats `ast` as the canonical form of `source`, which it was parsed
+/// from.
+///
+/// `comments` are the spans of all comments in `source`, as collected
+/// by [`crate::lexer::Lexer::comments`]. Comments that are alone on
+/// their line are kept on a line of their own before the statement or
+/// item that follows them. Comments after a statement on the same line
+/// stay there. Comments within a statement are moved in front of it.
+///
+/// Formatting the output again yields the same text.
+pub fn format(source: &str, ast: &Ast, comments: &[SourceSpan]) -> String {
+    let mut printer = Printer::new(source, &ast.exprs, comments, true);
+    for item in &ast.items {
+        let start = item.span.start().offset();
+        printer.leading_comments(start);
+        printer.inner_comments(item.span, &Nested::item(&ast.exprs, item));
+        printer.line(start);
+        printer.item(item);
+        printer.end_line(item.span.end().offset());
+        // Items are always separated by a blank line.
+        printer.blank_line = true;
+    }
+    printer.leading_comments(u32::MAX);
+
+    if !printer.out.is_empty() {
+        printer.out.push('\n');
+    }
+    printer.out
+}
+
+// Binding power of something that is never split up by an operator
+// next to it, such as a literal or a call.
+const ATOM: u8 = u8::MAX;
+
+// Gets the token that spells the outermost operator of `op`.
+fn operator_token(op: &OperatorExpression) -> TokenKind {
+    match op {
+        OperatorExpression::ArithmeticLogical { op, .. } => TokenKind::from(*op),
+        OperatorExpression::Comparison { op, .. } => TokenKind::from(*op),
+        OperatorExpression::CompoundAssignment { op, .. } => TokenKind::from(*op),
+        OperatorExpression::Boolean { op, .. } => TokenKind::from(*op),
+        OperatorExpression::Negation { op, .. } => TokenKind::from(*op),
+        OperatorExpression::Assignment { .. } => TokenKind::Eq,
+        OperatorExpression::AddressOf { .. } => TokenKind::And,
+        OperatorExpression::Dereference { .. } => TokenKind::Star,
+        OperatorExpression::Increment { .. } => TokenKind::PlusPlus,
+        OperatorExpression::Decrement { .. } => TokenKind::MinusMinus,
+    }
+}
+
+// Binding power of an expression ending in the postfix operator `op`.
+fn postfix(op: TokenKind) -> (u8, u8) {
+    (postfix_binding_power(op).unwrap().0, ATOM)
+}
+
+// Gets the left and right binding power of the outermost operator in
+// `expr`, mirroring the tables in the parser.
+fn binding_power(expr: &Expression) -> (u8, u8) {
+    match &expr.kind {
+        ExpressionKind::Operator(op) => {
+            let token = operator_token(op);
+            match op {
+                OperatorExpression::Negation { .. }
+                | OperatorExpression::AddressOf { .. }
+                | OperatorExpression::Dereference { .. } => (ATOM, prefix_binding_power(token).1),
+                OperatorExpression::Increment { .. } | OperatorExpression::Decrement { .. } => {
+                    postfix(token)
+                }
+                _ => infix_binding_power(token).unwrap(),
+            }
+        }
+        ExpressionKind::Range {
+            start, inclusive, ..
+        } => {
+            let token = if *inclusive {
+                TokenKind::DotDotEq
+            } else {
+                TokenKind::DotDot
+            };
+            let (lbp, rbp) = infix_binding_power(token).unwrap();
+            // Without a start, a range is parsed like a prefix operator.
+            // Without an end, it still keeps what follows out of it.
+            (if start.is_some() { lbp } else { ATOM }, rbp)
+        }
+        ExpressionKind::Index { .. } => postfix(TokenKind::LeftBracket),
+        ExpressionKind::Call { .. } => postfix(TokenKind::LeftParen),
+        ExpressionKind::Field { .. } | ExpressionKind::MethodCall { .. } => postfix(TokenKind::Dot),
+        ExpressionKind::Cast { .. } => postfix(TokenKind::As),
+        // A value after `return` or `break` extends as far as possible,
+        // just like the right-hand side of an assignment.
+        ExpressionKind::Return(Some(_)) | ExpressionKind::Break { value: Some(_), .. } => (ATOM, 0),
+        _ => (ATOM, ATOM),
+    }
+}
+
+// Gets the operands of `expr` that are printed without delimiters
+// around them, as `(lhs, rhs)`.
+fn operands(expr: &Expression) -> (Option<ExprId>, Option<ExprId>) {
+    match &expr.kind {
+        ExpressionKind::Operator(op) => match op {
+            OperatorExpression::ArithmeticLogical { lhs, rhs, .. }
+            | OperatorExpression::Comparison { lhs, rhs, .. }
+            | OperatorExpression::CompoundAssignment { lhs, rhs, .. }
+            | OperatorExpression::Boolean { lhs, rhs, .. }
+            | OperatorExpression::Assignment { lhs, rhs } => (Some(*lhs), Some(*rhs)),
+            OperatorExpression::Negation { expr, .. }
+            | OperatorExpression::AddressOf { expr }
+            | OperatorExpression::Dereference { expr } => (None, Some(*expr)),
+            OperatorExpression::Increment { expr } | OperatorExpression::Decrement { expr } => {
+                (Some(*expr), None)
+            }
+        },
+        ExpressionKind::Range { start, end, .. } => (*start, *end),
+        ExpressionKind::Index { cont: lhs, .. }
+        | ExpressionKind::Call { func: lhs, .. }
+        | ExpressionKind::Field { expr: lhs, .. }
+        | ExpressionKind::MethodCall { receiver: lhs, .. }
+        | ExpressionKind::Cast { expr: lhs, .. } => (Some(*lhs), None),
+        ExpressionKind::Return(Some(value))
+        | ExpressionKind::Break {
+            value: Some(value), ..
+        } => (None, Some(*value)),
+        _ => (None, None),
+    }
+}
+
+// Whether `child` needs parentheses as the left operand of `parent`.
+//
+// Besides precedence, a `<` right after the type of a cast would start
+// its generic arguments, so `(a as u8) < b` keeps its parentheses.
+fn needs_parens_left(exprs: &Exprs, parent: &Expression, child: &Expression) -> bool {
+    binding_power(parent).0 >= right_binding_power(exprs, child)
+        || (starts_with_lt(parent) && ends_in_cast(exprs, child))
+        || bitwise_in_comparison(parent, child)
+}
+
+// Whether `child` is a bitwise operation that is an operand of the
+// comparison `parent`. It binds tighter, but without parentheses it is
+// easily misread, which the precedence lint warns about.
+fn bitwise_in_comparison(parent: &Expression, child: &Expression) -> bool {
+    use ArithmeticLogicalOperator::*;
+    matches!(
+        parent.kind,
+        ExpressionKind::Operator(OperatorExpression::Comparison { .. })
+    ) && matches!(
+        child.kind,
+        ExpressionKind::Operator(OperatorExpression::ArithmeticLogical {
+            op: And | Or | Xor,
+            ..
+        })
+    )
+}
+
+// Whether the operator of `expr` is spelled starting with a `<`.
+fn starts_with_lt(expr: &Expression) -> bool {
+    use TokenKind::*;
+    let ExpressionKind::Operator(op) = &expr.kind else {
+        return false;
+    };
+    matches!(operator_token(op), Lt | LtEq | Shl | ShlEq)
+}
+
+// Whether `expr` is printed ending in the type of a cast.
+fn ends_in_cast(exprs: &Exprs, expr: &Expression) -> bool {
+    if let ExpressionKind::Cast { .. } = expr.kind {
+        return true;
+    }
+    match operands(expr).1.map(|rhs| &exprs[rhs]) {
+        Some(rhs) if !needs_parens_right(expr, rhs) => ends_in_cast(exprs, rhs),
+        _ => false,
+    }
+}
+
+// Whether `child` needs parentheses as the right operand of `parent`.
+fn needs_parens_right(parent: &Expression, child: &Expression) -> bool {
+    binding_power(child).0 < binding_power(parent).1 || bitwise_in_comparison(parent, child)
+}
+
+// Gets the lowest right binding power along the right edge of `expr`.
+// An operator after `expr` would be pulled into the operand with that
+// binding power, not just into `expr` itself.
+fn right_binding_power(exprs: &Exprs, expr: &Expression) -> u8 {
+    let rbp = binding_power(expr).1;
+    match operands(expr).1.map(|rhs| &exprs[rhs]) {
+        Some(rhs) if !needs_parens_right(expr, rhs) => rbp.min(right_binding_power(exprs, rhs)),
+        _ => rbp,
+    }
+}
+
+// Collects the spans of the blocks, structs and modules within a node,
+// which print the comments inside of them themselves.
+#[derive(Default)]
+struct Nested(Vec<SourceSpan>);
+
+impl Nested {
+    fn item(exprs: &Exprs, item: &Item) -> Vec<SourceSpan> {
+        let mut nested = Self::default();
+        visit::walk_item(&mut nested, exprs, item);
+        if let ItemKind::Struct(_) | ItemKind::Module(_) = item.kind {
+            nested.0.push(item.span);
+        }
+        nested.0
+    }
+
+    fn stmt(exprs: &Exprs, stmt: &Statement) -> Vec<SourceSpan> {
+        let mut nested = Self::default();
+        nested.visit_stmt(exprs, stmt);
+        nested.0
+    }
+
+    fn expr(exprs: &Exprs, expr: ExprId) -> Vec<SourceSpan> {
+        let mut nested = Self::default();
+        nested.visit_expr(exprs, expr);
+        nested.0
+    }
+}
+
+impl Visitor for Nested {
+    fn visit_item(&mut self, exprs: &Exprs, item: &Item) {
+        match item.kind {
+            ItemKind::Struct(_) | ItemKind::Module(_) => self.0.push(item.span),
+            _ => visit::walk_item(self, exprs, item),
+        }
+    }
+
+    fn visit_block(&mut self, _: &Exprs, block: &Block) {
+        self.0.push(block.span);
+    }
+}
+
+struct Printer<'src> {
+    source: &'src str,
+    exprs: &'src Exprs,
+    out: String,
+    indent: usize,
+
+    // Mirrors the parser's restriction on struct literals in front of
+    // a block, which need parentheses there.
+    no_struct_literal: bool,
+
+    // Set when formatting, which keeps the spelling of literals and
+    // tracks where lines end in the source.
+    formatting: bool,
+    // The comments that were not printed yet.
+    comments: VecDeque<SourceSpan>,
+    // Where the last line printed ends in the source, unless a block
+    // was just opened.
+    last_end: Option<u32>,
+    // Forces a blank line in front of the next line.
+    blank_line: bool,
+}
+
+impl<'src> Printer<'src> {
+    fn new(
+        source: &'src str,
+        exprs: &'src Exprs,
+        comments: &[SourceSpan],
+        formatting: bool,
+    ) -> Self {
+        Self {
+            source,
+            exprs,
+            out: String::new(),
+            indent: 0,
+            no_struct_literal: false,
+            formatting,
+            comments: comments.iter().copied().collect(),
+            last_end: None,
+            blank_line: false,
+        }
+    }
+
+    fn ident(&mut self, ident: &Ident) {
+        self.out.push_str(&self.source[ident.span]);
+    }
+
+    // Prints the label in front of a block or loop, if it has one.
+    fn label(&mut self, label: Option<&Ident>) {
+        if let Some(label) = label {
+            self.ident(label);
+            self.out.push_str(": ");
+        }
+    }
+
+    fn pattern(&mut self, pattern: &Pattern) {
+        match pattern {
+            Pattern::Ident(ident) => self.ident(ident),
+            Pattern::Wildcard(_) => self.out.push('_'),
+        }
+    }
+
+    fn newline(&mut self) {
+        self.out.push('\n');
+        for _ in 0..self.indent {
+            self.out.push_str("    ");
+        }
+    }
+
+    // Starts the line for something at `pos` in the source. A single
+    // blank line is kept in front of it if the source had any.
+    fn line(&mut self, pos: u32) {
+        let blank_line = self
+            .last_end
+            .filter(|&end| self.formatting && end <= pos)
+            .is_some_and(|end| {
+                self.source[end as usize..pos as usize]
+                    .matches('\n')
+                    .count()
+                    > 1
+            });
+        if mem::take(&mut self.blank_line) || blank_line {
+            self.out.push('\n');
+        }
+        // Nothing goes in front of the first item.
+        if !self.out.is_empty() {
+            self.newline();
+        }
+    }
+
+    // Prints the comments that start before `pos` on lines of their own.
+    fn leading_comments(&mut self, pos: u32) {
+        while let Some(&comment) = self.comments.front()
+            && comment.start().offset() < pos
+        {
+            self.comments.pop_front();
+            self.line(comment.start().offset());
+            self.out.push_str(&self.source[comment]);
+            self.last_end = Some(comment.end().offset());
+        }
+    }
+
+    // Prints the comments within `span` on lines of their own, in front
+    // of what it covers. The comments inside of `nested` are left to the
+    // blocks, structs and modules that print them.
+    fn inner_comments(&mut self, span: SourceSpan, nested: &[SourceSpan]) {
+        let mut i = 0;
+        while let Some(&comment) = self.comments.get(i)
+            && comment.start().offset() < span.end().offset()
+        {
+            if nested.iter().any(|outer| outer.contains_span(comment)) {
+                i += 1;
+                continue;
+            }
+            self.comments.remove(i);
+            // Blank lines within the span are dropped along with its
+            // line breaks.
+            self.line(span.start().offset());
+            self.out.push_str(&self.source[comment]);
+            self.last_end = Some(comment.end().offset());
+        }
+    }
+
+    // Finishes the line of something that ends at `end` in the source,
+    // keeping a comment that follows it on the same line.
+    fn end_line(&mut self, end: u32) {
+        self.last_end = Some(end);
+        if let Some(&comment) = self.comments.front()
+            && comment.start().offset() >= end
+            && !self.source[end as usize..comment.start().offset() as usize].contains('\n')
+        {
+            self.comments.pop_front();
+            self.out.push(' ');
+            self.out.push_str(&self.source[comment]);
+            self.last_end = Some(comment.end().offset());
+        }
+    }
+
+    fn list<T>(&mut self, elems: &[T], mut f: impl FnMut(&mut Self, &T)) {
+        for (i, elem) in elems.iter().enumerate() {
+            if i > 0 {
+                self.out.push_str(", ");
+            }
+            f(self, elem);
+        }
+    }
+
+    fn typ(&mut self, typ: &TypeNode) {
+        match typ {
+            TypeNode::Named { name, args } => {
+                self.ident(name);
+                if !args.is_empty() {
+                    self.out.push('<');
+                    self.list(args, |p, typ| p.typ(typ));
+                    self.out.push('>');
+                }
+            }
+            TypeNode::Reference(inner) => {
+                self.out.push('&');
+                self.typ(inner);
+            }
+            TypeNode::Pointer(inner) => {
+                self.out.push('*');
+                self.typ(inner);
+            }
+            TypeNode::Slice(inner) => {
+                self.out.push('[');
+                self.typ(inner);
+                self.out.push(']');
+            }
+            TypeNode::Array { elem, len } => {
+                self.out.push('[');
+                self.typ(elem);
+                self.out.push_str("; ");
+                self.nested(*len);
+                self.out.push(']');
+            }
+            TypeNode::Tuple(elems) => {
+                self.out.push('(');
+                self.list(elems, |p, typ| p.typ(typ));
+                if elems.len() == 1 {
+                    self.out.push(',');
+                }
+                self.out.push(')');
+            }
+        }
+    }
+
+    fn visibility(&mut self, visibility: Visibility) {
+        if visibility == Visibility::Public {
+            self.out.push_str("pub ");
+        }
+    }
+
+    fn item(&mut self, item: &Item) {
+        // Doc comments are among the comments that formatting prints.
+        if let Some(docs) = item.docs.as_deref().filter(|_| !self.formatting) {
+            for line in docs.split('\n') {
+                self.out.push_str("///");
+                if !line.is_empty() {
+                    self.out.push(' ');
+                    self.out.push_str(line);
+                }
+                self.newline();
+            }
+        }
+        for attr in &item.attrs {
+            self.attribute(attr);
+            self.newline();
+        }
+        match &item.kind {
+            ItemKind::Function(function) => self.function(function),
+            ItemKind::Struct(s) => self.struct_decl(s, item.span),
+            ItemKind::Const(c) => self.const_decl(c),
+            ItemKind::TypeAlias(alias) => self.type_alias(alias),
+            ItemKind::Module(module) => self.module(module, item.span),
+        }
+    }
+
+    fn attribute(&mut self, attr: &Attribute) {
+        self.out.push_str("#[");
+        self.ident(&attr.name);
+        if let Some(args) = &attr.args {
+            self.out.push('(');
+            self.list(args, |p, &arg| p.nested(arg));
+            self.out.push(')');
+        }
+        self.out.push(']');
+    }
+
+    fn function(&mut self, function: &Function) {
+        self.visibility(function.visibility);
+        self.out.push_str("fn ");
+        self.ident(&function.name);
+        self.generics(&function.generics);
+        self.out.push('(');
+        self.list(&function.args, |p, arg| {
+            p.pattern(&arg.pattern);
+            p.out.push_str(": ");
+            p.typ(&arg.typ);
+        });
+        self.out.push(')');
+        if let Some(ret) = &function.ret {
+            self.out.push_str(" -> ");
+            self.typ(ret);
+        }
+        self.out.push(' ');
+        self.block(&function.block);
+    }
+
+    fn struct_decl(&mut self, s: &Struct, span: SourceSpan) {
+        self.visibility(s.visibility);
+        self.out.push_str("struct ");
+        self.ident(&s.name);
+        // The closing brace is the last token of the item.
+        let close = span.end().offset().saturating_sub(1);
+        match &s.fields {
+            StructFields::Named(fields) if fields.is_empty() && !self.comment_before(close) => {
+                self.out.push_str(" {}")
+            }
+            StructFields::Named(fields) => {
+                self.out.push_str(" {");
+                self.indent += 1;
+                self.last_end = None;
+                for field in fields {
+                    let start = field.name.span.start().offset();
+                    self.leading_comments(start);
+                    self.line(start);
+                    self.ident(&field.name);
+                    self.out.push_str(": ");
+                    self.typ(&field.typ);
+                    self.out.push(',');
+                    // Types have no span, but rarely span several lines.
+                    self.end_line(field.name.span.end().offset());
+                }
+                self.leading_comments(close);
+                self.indent -= 1;
+                self.newline();
+                self.out.push('}');
+            }
+            StructFields::Tuple(types) => {
+                self.out.push('(');
+                self.list(types, |p, typ| p.typ(typ));
+                self.out.push_str(");");
+            }
+        }
+    }
+
+    fn const_decl(&mut self, c: &Const) {
+        self.visibility(c.visibility);
+        self.out.push_str("const ");
+        self.ident(&c.name);
+        if let Some(typ) = &c.typ {
+            self.out.push_str(": ");
+            self.typ(typ);
+        }
+        if let Some(expr) = c.expr {
+            self.out.push_str(" = ");
+            self.expr(expr);
+        }
+        self.out.push(';');
+    }
+
+    fn type_alias(&mut self, alias: &TypeAlias) {
+        self.visibility(alias.visibility);
+        self.out.push_str("type ");
+        self.ident(&alias.name);
+        self.generics(&alias.generics);
+        self.out.push_str(" = ");
+        self.typ(&alias.typ);
+        self.out.push(';');
+    }
+
+    fn module(&mut self, module: &Module, span: SourceSpan) {
+        self.visibility(module.visibility);
+        self.out.push_str("mod ");
+        self.ident(&module.name);
+        let Some(items) = &module.items else {
+            self.out.push(';');
+            return;
+        };
+
+        // The closing brace is the last token of the item.
+        let close = span.end().offset().saturating_sub(1);
+        if items.is_empty() && !self.comment_before(close) {
+            self.out.push_str(" {}");
+            return;
+        }
+
+        self.out.push_str(" {");
+        self.indent += 1;
+        self.last_end = None;
+        for item in items {
+            let start = item.span.start().offset();
+            self.leading_comments(start);
+            self.inner_comments(item.span, &Nested::item(self.exprs, item));
+            self.line(start);
+            self.item(item);
+            self.end_line(item.span.end().offset());
+            // Items are separated by a blank line, like at the top level.
+            self.blank_line = true;
+        }
+        self.leading_comments(close);
+        self.blank_line = false;
+        self.indent -= 1;
+        self.newline();
+        self.out.push('}');
+    }
+
+    fn generics(&mut self, generics: &[Ident]) {
+        if !generics.is_empty() {
+            self.out.push('<');
+            self.list(generics, Self::ident);
+            self.out.push('>');
+        }
+    }
+
+    fn stmt(&mut self, stmt: &Statement) {
+        match &stmt.node {
+            StatementKind::Item(item) => self.item(item),
+            StatementKind::Variable {
+                pattern,
+                expr,
+                mutable,
+            } => {
+                self.out.push_str(if *mutable { "mut " } else { "let " });
+                self.pattern(pattern);
+                self.out.push_str(" = ");
+                self.expr(*expr);
+                self.out.push(';');
+            }
+            StatementKind::Expression(expr) => {
+                self.expr(*expr);
+                if !self.exprs[*expr].is_block_like() {
+                    self.out.push(';');
+                }
+            }
+            StatementKind::For {
+                label,
+                binding,
+                iter,
+                body,
+            } => {
+                self.label(label.as_ref());
+                self.out.push_str("for ");
+                self.pattern(binding);
+                self.out.push_str(" in ");
+                self.condition(*iter);
+                self.out.push(' ');
+                self.block(body);
+            }
+        }
+    }
+
+    // Whether there is a comment left to print that starts before `pos`.
+    fn comment_before(&self, pos: u32) -> bool {
+        self.comments
+            .front()
+            .is_some_and(|comment| comment.start().offset() < pos)
+    }
+
+    fn block(&mut self, block: &Block) {
+        let close = block.span.end().offset().saturating_sub(1);
+        if block.stmts.is_empty() && block.tail.is_none() && !self.comment_before(close) {
+            self.out.push_str("{}");
+            return;
+        }
+
+        let outer = mem::replace(&mut self.no_struct_literal, false);
+        self.out.push('{');
+        self.indent += 1;
+        self.last_end = None;
+        for stmt in &block.stmts {
+            let start = stmt.span.start().offset();
+            self.leading_comments(start);
+            self.inner_comments(stmt.span, &Nested::stmt(self.exprs, stmt));
+            self.line(start);
+            self.stmt(stmt);
+            self.end_line(stmt.span.end().offset());
+        }
+        if let Some(tail) = block.tail {
+            let span = self.exprs[tail].span;
+            self.leading_comments(span.start().offset());
+            self.inner_comments(span, &Nested::expr(self.exprs, tail));
+            self.line(span.start().offset());
+            self.expr(tail);
+            self.end_line(span.end().offset());
+        }
+        self.leading_comments(close);
+        self.indent -= 1;
+        self.newline();
+        self.out.push('}');
+        self.no_struct_literal = outer;
+    }
+
+    fn condition(&mut self, expr: ExprId) {
+        let outer = mem::replace(&mut self.no_struct_literal, true);
+        self.expr(expr);
+        self.no_struct_literal = outer;
+    }
+
+    // Prints an expression inside of delimiters.
+    fn nested(&mut self, expr: ExprId) {
+        let outer = mem::replace(&mut self.no_struct_literal, false);
+        self.expr(expr);
+        self.no_struct_literal = outer;
+    }
+
+    fn parenthesized(&mut self, expr: ExprId, parens: bool) {
+        if parens {
+            self.out.push('(');
+            self.nested(expr);
+            self.out.push(')');
+        } else {
+            self.expr(expr);
+        }
+    }
+
+    fn lhs(&mut self, parent: &Expression, child: ExprId) {
+        let parens = needs_parens_left(self.exprs, parent, &self.exprs[child]);
+        self.parenthesized(child, parens);
+    }
+
+    fn rhs(&mut self, parent: &Expression, child: ExprId) {
+        self.parenthesized(child, needs_parens_right(parent, &self.exprs[child]));
+    }
+
+    fn expr(&mut self, id: ExprId) {
+        let expr = &self.exprs[id];
+        match &expr.kind {
+            ExpressionKind::Ident(ident) => self.ident(ident),
+            ExpressionKind::Array(elems) => {
+                self.out.push('[');
+                self.list(elems, |p, &elem| p.nested(elem));
+                self.out.push(']');
+            }
+            ExpressionKind::ArrayRepeat { value, len } => {
+                self.out.push('[');
+                self.nested(*value);
+                self.out.push_str("; ");
+                self.nested(*len);
+                self.out.push(']');
+            }
+            ExpressionKind::Block { label, block } => {
+                self.label(label.as_ref());
+                self.block(block);
+            }
+            ExpressionKind::Index { cont, idx } => {
+                self.lhs(expr, *cont);
+                self.out.push('[');
+                self.list(idx, |p, &idx| p.nested(idx));
+                self.out.push(']');
+            }
+            ExpressionKind::Call { func, params } => {
+                self.lhs(expr, *func);
+                self.out.push('(');
+                self.list(params, |p, &param| p.nested(param));
+                self.out.push(')');
+            }
+            ExpressionKind::Field { expr: value, name } => {
+                self.lhs(expr, *value);
+                self.out.push('.');
+                self.ident(name);
+            }
+            ExpressionKind::MethodCall {
+                receiver,
+                method,
+                args,
+            } => {
+                self.lhs(expr, *receiver);
+                self.out.push('.');
+                self.ident(method);
+                self.out.push('(');
+                self.list(args, |p, &arg| p.nested(arg));
+                self.out.push(')');
+            }
+            ExpressionKind::Cast { expr: value, typ } => {
+                self.lhs(expr, *value);
+                self.out.push_str(" as ");
+                self.typ(typ);
+            }
+            ExpressionKind::If {
+                cond,
+                then_block,
+                else_block,
+            } => {
+                self.out.push_str("if ");
+                self.condition(*cond);
+                self.out.push(' ');
+                self.block(then_block);
+                if let Some(else_block) = else_block {
+                    self.out.push_str(" else ");
+                    self.expr(*else_block);
+                }
+            }
+            // `()` is always printed the same, even with space within.
+            ExpressionKind::Literal(literal) if self.formatting && *literal != Literal::Unit => {
+                self.out.push_str(&self.source[expr.span]);
+            }
+            ExpressionKind::Literal(literal) => self.literal(literal),
+            ExpressionKind::Loop { label, body } => {
+                self.label(label.as_ref());
+                self.out.push_str("loop ");
+                self.block(body);
+            }
+            ExpressionKind::Operator(op) => self.operator(expr, op),
+            ExpressionKind::Range {
+                start,
+                end,
+                inclusive,
+            } => {
+                if let Some(start) = start {
+                    self.lhs(expr, *start);
+                }
+                self.out.push_str(if *inclusive { "..=" } else { ".." });
+                if let Some(end) = end {
+                    self.rhs(expr, *end);
+                }
+            }
+            ExpressionKind::StructLiteral { name, fields } => {
+                let parens = self.no_struct_literal;
+                if parens {
+                    self.out.push('(');
+                }
+                self.ident(name);
+                self.out.push_str(" { ");
+                self.list(fields, |p, field| {
+                    p.ident(&field.name);
+                    p.out.push_str(": ");
+                    p.nested(field.expr);
+                });
+                self.out
+                    .push_str(if fields.is_empty() { "}" } else { " }" });
+                if parens {
+                    self.out.push(')');
+                }
+            }
+            ExpressionKind::Tuple(elems) => {
+                self.out.push('(');
+                self.list(elems, |p, &elem| p.nested(elem));
+                if elems.len() == 1 {
+                    self.out.push(',');
+                }
+                self.out.push(')');
+            }
+            ExpressionKind::Return(value) => {
+                self.out.push_str("return");
+                if let Some(value) = value {
+                    self.out.push(' ');
+                    self.rhs(expr, *value);
+                }
+            }
+            ExpressionKind::Break { label, value } => {
+                self.out.push_str("break");
+                if let Some(label) = label {
+                    self.out.push(' ');
+                    self.ident(label);
+                }
+                if let Some(value) = value {
+                    self.out.push(' ');
+                    self.rhs(expr, *value);
+                }
+            }
+            ExpressionKind::Continue { label } => {
+                self.out.push_str("continue");
+                if let Some(label) = label {
+                    self.out.push(' ');
+                    self.ident(label);
+                }
+            }
+            ExpressionKind::Error => self.out.push_str("<error>"),
+        }
+    }
+
+    fn operator(&mut self, expr: &Expression, op: &OperatorExpression) {
+        let spelling = operator_token(op).as_str();
+        match operands(expr) {
+            (Some(lhs), Some(rhs)) => {
+                self.lhs(expr, lhs);
+                self.out.push(' ');
+                self.out.push_str(spelling);
+                self.out.push(' ');
+                self.rhs(expr, rhs);
+            }
+            (None, Some(operand)) => {
+                self.out.push_str(spelling);
+                // Keep `- -a` and `& &a` from being lexed as `--` and `&&`.
+                let start = self.out.len();
+                self.rhs(expr, operand);
+                if self.out[start..].starts_with(spelling) {
+                    self.out.insert(start, ' ');
+                }
+            }
+            (Some(operand), None) => {
+                self.lhs(expr, operand);
+                self.out.push_str(spelling);
+            }
+            (None, None) => unreachable!(),
+        }
+    }
+
+    fn literal(&mut self, literal: &Literal) {
+        match literal {
+            Literal::Int { value, typ } => {
+                self.out.push_str(&value.to_string());
+                if let Some(typ) = typ {
+                    self.out.push_str(typ.as_str());
+                }
+            }
+            // The `Debug` output always keeps a fraction or an exponent,
+            // so the literal is not read back as an integer.
+            Literal::Float(value) => self.out.push_str(&format!("{value:?}")),
+            Literal::Bool(value) => self.out.push_str(if *value { "true" } else { "false" }),
+            Literal::Str(text) => {
+                self.out.push('"');
+                self.out.push_str(&escape_string(text));
+                self.out.push('"');
+            }
+            Literal::Char(c) => {
+                self.out.push('\'');
+                match c {
+                    '\'' => self.out.push_str("\\'"),
+                    c => self
+                        .out
+                        .push_str(&escape_string(c.encode_utf8(&mut [0; 4]))),
+                }
+                self.out.push('\'');
+            }
+            Literal::Unit => self.out.push_str("()"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Lexer, Parser, ast::eq::ast_eq};
+
+    // Parses `source`, prints it and parses the output again, which must
+    // give the same tree. Returns the printed source.
+    fn round_trip(source: &str) -> String {
+        let (ast, diagnostics) = Parser::new(source).parse();
+        assert!(diagnostics.is_empty(), "{source}: {diagnostics:?}");
+
+        let printed = pretty_print(source, &ast);
+        let (reparsed, diagnostics) = Parser::new(&printed).parse();
+        assert!(diagnostics.is_empty(), "{printed}: {diagnostics:?}");
+        assert!(
+            ast_eq(&ast, source, &reparsed, &printed),
+            "{source}\n=>\n{printed}"
+        );
+        printed
+    }
+
+    // Formats `source` the way `serqlane fmt` does.
+    fn format_source(source: &str) -> String {
+        let (ast, diagnostics) = Parser::new(source).parse();
+        assert!(diagnostics.is_empty(), "{source}: {diagnostics:?}");
+
+        let mut lexer = Lexer::new(source);
+        lexer.by_ref().for_each(drop);
+        format(source, &ast, lexer.comments())
+    }
+
+    #[test]
+    fn keeps_needed_parentheses() {
+        let printed = round_trip(
+            "fn f() {
+                let a = (a + b) * c - (d - e)
+                let b = 2 ** 3 ** 2 + (2 ** 3) ** 2
+                let c = -(a.b) + (-a).b + *(a[0]) + (*a)[0]
+                let d = (a = b) == (c < d)
+                let e = (a..b).len() + (..n).len()
+                let f = (a as u8) < b
+                let g = (a + b as u8) < c
+                let h = (a as u8) << 2 <= (b as u16)
+                let i = (a as u8) > b
+            }",
+        );
+
+        for expected in [
+            "(a + b) * c - (d - e)",
+            "2 ** 3 ** 2 + (2 ** 3) ** 2",
+            "-a.b + (-a).b + *a[0] + (*a)[0]",
+            "(a = b) == (c < d)",
+            "(a as u8) < b",
+            "(a + b as u8) < c",
+            "(a as u8) << 2 <= b as u16",
+            "a as u8 > b",
+        ] {
+            assert!(printed.contains(expected), "{expected} not in\n{printed}");
+        }
+    }
+
+    #[test]
+    fn round_trips_items() {
+        round_trip(
+            "/// Docs.
+            ///
+            ///  Indented.
+            #[inline]
+            pub fn f<T>(a: u8, _: T) -> u8 {
+                'b: {
+                    if a > 0 { break 'b a }
+                    for _ in 0..=a { a += 1 }
+                    'l: loop { 'f: for i in a { continue 'l; break 'f } }
+                    return Point { x: \"a\\\"b\\n\", y: 'c' }
+                }
+            }
+            struct Point { x: str, y: char }
+            struct Pair(u8, u8)
+            const N: u64 = 1000
+            type Bytes = [u8]
+            mod m { fn g() {} }",
+        );
+    }
+
+    #[test]
+    fn formats_canonically() {
+        let source = "\
+// Leading comment.
+pub   fn  main( a:u8,b :  u8 )->bool{
+  let x=(a+b)*2;let y = ( a as Vec ) < b
+     x  =x<<1 // trailing
+      if x>1{return  true}else  {  y  }
+
+
+  /* block */ mut z=[ 1,2 , 3 ]
+}
+struct  Point{x:u8,y:u8}
+";
+        let expected = "\
+// Leading comment.
+pub fn main(a: u8, b: u8) -> bool {
+    let x = (a + b) * 2;
+    let y = (a as Vec) < b;
+    x = x << 1; // trailing
+    if x > 1 {
+        return true
+    } else {
+        y
+    }
+
+    /* block */
+    mut z = [1, 2, 3];
+}
+
+struct Point {
+    x: u8,
+    y: u8,
+}
+";
+        let formatted = format_source(source);
+        assert_eq!(formatted, expected);
+        assert_eq!(format_source(&formatted), formatted);
+    }
+
+    #[test]
+    fn comments_within_statements() {
+        let source = "\
+fn f() {
+    let x = 1 + /* inside */ 2
+    g(
+        1, // one
+        2,
+    )
+
+    if x > 1 {
+        // kept in the block
+        g(3)
+    }
+}
+";
+        let expected = "\
+fn f() {
+    /* inside */
+    let x = 1 + 2;
+    // one
+    g(1, 2);
+
+    if x > 1 {
+        // kept in the block
+        g(3)
+    }
+}
+";
+        let formatted = format_source(source);
+        assert_eq!(formatted, expected);
+        assert_eq!(format_source(&formatted), formatted);
+    }
+}
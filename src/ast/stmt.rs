@@ -1,28 +1,111 @@
 //! Defines structures for describing statements.
 
-use super::{Ident, Item, expr::Expression};
+use super::{Ident, Item, Pattern, Spanned, TypeNode, Visibility, expr::ExprId};
+use crate::diagnostic::SourceSpan;
+
+// Its span covers the whole statement, excluding the `;` that ends it.
+pub type Statement = Spanned<StatementKind>;
 
 #[derive(Clone, Debug)]
-pub enum Statement {
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub enum StatementKind {
     Item(Item),
     Variable {
-        ident: Ident,
-        expr: Expression,
+        pattern: Pattern,
+        expr: ExprId,
         mutable: bool,
     },
-    Expression(Expression),
+    Expression(ExprId),
+    // `for x in iter { ... }`, or `'a: for ...` with a label.
+    For {
+        label: Option<Ident>,
+        binding: Pattern,
+        iter: ExprId,
+        body: Block,
+    },
 }
 
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Block {
+    pub stmts: Box<[Statement]>,
+    // The trailing expression that gives the block its value, if any.
+    pub tail: Option<ExprId>,
+    // Covers the braces and everything between them. Empty where the
+    // block is missing.
+    pub span: SourceSpan,
+}
+
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct Function {
+    pub visibility: Visibility,
     pub name: Ident,
+    // The type parameters in `fn name<T, U>(...)`, if any.
+    pub generics: Box<[Ident]>,
     pub args: Box<[FunctionArg]>,
-    pub ret: Option<Ident>,
-    pub block: Box<[Statement]>,
+    pub ret: Option<Spanned<TypeNode>>,
+    pub block: Block,
 }
 
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct FunctionArg {
+    pub pattern: Pattern,
+    pub typ: Spanned<TypeNode>,
+}
+
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Struct {
+    pub visibility: Visibility,
+    pub name: Ident,
+    pub fields: StructFields,
+}
+
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub enum StructFields {
+    // `struct Point { x: int, y: int }`
+    Named(Box<[StructField]>),
+    // `struct Pair(int, int)`
+    Tuple(Box<[Spanned<TypeNode>]>),
+}
+
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct StructField {
+    pub name: Ident,
+    pub typ: Spanned<TypeNode>,
+}
+
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Const {
+    pub visibility: Visibility,
+    pub name: Ident,
+    pub typ: Option<Spanned<TypeNode>>,
+    // This is only `None` when the initializer is missing, which is
+    // reported as an error during parsing.
+    pub expr: Option<ExprId>,
+}
+
+// `mod name { ... }`, or `mod name;` for a module in another file.
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Module {
+    pub visibility: Visibility,
+    pub name: Ident,
+    // The items of an inline module, or `None` for `mod name;`.
+    pub items: Option<Box<[Item]>>,
+}
+
+// `type Pair<T> = (T, T)`
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct TypeAlias {
+    pub visibility: Visibility,
     pub name: Ident,
-    pub typ: Ident,
+    pub generics: Box<[Ident]>,
+    pub typ: Spanned<TypeNode>,
 }
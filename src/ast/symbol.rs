@@ -0,0 +1,55 @@
+//! Defines the interning of identifiers into [`Symbol`]s.
+
+use std::{collections::HashMap, rc::Rc};
+
+/// An interned string, as handed out by an [`Interner`].
+///
+/// Two symbols from the same interner are equal exactly when the
+/// strings they stand for are, which makes comparing and hashing them
+/// as cheap as for any `u32`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Symbol(u32);
+
+/// Stores every distinct string it is given exactly once and hands out
+/// a [`Symbol`] for each of them.
+#[derive(Clone, Debug, Default)]
+pub struct Interner {
+    symbols: HashMap<Rc<str>, Symbol>,
+    strings: Vec<Rc<str>>,
+}
+
+impl Interner {
+    /// Creates a new, empty [`Interner`].
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Gets the [`Symbol`] for a string, interning it on first use.
+    pub fn intern(&mut self, string: &str) -> Symbol {
+        if let Some(&symbol) = self.symbols.get(string) {
+            return symbol;
+        }
+
+        let symbol = Symbol(self.strings.len() as u32);
+        let string: Rc<str> = Rc::from(string);
+        self.strings.push(Rc::clone(&string));
+        self.symbols.insert(string, symbol);
+        symbol
+    }
+
+    /// Gets the string a [`Symbol`] from this interner stands for.
+    pub fn resolve(&self, symbol: Symbol) -> &str {
+        &self.strings[symbol.0 as usize]
+    }
+
+    /// Gets the number of distinct strings interned so far.
+    pub fn len(&self) -> usize {
+        self.strings.len()
+    }
+
+    /// Whether no string has been interned yet.
+    pub fn is_empty(&self) -> bool {
+        self.strings.is_empty()
+    }
+}
@@ -0,0 +1,61 @@
+//! Defines the types that type checking assigns to expressions.
+
+use std::fmt;
+
+use super::expr::IntType;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub enum Type {
+    Int(IntType),
+    // An integer literal without a suffix, which fits any integer type.
+    IntLiteral,
+    Float,
+    Bool,
+    Str,
+    Char,
+    Unit,
+    // Any type that is not checked yet, such as that of a struct, and
+    // the type of expressions that failed to check. It is compatible
+    // with every other type, so it never causes follow-up errors.
+    Unknown,
+}
+
+impl Type {
+    pub fn is_integer(self) -> bool {
+        matches!(self, Self::Int(_) | Self::IntLiteral | Self::Unknown)
+    }
+
+    pub fn is_numeric(self) -> bool {
+        self.is_integer() || self == Self::Float
+    }
+
+    /// Combines two types that must be the same into one.
+    ///
+    /// An [`Type::IntLiteral`] takes on the other type if it is an
+    /// integer, and [`Type::Unknown`] takes on any type. Returns [`None`]
+    /// if the types are incompatible.
+    pub fn unify(self, other: Self) -> Option<Self> {
+        match (self, other) {
+            (a, b) if a == b => Some(a),
+            (Self::Unknown, t) | (t, Self::Unknown) => Some(t),
+            (Self::IntLiteral, t @ Self::Int(_)) | (t @ Self::Int(_), Self::IntLiteral) => Some(t),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Type {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Int(typ) => f.write_str(typ.as_str()),
+            Self::IntLiteral => f.write_str("{integer}"),
+            Self::Float => f.write_str("float"),
+            Self::Bool => f.write_str("bool"),
+            Self::Str => f.write_str("str"),
+            Self::Char => f.write_str("char"),
+            Self::Unit => f.write_str("()"),
+            Self::Unknown => f.write_str("_"),
+        }
+    }
+}
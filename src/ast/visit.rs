@@ -0,0 +1,332 @@
+//! Provides traversal of the AST.
+//!
+//! A pass implements [`Visitor`] (or [`VisitorMut`] to rewrite the tree
+//! in place) and overrides the methods for the nodes it is interested
+//! in. The default methods call the matching `walk_*` function, which
+//! visits all children of a node. An overriding method that wants to
+//! keep descending has to call the `walk_*` function itself.
+//!
+//! Expressions are passed by their id, along with the arena to look
+//! them up in.
+
+use super::{
+    Item, ItemKind,
+    expr::{ExprId, ExpressionKind, Exprs, OperatorExpression},
+    stmt::{Block, Statement, StatementKind},
+};
+
+pub trait Visitor: Sized {
+    fn visit_item(&mut self, exprs: &Exprs, item: &Item) {
+        walk_item(self, exprs, item);
+    }
+
+    fn visit_stmt(&mut self, exprs: &Exprs, stmt: &Statement) {
+        walk_stmt(self, exprs, stmt);
+    }
+
+    fn visit_block(&mut self, exprs: &Exprs, block: &Block) {
+        walk_block(self, exprs, block);
+    }
+
+    fn visit_expr(&mut self, exprs: &Exprs, expr: ExprId) {
+        walk_expr(self, exprs, expr);
+    }
+}
+
+pub fn walk_item<V: Visitor>(visitor: &mut V, exprs: &Exprs, item: &Item) {
+    match &item.kind {
+        ItemKind::Function(function) => visitor.visit_block(exprs, &function.block),
+        ItemKind::Struct(_) | ItemKind::TypeAlias(_) => {}
+        ItemKind::Const(constant) => {
+            if let Some(expr) = constant.expr {
+                visitor.visit_expr(exprs, expr);
+            }
+        }
+        ItemKind::Module(module) => {
+            for item in module.items.iter().flatten() {
+                visitor.visit_item(exprs, item);
+            }
+        }
+    }
+}
+
+pub fn walk_stmt<V: Visitor>(visitor: &mut V, exprs: &Exprs, stmt: &Statement) {
+    match &stmt.node {
+        StatementKind::Item(item) => visitor.visit_item(exprs, item),
+        StatementKind::Variable { expr, .. } | StatementKind::Expression(expr) => {
+            visitor.visit_expr(exprs, *expr);
+        }
+        StatementKind::For { iter, body, .. } => {
+            visitor.visit_expr(exprs, *iter);
+            visitor.visit_block(exprs, body);
+        }
+    }
+}
+
+pub fn walk_block<V: Visitor>(visitor: &mut V, exprs: &Exprs, block: &Block) {
+    for stmt in &block.stmts {
+        visitor.visit_stmt(exprs, stmt);
+    }
+    if let Some(tail) = block.tail {
+        visitor.visit_expr(exprs, tail);
+    }
+}
+
+pub fn walk_expr<V: Visitor>(visitor: &mut V, exprs: &Exprs, expr: ExprId) {
+    match &exprs[expr].kind {
+        ExpressionKind::Array(elems) | ExpressionKind::Tuple(elems) => {
+            for elem in elems {
+                visitor.visit_expr(exprs, *elem);
+            }
+        }
+        ExpressionKind::ArrayRepeat { value, len } => {
+            visitor.visit_expr(exprs, *value);
+            visitor.visit_expr(exprs, *len);
+        }
+        ExpressionKind::Block { block, .. } | ExpressionKind::Loop { body: block, .. } => {
+            visitor.visit_block(exprs, block);
+        }
+        ExpressionKind::Index { cont, idx } => {
+            visitor.visit_expr(exprs, *cont);
+            for idx in idx {
+                visitor.visit_expr(exprs, *idx);
+            }
+        }
+        ExpressionKind::Call { func, params } => {
+            visitor.visit_expr(exprs, *func);
+            for param in params {
+                visitor.visit_expr(exprs, *param);
+            }
+        }
+        ExpressionKind::Field { expr, .. } => visitor.visit_expr(exprs, *expr),
+        ExpressionKind::MethodCall { receiver, args, .. } => {
+            visitor.visit_expr(exprs, *receiver);
+            for arg in args {
+                visitor.visit_expr(exprs, *arg);
+            }
+        }
+        ExpressionKind::Cast { expr, .. } => visitor.visit_expr(exprs, *expr),
+        ExpressionKind::If {
+            cond,
+            then_block,
+            else_block,
+        } => {
+            visitor.visit_expr(exprs, *cond);
+            visitor.visit_block(exprs, then_block);
+            if let Some(else_block) = else_block {
+                visitor.visit_expr(exprs, *else_block);
+            }
+        }
+        ExpressionKind::Operator(op) => match op {
+            OperatorExpression::ArithmeticLogical { lhs, rhs, .. }
+            | OperatorExpression::Comparison { lhs, rhs, .. }
+            | OperatorExpression::CompoundAssignment { lhs, rhs, .. }
+            | OperatorExpression::Boolean { lhs, rhs, .. }
+            | OperatorExpression::Assignment { lhs, rhs } => {
+                visitor.visit_expr(exprs, *lhs);
+                visitor.visit_expr(exprs, *rhs);
+            }
+            OperatorExpression::Negation { expr, .. }
+            | OperatorExpression::AddressOf { expr }
+            | OperatorExpression::Dereference { expr }
+            | OperatorExpression::Increment { expr }
+            | OperatorExpression::Decrement { expr } => visitor.visit_expr(exprs, *expr),
+        },
+        ExpressionKind::Range { start, end, .. } => {
+            for bound in [start, end].into_iter().flatten() {
+                visitor.visit_expr(exprs, *bound);
+            }
+        }
+        ExpressionKind::StructLiteral { fields, .. } => {
+            for field in fields {
+                visitor.visit_expr(exprs, field.expr);
+            }
+        }
+        ExpressionKind::Return(value) | ExpressionKind::Break { value, .. } => {
+            if let Some(value) = value {
+                visitor.visit_expr(exprs, *value);
+            }
+        }
+        ExpressionKind::Ident(_)
+        | ExpressionKind::Literal(_)
+        | ExpressionKind::Continue { .. }
+        | ExpressionKind::Error => {}
+    }
+}
+
+/// Like [`Visitor`], but with mutable access to every node.
+pub trait VisitorMut: Sized {
+    fn visit_item_mut(&mut self, exprs: &mut Exprs, item: &mut Item) {
+        walk_item_mut(self, exprs, item);
+    }
+
+    fn visit_stmt_mut(&mut self, exprs: &mut Exprs, stmt: &mut Statement) {
+        walk_stmt_mut(self, exprs, stmt);
+    }
+
+    fn visit_block_mut(&mut self, exprs: &mut Exprs, block: &mut Block) {
+        walk_block_mut(self, exprs, block);
+    }
+
+    fn visit_expr_mut(&mut self, exprs: &mut Exprs, expr: ExprId) {
+        walk_expr_mut(self, exprs, expr);
+    }
+}
+
+pub fn walk_item_mut<V: VisitorMut>(visitor: &mut V, exprs: &mut Exprs, item: &mut Item) {
+    match &mut item.kind {
+        ItemKind::Function(function) => visitor.visit_block_mut(exprs, &mut function.block),
+        ItemKind::Struct(_) | ItemKind::TypeAlias(_) => {}
+        ItemKind::Const(constant) => {
+            if let Some(expr) = constant.expr {
+                visitor.visit_expr_mut(exprs, expr);
+            }
+        }
+        ItemKind::Module(module) => {
+            for item in module.items.iter_mut().flatten() {
+                visitor.visit_item_mut(exprs, item);
+            }
+        }
+    }
+}
+
+pub fn walk_stmt_mut<V: VisitorMut>(visitor: &mut V, exprs: &mut Exprs, stmt: &mut Statement) {
+    match &mut stmt.node {
+        StatementKind::Item(item) => visitor.visit_item_mut(exprs, item),
+        StatementKind::Variable { expr, .. } | StatementKind::Expression(expr) => {
+            visitor.visit_expr_mut(exprs, *expr);
+        }
+        StatementKind::For { iter, body, .. } => {
+            visitor.visit_expr_mut(exprs, *iter);
+            visitor.visit_block_mut(exprs, body);
+        }
+    }
+}
+
+pub fn walk_block_mut<V: VisitorMut>(visitor: &mut V, exprs: &mut Exprs, block: &mut Block) {
+    for stmt in &mut block.stmts {
+        visitor.visit_stmt_mut(exprs, stmt);
+    }
+    if let Some(tail) = block.tail {
+        visitor.visit_expr_mut(exprs, tail);
+    }
+}
+
+pub fn walk_expr_mut<V: VisitorMut>(visitor: &mut V, exprs: &mut Exprs, expr: ExprId) {
+    exprs.with_kind(expr, |exprs, kind| match kind {
+        ExpressionKind::Array(elems) | ExpressionKind::Tuple(elems) => {
+            for elem in elems {
+                visitor.visit_expr_mut(exprs, *elem);
+            }
+        }
+        ExpressionKind::ArrayRepeat { value, len } => {
+            visitor.visit_expr_mut(exprs, *value);
+            visitor.visit_expr_mut(exprs, *len);
+        }
+        ExpressionKind::Block { block, .. } | ExpressionKind::Loop { body: block, .. } => {
+            visitor.visit_block_mut(exprs, block);
+        }
+        ExpressionKind::Index { cont, idx } => {
+            visitor.visit_expr_mut(exprs, *cont);
+            for idx in idx {
+                visitor.visit_expr_mut(exprs, *idx);
+            }
+        }
+        ExpressionKind::Call { func, params } => {
+            visitor.visit_expr_mut(exprs, *func);
+            for param in params {
+                visitor.visit_expr_mut(exprs, *param);
+            }
+        }
+        ExpressionKind::Field { expr, .. } => visitor.visit_expr_mut(exprs, *expr),
+        ExpressionKind::MethodCall { receiver, args, .. } => {
+            visitor.visit_expr_mut(exprs, *receiver);
+            for arg in args {
+                visitor.visit_expr_mut(exprs, *arg);
+            }
+        }
+        ExpressionKind::Cast { expr, .. } => visitor.visit_expr_mut(exprs, *expr),
+        ExpressionKind::If {
+            cond,
+            then_block,
+            else_block,
+        } => {
+            visitor.visit_expr_mut(exprs, *cond);
+            visitor.visit_block_mut(exprs, then_block);
+            if let Some(else_block) = else_block {
+                visitor.visit_expr_mut(exprs, *else_block);
+            }
+        }
+        ExpressionKind::Operator(op) => match op {
+            OperatorExpression::ArithmeticLogical { lhs, rhs, .. }
+            | OperatorExpression::Comparison { lhs, rhs, .. }
+            | OperatorExpression::CompoundAssignment { lhs, rhs, .. }
+            | OperatorExpression::Boolean { lhs, rhs, .. }
+            | OperatorExpression::Assignment { lhs, rhs } => {
+                visitor.visit_expr_mut(exprs, *lhs);
+                visitor.visit_expr_mut(exprs, *rhs);
+            }
+            OperatorExpression::Negation { expr, .. }
+            | OperatorExpression::AddressOf { expr }
+            | OperatorExpression::Dereference { expr }
+            | OperatorExpression::Increment { expr }
+            | OperatorExpression::Decrement { expr } => visitor.visit_expr_mut(exprs, *expr),
+        },
+        ExpressionKind::Range { start, end, .. } => {
+            for bound in [start, end].into_iter().flatten() {
+                visitor.visit_expr_mut(exprs, *bound);
+            }
+        }
+        ExpressionKind::StructLiteral { fields, .. } => {
+            for field in fields {
+                visitor.visit_expr_mut(exprs, field.expr);
+            }
+        }
+        ExpressionKind::Return(value) | ExpressionKind::Break { value, .. } => {
+            if let Some(value) = value {
+                visitor.visit_expr_mut(exprs, *value);
+            }
+        }
+        ExpressionKind::Ident(_)
+        | ExpressionKind::Literal(_)
+        | ExpressionKind::Continue { .. }
+        | ExpressionKind::Error => {}
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Parser;
+
+    // Counts the calls in a program, including those nested in other
+    // calls.
+    #[derive(Default)]
+    struct CallCounter {
+        calls: usize,
+    }
+
+    impl Visitor for CallCounter {
+        fn visit_expr(&mut self, exprs: &Exprs, expr: ExprId) {
+            if let ExpressionKind::Call { .. } = exprs[expr].kind {
+                self.calls += 1;
+            }
+            walk_expr(self, exprs, expr);
+        }
+    }
+
+    #[test]
+    fn counts_calls() {
+        let source =
+            "fn main() {\n    let x = f(g(1), 2)\n    if h() { x.m() }\n}\nconst N: int = k()";
+        let (ast, diagnostics) = Parser::new(source).parse();
+        assert!(diagnostics.is_empty(), "{diagnostics:?}");
+
+        let mut counter = CallCounter::default();
+        for item in &ast.items {
+            counter.visit_item(&ast.exprs, item);
+        }
+        // Method calls are not `Call`s.
+        assert_eq!(counter.calls, 4);
+    }
+}
@@ -0,0 +1,123 @@
+/// Identifies the kind of problem a [`super::Diagnostic`] describes.
+///
+/// Every code has a stable textual form like `E0001`, which is shown
+/// in rendered diagnostics. Codes must never be renumbered or reused,
+/// so new ones are only ever added at the end.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Code {
+    /// A token that does not fit the grammar where it appears.
+    UnexpectedToken,
+    /// A string literal that is missing its closing quote.
+    UnterminatedString,
+    /// A block comment that is missing its closing `*/`.
+    UnterminatedComment,
+    /// A character that cannot start any token.
+    UnexpectedCharacter,
+    /// An exponent in a number literal without any digits.
+    MissingExponentDigits,
+    /// A delimiter that is not closed before the end of the source.
+    UnclosedDelimiter,
+    /// A number literal with a suffix that is not a type.
+    InvalidNumberSuffix,
+    /// A float literal with an integer type suffix.
+    FloatWithIntegerSuffix,
+    /// An integer literal that does not fit into its suffixed type, or
+    /// into `u64` without a suffix.
+    IntegerOutOfRange,
+    /// A `const` declaration without a value.
+    MissingConstInitializer,
+    /// A name that does not refer to any declaration in scope.
+    UnresolvedName,
+    /// An expression whose type is not the one required where it is.
+    TypeMismatch,
+    /// An operator applied to operands of types it doesn't support.
+    InvalidOperands,
+    /// An item with the same name as another item in the same scope.
+    DuplicateDefinition,
+    /// A constant operation whose result does not fit into its type.
+    ArithmeticOverflow,
+    /// A constant division or remainder by zero.
+    DivisionByZero,
+    /// An assignment to a binding that was not declared with `mut`.
+    ImmutableAssignment,
+    /// A comparison whose left operand is another comparison without
+    /// parentheses, like `a < b < c`.
+    ChainedComparison,
+    /// A char literal that is missing its closing `'`.
+    UnterminatedChar,
+    /// An escape sequence in a literal that is malformed or stands for
+    /// no valid character.
+    InvalidEscape,
+    /// A char literal that does not hold exactly one character.
+    InvalidCharLiteral,
+    /// An assignment whose left-hand side is not a place, like `5 = x`.
+    InvalidAssignmentTarget,
+    /// A `break` or `continue` outside of a loop, which includes a
+    /// `break` without a label in a labeled block and a `continue` that
+    /// targets one.
+    BreakOutsideLoop,
+    /// A struct literal without parentheses right in front of a block,
+    /// like in the condition of an `if`.
+    StructLiteralNotAllowed,
+    /// A keyword that is reserved for a feature which is not supported
+    /// yet, like `match`.
+    UnsupportedSyntax,
+}
+
+impl Code {
+    /// Gets the stable textual form of the code.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::UnexpectedToken => "E0001",
+            Self::UnterminatedString => "E0002",
+            Self::UnterminatedComment => "E0003",
+            Self::UnexpectedCharacter => "E0004",
+            Self::MissingExponentDigits => "E0005",
+            Self::UnclosedDelimiter => "E0006",
+            Self::InvalidNumberSuffix => "E0007",
+            Self::FloatWithIntegerSuffix => "E0008",
+            Self::IntegerOutOfRange => "E0009",
+            Self::MissingConstInitializer => "E0010",
+            Self::UnresolvedName => "E0011",
+            Self::TypeMismatch => "E0012",
+            Self::InvalidOperands => "E0013",
+            Self::DuplicateDefinition => "E0014",
+            Self::ArithmeticOverflow => "E0015",
+            Self::DivisionByZero => "E0016",
+            Self::ImmutableAssignment => "E0017",
+            Self::ChainedComparison => "E0018",
+            Self::UnterminatedChar => "E0019",
+            Self::InvalidEscape => "E0020",
+            Self::InvalidCharLiteral => "E0021",
+            Self::InvalidAssignmentTarget => "E0022",
+            Self::BreakOutsideLoop => "E0023",
+            Self::StructLiteralNotAllowed => "E0024",
+            Self::UnsupportedSyntax => "E0025",
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Parser;
+
+    #[test]
+    fn syntax_error_codes() {
+        let cases = [
+            ("fn f() { let = 1 }", Code::UnexpectedToken),
+            ("const S: str = \"abc", Code::UnterminatedString),
+            ("fn f() {} /* x", Code::UnterminatedComment),
+            ("fn f() { @ }", Code::UnexpectedCharacter),
+            ("const N: float = 1e", Code::MissingExponentDigits),
+            ("fn f() { g(1)", Code::UnclosedDelimiter),
+            ("const N: int = 1x", Code::InvalidNumberSuffix),
+        ];
+        for (source, code) in cases {
+            let (_, diagnostics) = Parser::new(source).parse();
+            assert_eq!(diagnostics.len(), 1, "{source}: {diagnostics:?}");
+            assert_eq!(diagnostics[0].code(), Some(code), "{source}");
+        }
+        assert_eq!(Code::UnexpectedToken.as_str(), "E0001");
+    }
+}
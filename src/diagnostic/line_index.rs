@@ -0,0 +1,125 @@
+use std::{borrow::Cow, ops::Range};
+
+use super::SourceLocation;
+
+/// Maps [`SourceLocation`]s in a piece of source code to lines.
+///
+/// [`SourceLocation::as_line_and_column`] scans the source from the
+/// beginning on every call. When many locations are converted, as in
+/// rendering a batch of diagnostics, a line index should be built once
+/// instead. Finding the line is then a binary search, and only the line
+/// itself needs to be scanned for the column.
+#[derive(Clone, Debug)]
+pub struct LineIndex<'src> {
+    source: &'src str,
+    // Byte offset of the start of each line. The first line always
+    // starts at 0, so this is never empty.
+    line_starts: Cow<'src, [u32]>,
+}
+
+// Computes the byte offset of the start of each line in `source`.
+pub(super) fn line_starts(source: &str) -> Vec<u32> {
+    [0].into_iter()
+        .chain(source.match_indices('\n').map(|(i, _)| i as u32 + 1))
+        .collect()
+}
+
+impl<'src> LineIndex<'src> {
+    /// Builds the line index for `source`.
+    pub fn new(source: &'src str) -> Self {
+        Self {
+            source,
+            line_starts: Cow::Owned(line_starts(source)),
+        }
+    }
+
+    // Reuses line starts that were computed for `source` before.
+    pub(super) fn with_line_starts(source: &'src str, line_starts: &'src [u32]) -> Self {
+        Self {
+            source,
+            line_starts: Cow::Borrowed(line_starts),
+        }
+    }
+
+    /// Gets the source code this index was built for.
+    pub fn source(&self) -> &'src str {
+        self.source
+    }
+
+    /// Converts a [`SourceLocation`] to 1-based line and column.
+    ///
+    /// The result is the same as that of
+    /// [`SourceLocation::as_line_and_column`] for the indexed source.
+    pub fn line_and_column(&self, location: SourceLocation) -> (u32, u32) {
+        self.line_and_column_by(location, |_| 1)
+    }
+
+    /// Converts a [`SourceLocation`] to 1-based line and a column in
+    /// UTF-16 code units, like [`SourceLocation::as_line_and_utf16_column`].
+    pub fn line_and_utf16_column(&self, location: SourceLocation) -> (u32, u32) {
+        self.line_and_column_by(location, |c| c.len_utf16())
+    }
+
+    fn line_and_column_by(
+        &self,
+        location: SourceLocation,
+        width: impl Fn(char) -> usize,
+    ) -> (u32, u32) {
+        let pos = location.offset();
+        let line = self.line_starts.partition_point(|&start| start <= pos);
+
+        let start = self.line_starts[line - 1] as usize;
+        let column: usize = self.source[start..]
+            .char_indices()
+            .take_while(|&(i, _)| ((start + i) as u32) < pos)
+            .filter(|&(_, c)| c != '\r')
+            .map(|(_, c)| width(c))
+            .sum();
+
+        (line as u32, column as u32 + 1)
+    }
+
+    /// Gets the byte range of the given 1-based line, excluding the line
+    /// break at its end.
+    pub fn line_range(&self, line: u32) -> Range<usize> {
+        let start = self.line_starts[line as usize - 1] as usize;
+        let end = match self.line_starts.get(line as usize) {
+            Some(&next) => next as usize - 1,
+            None => self.source.len(),
+        };
+
+        if self.source[start..end].ends_with('\r') {
+            start..end - 1
+        } else {
+            start..end
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matches_linear_scan() {
+        let source = "fn grüße() {\r\n    let ü = \"😀\"\n\n\tü\n}";
+        let index = LineIndex::new(source);
+        for (offset, _) in source.char_indices().chain([(source.len(), ' ')]) {
+            let location = SourceLocation::new(offset as u32);
+            assert_eq!(
+                index.line_and_column(location),
+                location.as_line_and_column(source),
+                "{offset}",
+            );
+            assert_eq!(
+                index.line_and_utf16_column(location),
+                location.as_line_and_utf16_column(source),
+                "{offset}",
+            );
+        }
+
+        assert_eq!(&source[index.line_range(1)], "fn grüße() {");
+        assert_eq!(&source[index.line_range(3)], "");
+        assert_eq!(&source[index.line_range(5)], "}");
+    }
+}
@@ -1,4 +1,212 @@
 //! Provides machinery for compiler diagnostics.
 
+mod code;
+pub use code::Code;
+
+mod line_index;
+pub use line_index::LineIndex;
+
+mod render;
+pub use render::{render, render_colored};
+
+mod source_map;
+pub use source_map::{FileId, SourceFile, SourceMap};
+
 mod span;
 pub use span::{SourceLocation, SourceSpan};
+
+/// How serious a [`Diagnostic`] is.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Severity {
+    /// The source code is invalid and cannot be compiled.
+    Error,
+    /// The source code compiles but is likely not what was intended.
+    Warning,
+    /// Additional information that is not a problem by itself.
+    Note,
+}
+
+impl Severity {
+    /// Gets the lowercase name of the severity, as used in output.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Error => "error",
+            Self::Warning => "warning",
+            Self::Note => "note",
+        }
+    }
+}
+
+/// A secondary span that gives a [`Diagnostic`] more context, such as
+/// the opening delimiter of an unclosed block.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Label {
+    pub span: SourceSpan,
+    pub message: String,
+}
+
+/// Describes a problem that was found in a piece of source code.
+///
+/// Like [`SourceSpan`]s, diagnostics are not attributed to the source
+/// string they were produced from.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Diagnostic {
+    severity: Severity,
+    code: Option<Code>,
+    message: String,
+    span: SourceSpan,
+    labels: Vec<Label>,
+}
+
+impl Diagnostic {
+    /// Creates a new [`Diagnostic`] with a message at the given span.
+    pub fn new(severity: Severity, span: SourceSpan, message: impl Into<String>) -> Self {
+        Self {
+            severity,
+            code: None,
+            message: message.into(),
+            span,
+            labels: Vec::new(),
+        }
+    }
+
+    /// Creates a new [`Severity::Error`] diagnostic.
+    pub fn error(span: SourceSpan, message: impl Into<String>) -> Self {
+        Self::new(Severity::Error, span, message)
+    }
+
+    /// Creates a new [`Severity::Warning`] diagnostic.
+    pub fn warning(span: SourceSpan, message: impl Into<String>) -> Self {
+        Self::new(Severity::Warning, span, message)
+    }
+
+    /// Creates a new [`Severity::Note`] diagnostic.
+    pub fn note(span: SourceSpan, message: impl Into<String>) -> Self {
+        Self::new(Severity::Note, span, message)
+    }
+
+    /// Attaches a [`Code`] to this diagnostic.
+    pub fn with_code(mut self, code: Code) -> Self {
+        self.code = Some(code);
+        self
+    }
+
+    /// Attaches a secondary [`Label`] to this diagnostic.
+    pub fn with_label(mut self, span: SourceSpan, message: impl Into<String>) -> Self {
+        self.labels.push(Label {
+            span,
+            message: message.into(),
+        });
+        self
+    }
+
+    /// Gets the [`Severity`] of this diagnostic.
+    pub fn severity(&self) -> Severity {
+        self.severity
+    }
+
+    /// Gets the [`Code`] of this diagnostic, if it has one.
+    pub fn code(&self) -> Option<Code> {
+        self.code
+    }
+
+    /// Gets the human-readable message of this diagnostic.
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    /// Gets the primary [`SourceSpan`] this diagnostic is attributed to.
+    pub fn span(&self) -> SourceSpan {
+        self.span
+    }
+
+    /// Gets the secondary [`Label`]s of this diagnostic.
+    pub fn labels(&self) -> &[Label] {
+        &self.labels
+    }
+}
+
+/// Sorts diagnostics by where their primary span starts and removes the
+/// ones that repeat an earlier one.
+///
+/// Diagnostics repeat each other if they have the same severity, code
+/// and message, and their primary spans are equal or overlap, like the
+/// same error reported by two passes. Only the first of them is kept,
+/// together with the secondary labels of all of them.
+pub fn dedup(diagnostics: &mut Vec<Diagnostic>) {
+    diagnostics.sort_by_key(|d| d.span.start().offset());
+
+    let mut kept: Vec<Diagnostic> = Vec::with_capacity(diagnostics.len());
+    for diagnostic in diagnostics.drain(..) {
+        let earlier = kept.iter_mut().find(|d| {
+            (d.span == diagnostic.span || d.span.overlaps(diagnostic.span))
+                && d.severity == diagnostic.severity
+                && d.code == diagnostic.code
+                && d.message == diagnostic.message
+        });
+        match earlier {
+            Some(earlier) => {
+                for label in diagnostic.labels {
+                    if !earlier.labels.contains(&label) {
+                        earlier.labels.push(label);
+                    }
+                }
+            }
+            None => kept.push(diagnostic),
+        }
+    }
+
+    *diagnostics = kept;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Parser;
+
+    #[test]
+    fn parse_error() {
+        let source = "fn f() { let = 1 }";
+        let (_, diagnostics) = Parser::new(source).parse();
+        let expected = Diagnostic::error(SourceSpan::new(13, 14), "expected identifier, found `=`")
+            .with_code(Code::UnexpectedToken);
+        assert_eq!(diagnostics, [expected]);
+        assert_eq!(diagnostics[0].severity(), Severity::Error);
+        assert!(diagnostics[0].labels().is_empty());
+    }
+
+    #[test]
+    fn dedup_repeated_diagnostics() {
+        let error = |start, end, message| {
+            Diagnostic::error(SourceSpan::new(start, end), message).with_code(Code::TypeMismatch)
+        };
+
+        let mut diagnostics = vec![
+            error(20, 25, "second"),
+            error(2, 5, "first"),
+            error(2, 5, "first"),
+            error(30, 31, "second"),
+        ];
+        dedup(&mut diagnostics);
+        assert_eq!(
+            diagnostics,
+            [
+                error(2, 5, "first"),
+                error(20, 25, "second"),
+                error(30, 31, "second"),
+            ],
+        );
+
+        // Overlapping repeats are merged, along with their labels. A
+        // warning at the same span is a different diagnostic.
+        let mut diagnostics = vec![
+            error(2, 5, "first").with_label(SourceSpan::new(0, 1), "here"),
+            error(4, 8, "first").with_label(SourceSpan::new(9, 10), "and here"),
+            Diagnostic::warning(SourceSpan::new(2, 5), "first"),
+        ];
+        dedup(&mut diagnostics);
+        assert_eq!(diagnostics.len(), 2);
+        assert_eq!(diagnostics[0].labels().len(), 2);
+        assert_eq!(diagnostics[1].severity(), Severity::Warning);
+    }
+}
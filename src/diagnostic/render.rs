@@ -0,0 +1,215 @@
+use std::{fmt::Write, ops::Range};
+
+use super::{Diagnostic, LineIndex, Severity, SourceSpan};
+
+/// Renders a [`Diagnostic`] for humans, quoting the offending line of
+/// the indexed source with the span underlined:
+///
+/// ```text
+/// error[E0001]: expected `;`, found `x`
+///  --> x.serq:2:11
+///   |
+/// 2 |     let a = x
+///   |           ^
+/// ```
+///
+/// Secondary labels are quoted the same way, but underlined with `-`
+/// and followed by their message. Spans that cover multiple lines are
+/// underlined up to the end of their first line.
+pub fn render(diagnostic: &Diagnostic, path: &str, lines: &LineIndex) -> String {
+    render_with(diagnostic, path, lines, &Palette::PLAIN)
+}
+
+/// Like [`render`], but highlights the output with ANSI escape codes
+/// for display in a terminal.
+pub fn render_colored(diagnostic: &Diagnostic, path: &str, lines: &LineIndex) -> String {
+    render_with(diagnostic, path, lines, &Palette::ANSI)
+}
+
+// The escape codes to wrap each part of the output in. All of them
+// are empty for plain output.
+struct Palette {
+    error: &'static str,
+    warning: &'static str,
+    note: &'static str,
+    message: &'static str,
+    gutter: &'static str,
+    reset: &'static str,
+}
+
+impl Palette {
+    const PLAIN: Self = Self {
+        error: "",
+        warning: "",
+        note: "",
+        message: "",
+        gutter: "",
+        reset: "",
+    };
+
+    const ANSI: Self = Self {
+        error: "\x1b[1;31m",
+        warning: "\x1b[1;33m",
+        note: "\x1b[1;36m",
+        message: "\x1b[1m",
+        gutter: "\x1b[1;34m",
+        reset: "\x1b[0m",
+    };
+
+    fn severity(&self, severity: Severity) -> &'static str {
+        match severity {
+            Severity::Error => self.error,
+            Severity::Warning => self.warning,
+            Severity::Note => self.note,
+        }
+    }
+}
+
+fn render_with(
+    diagnostic: &Diagnostic,
+    path: &str,
+    lines: &LineIndex,
+    palette: &Palette,
+) -> String {
+    let (line, column) = lines.line_and_column(diagnostic.span().start());
+
+    let gutter = diagnostic
+        .labels()
+        .iter()
+        .map(|l| l.span)
+        .chain([diagnostic.span()])
+        .map(|span| lines.line_and_column(span.start()).0)
+        .max()
+        .unwrap_or(line)
+        .to_string()
+        .len();
+
+    let Palette {
+        message: bold,
+        gutter: blue,
+        reset,
+        ..
+    } = palette;
+    let color = palette.severity(diagnostic.severity());
+
+    let mut out = String::new();
+    let severity = match diagnostic.code() {
+        Some(code) => format!("{}[{}]", diagnostic.severity().as_str(), code.as_str()),
+        None => diagnostic.severity().as_str().to_owned(),
+    };
+    let message = diagnostic.message();
+    writeln!(out, "{color}{severity}{reset}{bold}: {message}{reset}").unwrap();
+    writeln!(out, "{blue}{:gutter$}-->{reset} {path}:{line}:{column}", "").unwrap();
+    writeln!(out, "{blue}{:gutter$} |{reset}", "").unwrap();
+    let primary = Marker {
+        char: '^',
+        color,
+        note: "",
+    };
+    snippet(&mut out, lines, diagnostic.span(), primary, gutter, palette);
+    for label in diagnostic.labels() {
+        let secondary = Marker {
+            char: '-',
+            color: blue,
+            note: &label.message,
+        };
+        snippet(&mut out, lines, label.span, secondary, gutter, palette);
+    }
+
+    out
+}
+
+// How a span is underlined.
+struct Marker<'a> {
+    char: char,
+    color: &'static str,
+    note: &'a str,
+}
+
+// Writes the first line of `span` followed by a line underlining it.
+fn snippet(
+    out: &mut String,
+    lines: &LineIndex,
+    span: SourceSpan,
+    marker: Marker,
+    gutter: usize,
+    palette: &Palette,
+) {
+    let source = lines.source();
+    let (line, _) = lines.line_and_column(span.start());
+    let Range {
+        start: line_start,
+        end: line_end,
+    } = lines.line_range(line);
+    let text = &source[line_start..line_end];
+
+    let Range { start, end } = Range::<usize>::from(span);
+    let start = start.clamp(line_start, line_end);
+
+    // Tabs are kept in the padding so that the underline lines up no
+    // matter how wide the terminal renders them.
+    let padding: String = source[line_start..start]
+        .chars()
+        .filter(|&c| c != '\r')
+        .map(|c| if c == '\t' { '\t' } else { ' ' })
+        .collect();
+    let end = end.clamp(start, line_end);
+    let width = source[start..end]
+        .chars()
+        .filter(|&c| c != '\r')
+        .count()
+        .max(1);
+    let underline = marker.char.to_string().repeat(width);
+
+    let Palette {
+        gutter: blue,
+        reset,
+        ..
+    } = palette;
+    let Marker { color, note, .. } = marker;
+    writeln!(out, "{blue}{line:>gutter$} |{reset} {text}").unwrap();
+    let underline = format!("{padding}{underline} {note}");
+    let underline = underline.trim_end();
+    writeln!(
+        out,
+        "{blue}{:gutter$} |{reset} {color}{underline}{reset}",
+        ""
+    )
+    .unwrap();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::diagnostic::Code;
+
+    #[test]
+    fn underlines_span_in_line() {
+        let source = "fn f() {\n    let a = b + c + d\n}";
+        let start = source.find("c + d").unwrap() as u32;
+        let diagnostic = Diagnostic::error(SourceSpan::new(start, start + 5), "bad operands")
+            .with_code(Code::TypeMismatch);
+
+        let expected = "\
+error[E0012]: bad operands
+ --> x.serq:2:17
+  |
+2 |     let a = b + c + d
+  |                 ^^^^^
+";
+        let rendered = render(&diagnostic, "x.serq", &LineIndex::new(source));
+        assert_eq!(rendered, expected);
+    }
+
+    #[test]
+    fn colors_only_when_asked() {
+        let source = "let a = b\n";
+        let lines = LineIndex::new(source);
+        let diagnostic = Diagnostic::warning(SourceSpan::new(4, 5), "unused variable `a`")
+            .with_label(SourceSpan::new(8, 9), "used here");
+
+        assert!(!render(&diagnostic, "x.serq", &lines).contains('\x1b'));
+        let colored = render_colored(&diagnostic, "x.serq", &lines);
+        assert!(colored.starts_with("\x1b[1;33mwarning"), "{colored:?}");
+    }
+}
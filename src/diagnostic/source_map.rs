@@ -0,0 +1,104 @@
+use super::{LineIndex, SourceLocation, SourceSpan, line_index};
+
+/// Identifies a file registered in a [`SourceMap`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct FileId(u32);
+
+/// A named piece of source code in a [`SourceMap`].
+#[derive(Clone, Debug)]
+pub struct SourceFile {
+    name: String,
+    source: String,
+    // Byte offset of the start of each line, as in `LineIndex`.
+    line_starts: Box<[u32]>,
+}
+
+impl SourceFile {
+    /// Gets the name the file was registered with, usually its path.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Gets the source code of the file.
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    /// Gets a [`LineIndex`] over the source code of the file.
+    pub fn lines(&self) -> LineIndex<'_> {
+        LineIndex::with_line_starts(&self.source, &self.line_starts)
+    }
+}
+
+/// Owns the source code of all files that make up a program.
+///
+/// [`SourceSpan`]s are relative to the file they were produced from,
+/// so they need to be paired with the [`FileId`] of that file before
+/// they can be resolved through the map.
+#[derive(Clone, Debug, Default)]
+pub struct SourceMap {
+    files: Vec<SourceFile>,
+}
+
+impl SourceMap {
+    /// Creates an empty [`SourceMap`].
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers a file and returns its [`FileId`].
+    pub fn add_file(&mut self, name: impl Into<String>, source: impl Into<String>) -> FileId {
+        let source = source.into();
+        let line_starts = line_index::line_starts(&source).into_boxed_slice();
+
+        let id = FileId(self.files.len() as u32);
+        self.files.push(SourceFile {
+            name: name.into(),
+            source,
+            line_starts,
+        });
+        id
+    }
+
+    /// Gets the [`SourceFile`] for a [`FileId`] of this map.
+    pub fn file(&self, file: FileId) -> &SourceFile {
+        &self.files[file.0 as usize]
+    }
+
+    /// Converts a [`SourceLocation`] in a file to 1-based line and
+    /// column.
+    pub fn lookup(&self, file: FileId, location: SourceLocation) -> (u32, u32) {
+        self.file(file).lines().line_and_column(location)
+    }
+
+    /// Attempts to extract the spanned substring of a file.
+    ///
+    /// See [`SourceSpan::text`] for when this returns [`None`].
+    pub fn span_text(&self, file: FileId, span: SourceSpan) -> Option<&str> {
+        span.text(self.file(file).source())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn resolves_spans_per_file() {
+        let mut map = SourceMap::new();
+        let main = map.add_file("main.serq", "fn main() {\n    util()\n}");
+        let util = map.add_file("util.serq", "fn util() {}");
+        assert_ne!(main, util);
+        assert_eq!(map.file(util).name(), "util.serq");
+
+        // The same span means something else in each file.
+        let span = SourceSpan::new(3, 7);
+        assert_eq!(map.span_text(main, span), Some("main"));
+        assert_eq!(map.span_text(util, span), Some("util"));
+        assert_eq!(map.span_text(util, SourceSpan::new(10, 20)), None);
+
+        let location = SourceLocation::new(12);
+        assert_eq!(map.lookup(main, location), (2, 1));
+        assert_eq!(map.lookup(util, location), (1, 13));
+    }
+}
@@ -10,6 +10,7 @@ use std::ops::{Index, Range};
 /// they reference, which makes it the user's responsibility to track
 /// these logical relations.
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct SourceLocation(u32);
 
 impl SourceLocation {
@@ -18,11 +19,33 @@ impl SourceLocation {
         Self(pos)
     }
 
+    /// Gets the byte offset of this location.
+    pub const fn offset(self) -> u32 {
+        self.0
+    }
+
     /// Converts the [`SourceLocation`] to 1-based line and column.
     ///
     /// This information is only used in error messages, so we choose
-    /// to compute it lazily only when we actually need it.
+    /// to compute it lazily only when we actually need it. To convert
+    /// many locations, use a [`super::LineIndex`] instead.
+    ///
+    /// Both `\n` and `\r\n` end a line. A `\r` never counts towards
+    /// the column, and a lone `\r` is not treated as a line break.
     pub fn as_line_and_column(self, input: &str) -> (u32, u32) {
+        self.line_and_column_by(input, |_| 1)
+    }
+
+    /// Like [`SourceLocation::as_line_and_column`], but counts the
+    /// column in UTF-16 code units instead of Unicode scalar values.
+    ///
+    /// This is what the Language Server Protocol uses for positions,
+    /// except that both line and column are still 1-based here.
+    pub fn as_line_and_utf16_column(self, input: &str) -> (u32, u32) {
+        self.line_and_column_by(input, |c| c.len_utf16() as u32)
+    }
+
+    fn line_and_column_by(self, input: &str, width: impl Fn(char) -> u32) -> (u32, u32) {
         let mut line = 1;
         let mut column = 1;
 
@@ -32,11 +55,13 @@ impl SourceLocation {
                 break;
             }
 
-            if c == '\n' {
-                line += 1;
-                column = 1;
-            } else {
-                column += 1;
+            match c {
+                '\n' => {
+                    line += 1;
+                    column = 1;
+                }
+                '\r' => {}
+                _ => column += width(c),
             }
 
             pos += c.len_utf8() as u32;
@@ -52,6 +77,7 @@ impl SourceLocation {
 /// less memory overhead than a substring, which makes it convenient
 /// to store and pass around.
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct SourceSpan {
     start: SourceLocation,
     end: SourceLocation,
@@ -83,6 +109,48 @@ impl SourceSpan {
         self.end.0 - self.start.0
     }
 
+    /// Whether this source span covers no bytes at all, like the
+    /// span of an implicit semicolon.
+    pub fn is_empty(self) -> bool {
+        self.start == self.end
+    }
+
+    /// Whether `pos` lies within this span, which includes its start
+    /// but not its end. Empty spans thus contain no location at all.
+    pub fn contains(self, pos: SourceLocation) -> bool {
+        self.start.0 <= pos.0 && pos.0 < self.end.0
+    }
+
+    /// Whether `other` lies entirely within this span.
+    ///
+    /// Every span contains itself, and an empty span is contained in
+    /// any span that it lies within or at either end of.
+    pub fn contains_span(self, other: SourceSpan) -> bool {
+        self.start.0 <= other.start.0 && other.end.0 <= self.end.0
+    }
+
+    /// Whether this span and `other` share at least one byte.
+    ///
+    /// Spans that only touch at one end don't overlap, and neither do
+    /// empty spans.
+    pub fn overlaps(self, other: SourceSpan) -> bool {
+        self.start.0 < other.end.0 && other.start.0 < self.end.0
+    }
+
+    /// Creates the smallest span that covers both `self` and `other`.
+    ///
+    /// The spans don't need to touch; anything between them is covered
+    /// as well.
+    pub fn join(self, other: SourceSpan) -> SourceSpan {
+        Self::new(self.start.0.min(other.start.0), self.end.0.max(other.end.0))
+    }
+
+    /// Creates a span from the start of `self` to the end of `end`,
+    /// such as from the first to the last token of a node.
+    pub fn to(self, end: SourceSpan) -> SourceSpan {
+        Self::new(self.start.0, end.end.0.max(self.start.0))
+    }
+
     /// Attempts to extract the spanned substring from `input`.
     ///
     /// This may return [`None`] if the span is out of bounds for the
@@ -119,3 +187,54 @@ impl Index<SourceSpan> for str {
         &self[range]
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn join() {
+        let a = SourceSpan::new(2, 5);
+        assert_eq!(a.join(SourceSpan::new(8, 10)), SourceSpan::new(2, 10));
+        assert_eq!(SourceSpan::new(8, 10).join(a), SourceSpan::new(2, 10));
+        assert_eq!(a.join(SourceSpan::new(4, 7)), SourceSpan::new(2, 7));
+        assert_eq!(a.join(SourceSpan::new(3, 4)), a);
+        assert_eq!(a.join(a), a);
+
+        assert_eq!(a.to(SourceSpan::new(8, 10)), SourceSpan::new(2, 10));
+        // An end before the start gives an empty span.
+        assert_eq!(a.to(SourceSpan::new(0, 1)), SourceSpan::new(2, 2));
+    }
+
+    #[test]
+    fn utf16_columns() {
+        // `😀` is 4 bytes, 1 scalar value and 2 UTF-16 code units.
+        let source = "let s = \"😀\" + x";
+        let x = SourceLocation::new(source.find('x').unwrap() as u32);
+        assert_eq!(x.as_line_and_column(source), (1, 15));
+        assert_eq!(x.as_line_and_utf16_column(source), (1, 16));
+    }
+
+    #[test]
+    fn empty_spans_and_boundaries() {
+        let span = SourceSpan::new(2, 5);
+        assert!(!span.is_empty());
+        assert!(SourceSpan::new(3, 3).is_empty());
+
+        // The start is inside the span, the end is not.
+        assert!(!span.contains(SourceLocation::new(1)));
+        assert!(span.contains(SourceLocation::new(2)));
+        assert!(span.contains(SourceLocation::new(4)));
+        assert!(!span.contains(SourceLocation::new(5)));
+        assert!(!SourceSpan::new(3, 3).contains(SourceLocation::new(3)));
+
+        assert!(span.contains_span(span));
+        assert!(span.contains_span(SourceSpan::new(3, 4)));
+        assert!(!span.contains_span(SourceSpan::new(1, 4)));
+        assert!(!span.contains_span(SourceSpan::new(4, 6)));
+        // Empty spans at either end are contained, but not past them.
+        assert!(span.contains_span(SourceSpan::new(2, 2)));
+        assert!(span.contains_span(SourceSpan::new(5, 5)));
+        assert!(!span.contains_span(SourceSpan::new(6, 6)));
+    }
+}
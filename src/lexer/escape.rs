@@ -0,0 +1,221 @@
+use std::{error::Error, fmt};
+
+/// The reason an escape sequence in a string literal is invalid.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EscapeErrorKind {
+    /// A backslash followed by a character that has no escape, like `\q`.
+    UnknownEscape(char),
+    /// A backslash at the very end of the string.
+    LoneBackslash,
+    /// A `\u` that is not followed by 1 to 6 hex digits in braces.
+    MalformedUnicode,
+    /// A `\u{...}` of a surrogate or a value above `10FFFF`.
+    InvalidCodePoint(u32),
+    /// A `\x` that is not followed by exactly two hex digits.
+    MalformedHex,
+    /// A `\x..` above `7F`, which is not an ASCII character.
+    HexOutOfRange(u8),
+}
+
+/// The error for an invalid escape sequence found by [`unescape_string`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct EscapeError {
+    kind: EscapeErrorKind,
+    // The byte offset of the backslash that starts the escape sequence.
+    offset: usize,
+    // The byte offset right after the invalid part of the sequence.
+    end: usize,
+}
+
+impl EscapeError {
+    /// Gets what is wrong with the escape sequence.
+    pub fn kind(self) -> EscapeErrorKind {
+        self.kind
+    }
+
+    /// Gets the byte offset of the backslash that starts the escape
+    /// sequence, relative to the start of the text given to
+    /// [`unescape_string`].
+    pub fn offset(self) -> usize {
+        self.offset
+    }
+
+    /// Gets the byte offset right after the invalid escape sequence, or
+    /// after the part of it that could be read.
+    pub fn end(self) -> usize {
+        self.end
+    }
+}
+
+impl fmt::Display for EscapeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.kind {
+            EscapeErrorKind::UnknownEscape(c) => {
+                write!(f, "unknown character escape `\\{}`", c.escape_debug())
+            }
+            EscapeErrorKind::LoneBackslash => f.write_str("escape sequence without a character"),
+            EscapeErrorKind::MalformedUnicode => {
+                f.write_str("unicode escape must be 1 to 6 hex digits in braces, like `\\u{7FFF}`")
+            }
+            EscapeErrorKind::InvalidCodePoint(value) => {
+                write!(f, "`\\u{{{value:X}}}` is not a valid unicode character")
+            }
+            EscapeErrorKind::MalformedHex => {
+                f.write_str("hex escape must be exactly two hex digits, like `\\x7F`")
+            }
+            EscapeErrorKind::HexOutOfRange(value) => {
+                write!(
+                    f,
+                    "`\\x{value:02X}` is out of range, hex escapes go up to `\\x7F`"
+                )
+            }
+        }
+    }
+}
+
+impl Error for EscapeError {}
+
+/// Replaces the escape sequences in the contents of a string literal,
+/// without its quotes, by the characters they stand for.
+///
+/// The supported escapes are `\n`, `\t`, `\r`, `\\`, `\"`, `\'`, `\0`,
+/// `\x..` with the hex value of an ASCII character, and `\u{...}` with
+/// the hex value of any unicode character. The first invalid escape
+/// sequence makes this fail.
+pub fn unescape_string(text: &str) -> Result<String, EscapeError> {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find('\\') {
+        out.push_str(&rest[..start]);
+        let offset = text.len() - rest.len() + start;
+        let error = |kind, len| EscapeError {
+            kind,
+            offset,
+            end: offset + len,
+        };
+
+        let mut escape = rest[start + 1..].chars();
+        let c = match escape.next() {
+            Some('n') => '\n',
+            Some('t') => '\t',
+            Some('r') => '\r',
+            Some('\\') => '\\',
+            Some('"') => '"',
+            Some('\'') => '\'',
+            Some('0') => '\0',
+            Some('x') => {
+                let digits = escape
+                    .as_str()
+                    .get(..2)
+                    .filter(|digits| digits.bytes().all(|b| b.is_ascii_hexdigit()))
+                    .ok_or(error(EscapeErrorKind::MalformedHex, 2))?;
+
+                let value = u8::from_str_radix(digits, 16).unwrap();
+                if !value.is_ascii() {
+                    return Err(error(EscapeErrorKind::HexOutOfRange(value), 4));
+                }
+                escape = escape.as_str()[2..].chars();
+                char::from(value)
+            }
+            Some('u') => {
+                let braced = escape.as_str();
+                let digits = braced
+                    .strip_prefix('{')
+                    .and_then(|braced| braced.split_once('}'))
+                    .map(|(digits, _)| digits)
+                    .filter(|digits| {
+                        (1..=6).contains(&digits.len())
+                            && digits.bytes().all(|b| b.is_ascii_hexdigit())
+                    })
+                    .ok_or(error(EscapeErrorKind::MalformedUnicode, 2))?;
+
+                let len = "{}".len() + digits.len();
+                let value = u32::from_str_radix(digits, 16).unwrap();
+                let c = char::from_u32(value)
+                    .ok_or(error(EscapeErrorKind::InvalidCodePoint(value), 2 + len))?;
+                escape = braced[len..].chars();
+                c
+            }
+            Some(c) => {
+                let kind = EscapeErrorKind::UnknownEscape(c);
+                return Err(error(kind, 1 + c.len_utf8()));
+            }
+            None => return Err(error(EscapeErrorKind::LoneBackslash, 1)),
+        };
+        out.push(c);
+        rest = escape.as_str();
+    }
+    out.push_str(rest);
+
+    Ok(out)
+}
+
+/// Escapes a string so that it can be written as the contents of a
+/// string literal, the inverse of [`unescape_string`].
+///
+/// Backslashes, quotes and control characters are escaped, everything
+/// else is kept as is.
+pub fn escape_string(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '\n' => out.push_str("\\n"),
+            '\t' => out.push_str("\\t"),
+            '\r' => out.push_str("\\r"),
+            '\\' => out.push_str("\\\\"),
+            '"' => out.push_str("\\\""),
+            '\0' => out.push_str("\\0"),
+            c if c.is_control() => out.push_str(&format!("\\u{{{:x}}}", c as u32)),
+            c => out.push(c),
+        }
+    }
+
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        for text in [
+            "",
+            "plain",
+            "a\"b\\c",
+            "tab\tnew\nline\r",
+            "\0nul",
+            "ünï\u{7F}\u{1F600}",
+        ] {
+            assert_eq!(unescape_string(&escape_string(text)).as_deref(), Ok(text));
+        }
+    }
+
+    #[test]
+    fn escapes() {
+        let text = r#"\n\t\r\\\"\'\0\x41\u{42}\u{1F600}"#;
+        assert_eq!(unescape_string(text).unwrap(), "\n\t\r\\\"'\0AB\u{1F600}");
+    }
+
+    #[test]
+    fn invalid_escapes() {
+        use EscapeErrorKind::*;
+
+        let cases = [
+            ("ab\\q", UnknownEscape('q'), 2..4),
+            ("ab\\", LoneBackslash, 2..3),
+            ("\\u41", MalformedUnicode, 0..2),
+            ("\\u{}", MalformedUnicode, 0..2),
+            ("\\u{1234567}", MalformedUnicode, 0..2),
+            ("\\u{D800}", InvalidCodePoint(0xD800), 0..8),
+            ("\\u{110000}", InvalidCodePoint(0x110000), 0..10),
+            ("\\x4", MalformedHex, 0..2),
+            ("\\xG0", MalformedHex, 0..2),
+            ("\\x80", HexOutOfRange(0x80), 0..4),
+        ];
+        for (text, kind, span) in cases {
+            let e = unescape_string(text).unwrap_err();
+            assert_eq!((e.kind(), e.offset()..e.end()), (kind, span), "{text:?}");
+        }
+    }
+}
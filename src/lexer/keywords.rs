@@ -38,21 +38,69 @@ pub fn check_keyword(buf: [u8; MAX_KEYWORD_LEN]) -> TokenKind {
     // fit into registers, we get string comparison with very efficient
     // cmp instructions.
     match buf[0] {
+        b'a' => match_kw(buf, const { kw("as") }, As),
         b'b' => match_kw(buf, const { kw("break") }, Break),
         b'c' if buf[3] == b's' => match_kw(buf, const { kw("const") }, Const),
         b'c' if buf[3] == b't' => match_kw(buf, const { kw("continue") }, Continue),
         b'e' if buf[1] == b'l' => match_kw(buf, const { kw("else") }, Else),
         b'e' if buf[1] == b'n' => match_kw(buf, const { kw("enum") }, Enum),
         b'f' if buf[1] == b'a' => match_kw(buf, const { kw("false") }, False),
-        b'f' if buf[1] == b'n' => Fn,
+        b'f' if buf[1] == b'n' => match_kw(buf, const { kw("fn") }, Fn),
         b'f' if buf[1] == b'o' => match_kw(buf, const { kw("for") }, For),
-        b'i' => match_kw(buf, const { kw("if") }, If),
-        b'l' => match_kw(buf, const { kw("let") }, Let),
-        b'm' => match_kw(buf, const { kw("mut") }, Mut),
+        b'i' if buf[1] == b'f' => match_kw(buf, const { kw("if") }, If),
+        b'i' if buf[1] == b'm' => match_kw(buf, const { kw("impl") }, Impl),
+        b'i' if buf[1] == b'n' => match_kw(buf, const { kw("in") }, In),
+        b'l' if buf[1] == b'e' => match_kw(buf, const { kw("let") }, Let),
+        b'l' if buf[1] == b'o' => match_kw(buf, const { kw("loop") }, Loop),
+        b'm' if buf[1] == b'a' => match_kw(buf, const { kw("match") }, Match),
+        b'm' if buf[1] == b'o' => match_kw(buf, const { kw("mod") }, Mod),
+        b'm' if buf[1] == b'u' => match_kw(buf, const { kw("mut") }, Mut),
         b'p' => match_kw(buf, const { kw("pub") }, Pub),
         b'r' => match_kw(buf, const { kw("return") }, Return),
-        b't' => match_kw(buf, const { kw("true") }, True),
+        b's' => match_kw(buf, const { kw("struct") }, Struct),
+        b't' if buf[2] == b'a' => match_kw(buf, const { kw("trait") }, Trait),
+        b't' if buf[2] == b'u' => match_kw(buf, const { kw("true") }, True),
+        b't' if buf[2] == b'p' => match_kw(buf, const { kw("type") }, Type),
         b'w' => match_kw(buf, const { kw("while") }, While),
         _ => Identifier,
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::lexer::{Lexer, TokenKind};
+
+    fn lex_one(source: &str) -> TokenKind {
+        Lexer::new(source).next().unwrap().kind()
+    }
+
+    #[test]
+    fn keyword_prefixes_are_identifiers() {
+        for source in ["fnord", "fn2", "fnmain", "fn_helper", "f", "asm", "loops"] {
+            assert_eq!(lex_one(source), TokenKind::Identifier, "{source}");
+        }
+        assert_eq!(lex_one("fn"), TokenKind::Fn);
+    }
+
+    #[test]
+    fn item_and_control_keywords() {
+        use TokenKind::*;
+        let keywords = [
+            ("struct", Struct),
+            ("trait", Trait),
+            ("impl", Impl),
+            ("match", Match),
+            ("loop", Loop),
+            ("as", As),
+            ("in", In),
+        ];
+        for (source, kind) in keywords {
+            assert_eq!(lex_one(source), kind, "{source}");
+        }
+        for source in [
+            "structs", "traits", "impls", "matches", "looped", "ass", "inn", "i", "a",
+        ] {
+            assert_eq!(lex_one(source), Identifier, "{source}");
+        }
+    }
+}
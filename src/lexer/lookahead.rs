@@ -0,0 +1,97 @@
+use std::{collections::VecDeque, ops::Range};
+
+use super::{Lexer, Token, TokenKind};
+use crate::diagnostic::{Diagnostic, SourceSpan};
+
+/// Wraps a [`Lexer`] to provide lookahead of an arbitrary number of tokens.
+///
+/// Tokens that are peeked at are buffered, so every token is only lexed
+/// once regardless of how far ahead a consumer looks.
+#[derive(Clone, Debug)]
+pub struct Lookahead<'src> {
+    lexer: Lexer<'src>,
+    buffer: VecDeque<Token>,
+}
+
+impl<'src> Lookahead<'src> {
+    /// Creates a new [`Lookahead`] over the tokens of `lexer`.
+    pub fn new(lexer: Lexer<'src>) -> Self {
+        Self {
+            lexer,
+            buffer: VecDeque::new(),
+        }
+    }
+
+    /// Gets the next token without consuming it.
+    pub fn peek(&mut self) -> Option<Token> {
+        self.peek_nth(0)
+    }
+
+    /// Gets the `n`-th upcoming token without consuming anything.
+    ///
+    /// `peek_nth(0)` is equivalent to [`Lookahead::peek`]. Returns
+    /// [`None`] if the input ends before the requested token.
+    pub fn peek_nth(&mut self, n: usize) -> Option<Token> {
+        while self.buffer.len() <= n {
+            let token = self.lexer.next()?;
+            self.buffer.push_back(token);
+        }
+        Some(self.buffer[n])
+    }
+
+    /// Splits an upcoming `>>` into two `>` tokens, and `>>=` into `>`
+    /// and `>=`.
+    ///
+    /// This lets a parser close nested generic argument lists like in
+    /// `Vec<Vec<int>>` one at a time. `>=` becomes `>` and `=` for the
+    /// same reason, as in `const V: Vec<int>= ...`. Any other token is
+    /// left as is.
+    pub fn split_shr(&mut self) {
+        let Some(token) = self.peek() else {
+            return;
+        };
+        let rest = match token.kind {
+            TokenKind::Shr => TokenKind::Gt,
+            TokenKind::ShrEq => TokenKind::GtEq,
+            TokenKind::GtEq => TokenKind::Eq,
+            _ => return,
+        };
+
+        let Range { start, end } = Range::<u32>::from(token.span);
+        self.buffer[0] = Token {
+            kind: TokenKind::Gt,
+            span: SourceSpan::new(start, start + 1),
+        };
+        self.buffer.insert(
+            1,
+            Token {
+                kind: rest,
+                span: SourceSpan::new(start + 1, end),
+            },
+        );
+    }
+
+    /// Gets the diagnostics of the underlying [`Lexer`].
+    ///
+    /// This only covers tokens that were lexed so far, including the
+    /// ones that are still buffered.
+    pub fn diagnostics(&self) -> &[Diagnostic] {
+        self.lexer.diagnostics()
+    }
+
+    /// Gets the spans of the comments of the underlying [`Lexer`].
+    ///
+    /// Like [`Lookahead::diagnostics`], this includes the comments in
+    /// front of buffered tokens.
+    pub fn comments(&self) -> &[SourceSpan] {
+        self.lexer.comments()
+    }
+}
+
+impl<'src> Iterator for Lookahead<'src> {
+    type Item = Token;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.buffer.pop_front().or_else(|| self.lexer.next())
+    }
+}
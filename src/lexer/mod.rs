@@ -4,44 +4,113 @@
 //! piece of source code into a sequence of tokens that is easier for
 //! us to work with in subsequent stages:
 //!
-//! ```no_run
+//! ```text
 //! mut x = 5
 //! ```
 //!
 //! is turned into the following stream:
 //!
-//! ```no_run
+//! ```text
 //! Mut Identifier Eq Number Semicolon
 //! ```
 //!
 //! At the core, the [`Lexer`] allows for tokens to be streamed on demand
 //! through its `Iterator<Item = Token>` interface, which can then be fed
-//! into the parser. It should be noted that no parsing work is performed
+//! into the parser. A [`Lookahead`] buffers tokens for consumers that
+//! need to look more than one token ahead. It should be noted that no parsing work is performed
 //! during lexing, which makes a [`Token`] rather low-level. Consider for
 //! example [`TokenKind::Number`], which doesn't specify the type of int
 //! of float that was encountered.
 //!
 //! Serqlane supports the automatic insertion of implicit semicolons to
 //! terminate expressions following the token types listed in the
-//! `should_terminate_expr` helper function. Other than that, this lexer
+//! `should_terminate_expr` helper function. Tools that want the raw
+//! token stream can opt out with [`Lexer::without_auto_semicolons`]. Other than that, this lexer
 //! is fairly conventional and doesn't have outstanding intricacies.
 
-use std::str::CharIndices;
+use std::{error::Error, fmt, str::CharIndices};
 
-use crate::diagnostic::SourceSpan;
+use crate::diagnostic::{Code, Diagnostic, SourceSpan};
+
+mod escape;
+pub use escape::{EscapeError, EscapeErrorKind, escape_string, unescape_string};
 
 mod keywords;
 use keywords::{MAX_KEYWORD_LEN, check_keyword};
 
+mod lookahead;
+pub use lookahead::Lookahead;
+
 mod token;
-pub use token::{Token, TokenKind};
+pub use token::{Token, TokenCategory, TokenKind, token_at};
 
 const EOF_CHAR: char = '\0';
+const BOM_CHAR: char = '\u{FEFF}';
+
+// Lets tests turn off skipping whitespace and comments in bulk, so they
+// can check that it gives the same tokens as decoding every char.
+#[cfg(test)]
+thread_local! {
+    static DECODE_EVERY_CHAR: std::cell::Cell<bool> = const { std::cell::Cell::new(false) };
+}
+
+// Whether whitespace and comments may be skipped without decoding them.
+fn skip_in_bulk() -> bool {
+    #[cfg(test)]
+    if DECODE_EVERY_CHAR.get() {
+        return false;
+    }
+    true
+}
+
+/// The largest source code in bytes that can be lexed, so that every
+/// offset into it fits in a `u32`.
+pub const MAX_SOURCE_LEN: usize = u32::MAX as usize;
+
+/// The error for source code that is too large to be lexed, i.e. longer
+/// than [`MAX_SOURCE_LEN`] bytes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SourceTooLarge {
+    // The length of the rejected source code in bytes.
+    len: usize,
+}
+
+impl SourceTooLarge {
+    /// Gets the length of the rejected source code in bytes.
+    pub fn source_len(self) -> usize {
+        self.len
+    }
+}
+
+impl fmt::Display for SourceTooLarge {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "source code of {} bytes is too large, it must be smaller than 4GiB",
+            self.len
+        )
+    }
+}
+
+impl Error for SourceTooLarge {}
+
+// Checks a source length against a limit, which is only ever something
+// other than `MAX_SOURCE_LEN` to exercise the boundary without
+// allocating 4GiB.
+fn check_len(len: usize, max: usize) -> Result<(), SourceTooLarge> {
+    if len > max {
+        Err(SourceTooLarge { len })
+    } else {
+        Ok(())
+    }
+}
 
 /// Breaks down a given piece of source code into tokens.
 ///
-/// The only public-facing interface to drive the lexer is [`Iterator`];
-/// iteration stops after [`TokenKind::Eof`] has been yielded once.
+/// The primary interface to drive the lexer is [`Iterator`]; iteration
+/// stops once the end of input is reached, without yielding
+/// [`TokenKind::Eof`]. Use [`Lexer::tokenize`] to get a token stream
+/// that is explicitly terminated by it.
 ///
 /// See the [module documentation][crate::lexer] for more details.
 #[derive(Clone, Debug)]
@@ -50,12 +119,59 @@ pub struct Lexer<'src> {
     // offsets for each consumed character.
     source: CharIndices<'src>,
 
+    // The offset of the start of `source` in the original source code.
+    // It only changes when bytes are skipped without decoding them.
+    base: usize,
+
     // The last token that was read from the source.
     // This is used to determine when an implicit semicolon
     // should be injected into the stream.
     previous: TokenKind,
+
+    // Whether implicit semicolons are injected at all.
+    auto_semicolons: bool,
+
+    // The spans of all comments skipped so far, in source order.
+    comments: Vec<SourceSpan>,
+
+    // Diagnostics for every error encountered so far. Each of
+    // them corresponds to a yielded `TokenKind::Error`, except
+    // for unterminated comments which produce no token.
+    diagnostics: Vec<Diagnostic>,
 }
 
+/// A snapshot of how far a [`Lexer`] got into its source code, taken
+/// with [`Lexer::state`] and used to continue from there with
+/// [`Lexer::resume`].
+///
+/// This makes it possible to re-lex only the part of a file that follows
+/// an edit: the state of the last token before the edit stays valid, as
+/// it only depends on the text up to its offset. An edit anywhere before
+/// that offset invalidates the state, and lexing has to be resumed from
+/// an earlier one instead.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct LexerState {
+    // The offset at which lexing continues.
+    offset: u32,
+
+    // The last token before `offset`, which decides whether the next
+    // newline injects an implicit semicolon.
+    previous: TokenKind,
+
+    // Whether implicit semicolons are injected at all.
+    auto_semicolons: bool,
+}
+
+impl LexerState {
+    /// Gets the byte offset at which lexing continues.
+    pub fn offset(self) -> u32 {
+        self.offset
+    }
+}
+
+// Whether a line that ends in `token` gets an implicit semicolon. This
+// must hold for every token that can end an expression, so a new kind
+// of literal or postfix operator has to be added here.
 fn should_terminate_expr(token: TokenKind) -> bool {
     matches!(
         token,
@@ -66,10 +182,15 @@ fn should_terminate_expr(token: TokenKind) -> bool {
 
         // Identifiers and literals
         | TokenKind::Identifier
+        | TokenKind::Label
+        | TokenKind::Underscore
         | TokenKind::String
+        | TokenKind::Char
         | TokenKind::Number
 
         // Keywords
+        | TokenKind::True
+        | TokenKind::False
         | TokenKind::Break
         | TokenKind::Continue
         | TokenKind::Return
@@ -77,6 +198,25 @@ fn should_terminate_expr(token: TokenKind) -> bool {
         // Operators
         | TokenKind::PlusPlus
         | TokenKind::MinusMinus
+        | TokenKind::Question
+        // Ends a range without an end, like `a..`
+        | TokenKind::DotDot
+    )
+}
+
+// Whitespace as defined by Unicode's Pattern_White_Space property,
+// which unlike White_Space is guaranteed to never change.
+fn is_whitespace(c: char) -> bool {
+    matches!(
+        c,
+        // ASCII whitespace, including vertical tab and form feed
+        '\t' | '\n' | '\u{000B}' | '\u{000C}' | '\r' | ' '
+
+        // Next line, left-to-right and right-to-left marks
+        | '\u{0085}' | '\u{200E}' | '\u{200F}'
+
+        // Line and paragraph separators
+        | '\u{2028}' | '\u{2029}'
     )
 }
 
@@ -95,19 +235,124 @@ fn is_ident2(c: char) -> bool {
 
 impl<'src> Lexer<'src> {
     /// Creates a new [`Lexer`] over a given string of source code.
+    ///
+    /// A leading byte order mark is skipped, but spans of the produced
+    /// tokens remain relative to the original bytes including the BOM.
+    /// A BOM anywhere else in the source is lexed as an error.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `source` is 4GiB or larger, since spans could not
+    /// cover all of it with `u32` offsets. Use [`Lexer::try_new`] for
+    /// sources that are not known to be small enough.
     pub fn new(source: &'src str) -> Self {
-        // Invariant: Source files need to be smaller than 4GiB so that
-        // our spans can cover the entire text with u32 offsets.
-        debug_assert!(u32::try_from(source.len()).is_ok());
+        Self::try_new(source).unwrap_or_else(|e| panic!("{e}"))
+    }
+
+    /// Creates a new [`Lexer`] like [`Lexer::new`], but returns an error
+    /// instead of panicking if `source` is longer than
+    /// [`MAX_SOURCE_LEN`] bytes.
+    pub fn try_new(source: &'src str) -> Result<Self, SourceTooLarge> {
+        check_len(source.len(), MAX_SOURCE_LEN)?;
 
-        Self {
-            source: source.char_indices(),
+        let mut chars = source.char_indices();
+        if source.starts_with(BOM_CHAR) {
+            chars.next();
+        }
+
+        Ok(Self {
+            source: chars,
+            base: 0,
             previous: TokenKind::Eof,
+            auto_semicolons: true,
+            comments: Vec::new(),
+            diagnostics: Vec::new(),
+        })
+    }
+
+    /// Disables the insertion of implicit semicolons.
+    ///
+    /// Newlines are then treated like any other whitespace, and it is
+    /// up to the consumer to require explicit `;` where needed.
+    pub fn without_auto_semicolons(mut self) -> Self {
+        self.auto_semicolons = false;
+        self
+    }
+
+    /// Creates a new [`Lexer`] that continues lexing a piece of source
+    /// code from a [`LexerState`] of an earlier one.
+    ///
+    /// The produced tokens are the same that the earlier lexer produces
+    /// after its state was taken, as long as the source code was not
+    /// changed before [`LexerState::offset`]. Diagnostics and comments
+    /// from before that offset are not included.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the offset of `state` is out of bounds or not on a char
+    /// boundary of `source`, and if `source` is 4GiB or larger.
+    pub fn resume(source: &'src str, state: LexerState) -> Self {
+        let mut lexer = Self::new(source);
+        let offset = state.offset as usize;
+        if offset > 0 {
+            lexer.source = source[offset..].char_indices();
+            lexer.base = offset;
+        }
+        lexer.previous = state.previous;
+        lexer.auto_semicolons = state.auto_semicolons;
+        lexer
+    }
+
+    /// Lexes an entire piece of source code in one go.
+    ///
+    /// Unlike the [`Iterator`] interface, the returned tokens always end
+    /// with a single [`TokenKind::Eof`]. Errors do not stop lexing; each
+    /// error token has a corresponding [`Diagnostic`] in the second vector.
+    pub fn tokenize(source: &'src str) -> (Vec<Token>, Vec<Diagnostic>) {
+        let mut lexer = Self::new(source);
+        let mut tokens = Vec::new();
+        loop {
+            let token = Self::scan(&mut lexer);
+            tokens.push(token);
+            if token.kind == TokenKind::Eof {
+                break;
+            }
         }
+
+        (tokens, lexer.diagnostics)
+    }
+
+    /// Gets the diagnostics for all errors encountered so far.
+    pub fn diagnostics(&self) -> &[Diagnostic] {
+        &self.diagnostics
+    }
+
+    /// Gets the spans of all comments skipped so far.
+    ///
+    /// Comments produce no tokens, so this is how tools like formatters
+    /// can find them. Unterminated block comments extend to the end of
+    /// the source.
+    pub fn comments(&self) -> &[SourceSpan] {
+        &self.comments
+    }
+
+    /// Takes a snapshot of the lexer's position in the source code, to
+    /// resume lexing from it later with [`Lexer::resume`].
+    pub fn state(&self) -> LexerState {
+        LexerState {
+            offset: self.offset(),
+            previous: self.previous,
+            auto_semicolons: self.auto_semicolons,
+        }
+    }
+
+    /// Turns the lexer into a [`Lookahead`] over its tokens.
+    pub fn lookahead(self) -> Lookahead<'src> {
+        Lookahead::new(self)
     }
 
     fn offset(&self) -> u32 {
-        self.source.offset() as u32
+        (self.base + self.source.offset()) as u32
     }
 
     fn peek(&self) -> char {
@@ -128,6 +373,21 @@ impl<'src> Lexer<'src> {
         self.source.next().map(|v| v.1).unwrap_or(EOF_CHAR)
     }
 
+    // Skips the next `len` bytes of source code in one go. They must
+    // end on a char boundary.
+    fn skip_bytes(&mut self, len: usize) {
+        let rest = &self.source.as_str()[len..];
+        self.base = self.offset() as usize + len;
+        self.source = rest.char_indices();
+    }
+
+    fn error(&mut self, start: u32, code: Code, message: impl Into<String>) -> TokenKind {
+        let span = SourceSpan::from(start..self.offset());
+        let diagnostic = Diagnostic::error(span, message).with_code(code);
+        self.diagnostics.push(diagnostic);
+        TokenKind::Error
+    }
+
     fn match1(&mut self, c: char, a: TokenKind, b: TokenKind) -> TokenKind {
         if self.peek() == c {
             self.consume();
@@ -159,17 +419,38 @@ impl<'src> Lexer<'src> {
     }
 
     fn line_comment(&mut self) {
-        while self.peek() != '\n' && !self.reached_eof() {
-            self.consume();
+        let start = self.offset();
+        if skip_in_bulk() {
+            // Neither `\n` nor `EOF_CHAR` can occur as part of a
+            // multi-byte char, so the body can be skipped without
+            // decoding it.
+            let rest = self.source.as_str().as_bytes();
+            let len = rest
+                .iter()
+                .position(|&b| b == b'\n' || b == EOF_CHAR as u8)
+                .unwrap_or(rest.len());
+            self.skip_bytes(len);
+        } else {
+            while self.peek() != '\n' && !self.reached_eof() {
+                self.consume();
+            }
         }
+        self.comments.push(SourceSpan::from(start..self.offset()));
     }
 
     fn multi_line_comment(&mut self) {
+        let start = self.offset();
         self.consume();
         self.consume();
 
         while !(self.peek() == '*' && self.peek2() == '/') {
             if self.reached_eof() {
+                self.error(
+                    start,
+                    Code::UnterminatedComment,
+                    "unterminated block comment",
+                );
+                self.comments.push(SourceSpan::from(start..self.offset()));
                 return;
             }
 
@@ -178,6 +459,7 @@ impl<'src> Lexer<'src> {
 
         self.consume();
         self.consume();
+        self.comments.push(SourceSpan::from(start..self.offset()));
     }
 
     fn whitespace(&mut self) -> Option<Token> {
@@ -188,21 +470,52 @@ impl<'src> Lexer<'src> {
                 // of the line to see if an implicit semicolon should be
                 // injected to terminate an expression. On EOF, we do the
                 // same so we don't accidentally cut a semicolon off.
-                '\n' | EOF_CHAR => {
-                    if should_terminate_expr(self.previous) {
+                //
+                // A `\r\n` sequence is treated as a single newline. A lone
+                // `\r` is not a line break and is skipped as whitespace.
+                //
+                // Only the first newline after the token counts, so the
+                // semicolon ends up at the end of its line no matter how
+                // many blank lines and comments follow.
+                c @ ('\n' | '\r' | EOF_CHAR) => {
+                    if c == '\r' && self.peek2() != '\n' {
+                        self.consume();
+                        continue;
+                    }
+
+                    if token.is_none()
+                        && self.auto_semicolons
+                        && should_terminate_expr(self.previous)
+                    {
                         let pos = self.offset();
                         token = Some(Token {
                             kind: TokenKind::Semicolon,
                             span: SourceSpan::from(pos..pos),
                         });
                     }
+                    if c == '\r' {
+                        self.consume();
+                    }
                     if self.consume() == EOF_CHAR {
                         break;
                     }
                 }
 
+                // Runs of indentation and spaces are the most common
+                // kind of whitespace, so they are skipped in bulk
+                // without decoding them.
+                ' ' | '\t' | '\u{000B}' | '\u{000C}' if skip_in_bulk() => {
+                    let len = self
+                        .source
+                        .as_str()
+                        .bytes()
+                        .take_while(|b| matches!(b, b' ' | b'\t' | 0x0B | 0x0C))
+                        .count();
+                    self.skip_bytes(len);
+                }
+
                 // Other whitespace can be trivially ignored.
-                c if c.is_whitespace() => {
+                c if is_whitespace(c) => {
                     self.consume();
                 }
 
@@ -213,6 +526,8 @@ impl<'src> Lexer<'src> {
                         self.line_comment();
                     } else if c2 == '*' {
                         self.multi_line_comment();
+                    } else {
+                        break;
                     }
                 }
 
@@ -223,24 +538,97 @@ impl<'src> Lexer<'src> {
         token
     }
 
-    fn string(&mut self) -> TokenKind {
+    // A string may span several lines, which keeps the newlines in its
+    // contents. No implicit semicolon ends up inside of it, as those are
+    // only inserted in the whitespace between tokens.
+    fn string(&mut self, start: u32) -> TokenKind {
         while self.peek() != '"' {
             if self.reached_eof() {
-                return TokenKind::Error;
+                return self.error(
+                    start,
+                    Code::UnterminatedString,
+                    "unterminated string literal",
+                );
             }
 
-            self.consume();
+            // Skip over the escaped character so that `\"` doesn't
+            // terminate the string.
+            if self.consume() == '\\' {
+                self.consume();
+            }
         }
 
         self.consume();
         TokenKind::String
     }
 
-    fn number(&mut self) -> TokenKind {
-        // TODO: Handle more complex number representations.
+    // Unlike a string, a char literal ends at the end of its line. How
+    // many characters it holds is left for the parser to check, along
+    // with its escape sequence.
+    fn char(&mut self, start: u32) -> TokenKind {
+        // A name after the quote that isn't closed by another one right
+        // after it is a label, like `'outer`.
+        let mut rest = self.source.clone().map(|(_, c)| c);
+        if rest.next().is_some_and(is_ident1) && rest.find(|&c| !is_ident2(c)) != Some('\'') {
+            while is_ident2(self.peek()) && !self.reached_eof() {
+                self.consume();
+            }
+            return TokenKind::Label;
+        }
+
+        while self.peek() != '\'' {
+            if self.reached_eof() || self.peek() == '\n' {
+                return self.error(start, Code::UnterminatedChar, "unterminated char literal");
+            }
+
+            if self.consume() == '\\' && self.peek() != '\n' {
+                self.consume();
+            }
+        }
+
+        self.consume();
+        TokenKind::Char
+    }
+
+    fn digits(&mut self) {
         while self.peek().is_ascii_digit() && !self.reached_eof() {
             self.consume();
         }
+    }
+
+    fn number(&mut self, start: u32) -> TokenKind {
+        // TODO: Handle more complex number representations.
+        self.digits();
+
+        // A fraction needs a digit right after the dot, which keeps
+        // ranges like `0..10` intact.
+        if self.peek() == '.' && self.peek2().is_ascii_digit() {
+            self.consume();
+            self.digits();
+        }
+
+        if matches!(self.peek(), 'e' | 'E') {
+            self.consume();
+            if matches!(self.peek(), '+' | '-') {
+                self.consume();
+            }
+            if !self.peek().is_ascii_digit() {
+                return self.error(
+                    start,
+                    Code::MissingExponentDigits,
+                    "expected digits in exponent",
+                );
+            }
+            self.digits();
+        }
+
+        // A type suffix such as `u8` is part of the literal. It is
+        // validated by the parser.
+        if is_ident1(self.peek()) {
+            while is_ident2(self.peek()) && !self.reached_eof() {
+                self.consume();
+            }
+        }
 
         TokenKind::Number
     }
@@ -297,15 +685,14 @@ impl<'src> Lexer<'src> {
         let c = self.consume();
         if c == EOF_CHAR {
             self.previous = Eof;
-            return Token {
-                kind: Eof,
-                span: SourceSpan::from(start..start),
-            };
+            return Token::eof(start);
         };
 
         let kind = match c {
+            // A lone `_` is a wildcard rather than a name.
+            '_' if !is_ident2(self.peek()) => Underscore,
             c if is_ident1(c) => self.name(c),
-            c if c.is_ascii_digit() => self.number(),
+            c if c.is_ascii_digit() => self.number(start),
 
             '(' => LeftParen,
             ')' => RightParen,
@@ -314,12 +701,48 @@ impl<'src> Lexer<'src> {
             '[' => LeftBracket,
             ']' => RightBracket,
             '+' => self.match2('=', PlusEq, '+', PlusPlus, Plus),
-            '-' => self.match2('=', MinusEq, '-', MinusMinus, Minus),
-            '*' => self.match1('=', StarEq, Star),
+            '-' => match self.peek() {
+                '=' => {
+                    self.consume();
+                    MinusEq
+                }
+                '-' => {
+                    self.consume();
+                    MinusMinus
+                }
+                '>' => {
+                    self.consume();
+                    Arrow
+                }
+                _ => Minus,
+            },
+            // `a * *b` needs the space to not be a power.
+            '*' => {
+                if self.peek() == '*' {
+                    self.consume();
+                    self.match1('=', StarStarEq, StarStar)
+                } else {
+                    self.match1('=', StarEq, Star)
+                }
+            }
             '/' => self.match1('=', SlashEq, Slash),
             '%' => self.match1('=', PercentEq, Percent),
-            '&' => self.match2('=', AndEq, '&', AndAnd, And),
-            '|' => self.match2('=', OrEq, '|', OrOr, Or),
+            '&' => {
+                if self.peek() == '&' {
+                    self.consume();
+                    self.match1('=', AndAndEq, AndAnd)
+                } else {
+                    self.match1('=', AndEq, And)
+                }
+            }
+            '|' => {
+                if self.peek() == '|' {
+                    self.consume();
+                    self.match1('=', OrOrEq, OrOr)
+                } else {
+                    self.match1('=', OrEq, Or)
+                }
+            }
             '^' => self.match1('=', CaretEq, Caret),
             '<' => {
                 if self.peek() == '<' {
@@ -337,16 +760,31 @@ impl<'src> Lexer<'src> {
                     self.match1('=', GtEq, Gt)
                 }
             }
-            '=' => self.match1('=', EqEq, Eq),
+            '=' => self.match2('=', EqEq, '>', FatArrow, Eq),
             '!' => self.match1('=', BangEq, Bang),
             '~' => Tilde,
-            '.' => Dot,
-            ':' => Colon,
+            // Lexing is greedy, so `...` becomes `..` followed by `.`.
+            '.' => {
+                if self.peek() == '.' {
+                    self.consume();
+                    self.match1('=', DotDotEq, DotDot)
+                } else {
+                    Dot
+                }
+            }
+            ':' => self.match1(':', ColonColon, Colon),
             ',' => Comma,
+            '?' => Question,
+            '#' => Pound,
             ';' => Semicolon,
-            '"' => self.string(),
+            '"' => self.string(start),
+            '\'' => self.char(start),
 
-            _ => Error,
+            c => self.error(
+                start,
+                Code::UnexpectedCharacter,
+                format!("unexpected character `{c}`"),
+            ),
         };
         self.previous = kind;
 
@@ -370,3 +808,396 @@ impl<'src> Iterator for Lexer<'src> {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Parser, ast::ItemKind};
+
+    // Lexes `source` and gets the kinds of all tokens, which must come
+    // without any diagnostics.
+    fn kinds(source: &str) -> Vec<TokenKind> {
+        let mut lexer = Lexer::new(source);
+        let kinds = lexer.by_ref().map(Token::kind).collect();
+        assert!(lexer.diagnostics().is_empty(), "{source}");
+        kinds
+    }
+
+    #[test]
+    fn leading_bom() {
+        let source = "\u{FEFF}fn main() {}";
+        let (ast, diagnostics) = Parser::new(source).parse();
+        assert!(diagnostics.is_empty(), "{diagnostics:?}");
+        assert_eq!(ast.items.len(), 1);
+
+        // Spans still count the three bytes of the BOM.
+        let (tokens, _) = Lexer::tokenize(source);
+        assert_eq!(tokens[0].kind(), TokenKind::Fn);
+        assert_eq!(tokens[0].span().start().offset(), 3);
+
+        let (_, diagnostics) = Lexer::tokenize("a\u{FEFF}");
+        assert_eq!(diagnostics.len(), 1);
+    }
+
+    #[test]
+    fn arrow() {
+        use TokenKind::*;
+        assert_eq!(
+            kinds("fn f() -> int {}"),
+            [
+                Fn, Identifier, LeftParen, RightParen, Arrow, Identifier, LeftBrace, RightBrace,
+                Semicolon
+            ],
+        );
+        assert_eq!(
+            kinds("a - -b"),
+            [Identifier, Minus, Minus, Identifier, Semicolon],
+        );
+        assert_eq!(
+            kinds("a-->b"),
+            [Identifier, MinusMinus, Gt, Identifier, Semicolon],
+        );
+    }
+
+    #[test]
+    fn fat_arrow() {
+        use TokenKind::*;
+        assert_eq!(
+            kinds("a => b"),
+            [Identifier, FatArrow, Identifier, Semicolon]
+        );
+        assert_eq!(kinds("a = >b"), [Identifier, Eq, Gt, Identifier, Semicolon]);
+        assert_eq!(kinds("a == b"), [Identifier, EqEq, Identifier, Semicolon]);
+    }
+
+    #[test]
+    fn ranges() {
+        use TokenKind::*;
+        assert_eq!(kinds("0..10"), [Number, DotDot, Number, Semicolon]);
+        assert_eq!(kinds("0..=10"), [Number, DotDotEq, Number, Semicolon]);
+        assert_eq!(kinds("a.b"), [Identifier, Dot, Identifier, Semicolon]);
+        assert_eq!(
+            kinds("a..b.c"),
+            [Identifier, DotDot, Identifier, Dot, Identifier, Semicolon],
+        );
+        assert_eq!(
+            kinds("a...b"),
+            [Identifier, DotDot, Dot, Identifier, Semicolon]
+        );
+    }
+
+    #[test]
+    fn path_separator() {
+        use TokenKind::*;
+        assert_eq!(
+            kinds("a::b::c"),
+            [
+                Identifier, ColonColon, Identifier, ColonColon, Identifier, Semicolon
+            ],
+        );
+        assert_eq!(
+            kinds("a: :b"),
+            [Identifier, Colon, Colon, Identifier, Semicolon]
+        );
+    }
+
+    #[test]
+    fn question_mark() {
+        use TokenKind::*;
+        // A line ending in `?` gets an implicit `;`, like one ending in `++`.
+        assert_eq!(
+            kinds("x?\ny"),
+            [Identifier, Question, Semicolon, Identifier, Semicolon],
+        );
+        assert_eq!(
+            kinds("x ? y : z"),
+            [
+                Identifier, Question, Identifier, Colon, Identifier, Semicolon
+            ],
+        );
+    }
+
+    #[test]
+    fn crlf_line_endings() {
+        let crlf = "let a = 1\r\nlet b = a\r\n";
+        let lf = "let a = 1\nlet b = a\n";
+        assert_eq!(kinds(crlf), kinds(lf));
+
+        // `b` is in the same place on the second line of both.
+        for source in [crlf, lf] {
+            let (tokens, _) = Lexer::tokenize(source);
+            assert_eq!(&source[tokens[6].span()], "b");
+            let start = tokens[6].span().start();
+            assert_eq!(start.as_line_and_column(source), (2, 5));
+        }
+    }
+
+    #[test]
+    fn auto_semicolon_modes() {
+        use TokenKind::*;
+        let source = "let a = 1\nf(a)\n";
+        assert_eq!(
+            kinds(source),
+            [
+                Let, Identifier, Eq, Number, Semicolon, Identifier, LeftParen, Identifier,
+                RightParen, Semicolon
+            ],
+        );
+        let kinds: Vec<_> = Lexer::new(source)
+            .without_auto_semicolons()
+            .map(Token::kind)
+            .collect();
+        assert_eq!(
+            kinds,
+            [
+                Let, Identifier, Eq, Number, Identifier, LeftParen, Identifier, RightParen
+            ],
+        );
+    }
+
+    #[test]
+    fn edge_cases_never_panic() {
+        // The checks of the fuzz target, on inputs that end right inside
+        // a token or start with one that takes an implicit `;`.
+        let sources = [
+            "",
+            "\n",
+            "++\n",
+            ";",
+            "\u{FEFF}",
+            "\u{FEFF}\n",
+            "a\u{FEFF}",
+            "'",
+            "'\\",
+            "'\\u{",
+            "'\\u{110000}'",
+            "\"",
+            "\"\\",
+            "\"\\x",
+            "/*",
+            "/* /*",
+            "//",
+            "1e",
+            "1e+",
+            "0x",
+            "1.",
+            "1u",
+            "\u{FFFD}\u{FFFD}",
+            "é",
+            "\r",
+            "\r\n",
+            "..=",
+            "'a",
+            "'a:",
+        ];
+        for source in sources {
+            let (tokens, _) = Lexer::tokenize(source);
+            assert_eq!(
+                tokens.last().map(|t| t.kind()),
+                Some(TokenKind::Eof),
+                "{source:?}"
+            );
+
+            let mut end = 0;
+            for token in &tokens {
+                let span = token.span();
+                assert!(end <= span.start().offset(), "{source:?}");
+                assert!(span.text(source).is_some(), "{source:?}");
+                end = span.end().offset();
+            }
+
+            for _ in Lexer::new(source).without_auto_semicolons() {}
+        }
+    }
+
+    #[test]
+    fn source_length_limit() {
+        assert_eq!(check_len(10, 10), Ok(()));
+        let error = check_len(11, 10).unwrap_err();
+        assert_eq!(error.source_len(), 11);
+        assert_eq!(
+            error.to_string(),
+            "source code of 11 bytes is too large, it must be smaller than 4GiB",
+        );
+
+        assert_eq!(check_len(MAX_SOURCE_LEN, MAX_SOURCE_LEN), Ok(()));
+        assert!(check_len(MAX_SOURCE_LEN + 1, MAX_SOURCE_LEN).is_err());
+        assert!(Lexer::try_new("fn main() {}").is_ok());
+    }
+
+    #[test]
+    fn resume_mid_file() {
+        let source = "fn f() {\n    let a = 1 /* c */\n    a + \"s\"\n}\n";
+        let full: Vec<_> = Lexer::new(source).collect();
+
+        for skip in 0..full.len() {
+            let mut lexer = Lexer::new(source);
+            lexer.by_ref().take(skip).for_each(drop);
+            let state = lexer.state();
+            let resumed: Vec<_> = Lexer::resume(source, state).collect();
+            assert_eq!(resumed, full[skip..], "resumed after {skip} tokens");
+        }
+
+        // An edit after the offset of the state leaves it valid.
+        let mut lexer = Lexer::new(source);
+        lexer.by_ref().take(9).for_each(drop);
+        let state = lexer.state();
+        let offset = state.offset() as usize;
+        let edited = format!("{} + 2 // edited\n}}\n", &source[..offset]);
+        let resumed: Vec<_> = Lexer::resume(&edited, state).map(Token::kind).collect();
+        assert_eq!(resumed, kinds(&edited)[9..]);
+    }
+
+    #[test]
+    fn one_semicolon_per_line_break() {
+        use TokenKind::*;
+        assert_eq!(
+            kinds("a\n\n\nb"),
+            [Identifier, Semicolon, Identifier, Semicolon]
+        );
+        assert_eq!(
+            kinds("a\r\n\r\n\r\nb"),
+            [Identifier, Semicolon, Identifier, Semicolon]
+        );
+        // Comments after the token or on the lines in between don't add any.
+        for source in [
+            "a // note\nb",
+            "a /* note */\nb",
+            "a\n// note\n\n/* note */\nb",
+        ] {
+            assert_eq!(
+                kinds(source),
+                [Identifier, Semicolon, Identifier, Semicolon],
+                "{source:?}",
+            );
+        }
+
+        // The `;` sits at the first line break, after the comment.
+        let (tokens, _) = Lexer::tokenize("a // note\n\nb");
+        assert_eq!(tokens[1].span(), SourceSpan::new(9, 9));
+    }
+
+    #[test]
+    fn expression_ends_before_line_break() {
+        use TokenKind::*;
+        assert_eq!(
+            kinds("f(x)?\nlet y = 1"),
+            [
+                Identifier, LeftParen, Identifier, RightParen, Question, Semicolon, Let,
+                Identifier, Eq, Number, Semicolon
+            ],
+        );
+        assert_eq!(
+            kinds("let c = 'c'\nc"),
+            [Let, Identifier, Eq, Char, Semicolon, Identifier, Semicolon],
+        );
+
+        // `?` isn't parsed yet, but a char literal ends a statement.
+        let source = "fn f() {\n    let a = 'c'\n    let b = a\n    b\n}";
+        let (ast, diagnostics) = Parser::new(source).parse();
+        assert!(diagnostics.is_empty(), "{diagnostics:?}");
+        let ItemKind::Function(function) = &ast.items[0].kind else {
+            panic!("expected a function");
+        };
+        assert_eq!(function.block.stmts.len(), 2);
+    }
+
+    #[test]
+    fn multi_line_strings() {
+        use TokenKind::*;
+        let source = "let s = \"one\ntwo\"\nlet t = s";
+        assert_eq!(
+            kinds(source),
+            [
+                Let, Identifier, Eq, String, Semicolon, Let, Identifier, Eq, Identifier, Semicolon
+            ],
+        );
+        let (tokens, _) = Lexer::tokenize(source);
+        assert_eq!(&source[tokens[3].span()], "\"one\ntwo\"");
+        let t = tokens[6].span().start();
+        assert_eq!(t.as_line_and_column(source), (3, 5));
+
+        // Diagnostics after the string point at the right line.
+        let source = "fn f() {\n    let s = \"one\n    two\"\n    let = 1\n}";
+        let (_, diagnostics) = Parser::new(source).parse();
+        assert_eq!(diagnostics.len(), 1, "{diagnostics:?}");
+        assert_eq!(&source[diagnostics[0].span()], "=");
+        assert_eq!(
+            diagnostics[0].span().start().as_line_and_column(source),
+            (4, 9)
+        );
+    }
+
+    #[test]
+    fn bulk_skipping_matches_decoding_every_char() {
+        // Fragments that take both the bulk and the char-based paths,
+        // including whitespace and comments around multi-byte chars.
+        let fragments = [
+            " ",
+            "    ",
+            "\t",
+            "\u{B}",
+            "\u{C}",
+            "\n",
+            "\r\n",
+            "\r",
+            "\u{A0}",
+            "\u{2003}",
+            "// note",
+            "//é 😀",
+            "///",
+            "/* é */",
+            "/*",
+            "\0",
+            "a",
+            "é",
+            "1",
+            "+",
+            "(",
+            ")",
+            "{",
+            "}",
+            "\"s t\"",
+            "'c'",
+            "++",
+            "fn",
+            "@",
+        ];
+        // Gets the tokens, diagnostics and comments of `source`.
+        fn lex(source: &str, auto_semicolons: bool) -> impl PartialEq + fmt::Debug {
+            let mut lexer = Lexer::new(source);
+            if !auto_semicolons {
+                lexer = lexer.without_auto_semicolons();
+            }
+            let tokens: Vec<_> = lexer.by_ref().collect();
+            (
+                tokens,
+                lexer.diagnostics().to_vec(),
+                lexer.comments().to_vec(),
+            )
+        }
+
+        // A xorshift64* generator, so every run checks the same inputs.
+        let mut state = 1_u64;
+        let mut random = |n: usize| {
+            state ^= state >> 12;
+            state ^= state << 25;
+            state ^= state >> 27;
+            (state.wrapping_mul(0x2545_F491_4F6C_DD1D) >> 32) as usize % n
+        };
+        for _ in 0..2000 {
+            let mut source = String::new();
+            for _ in 0..random(24) {
+                source.push_str(fragments[random(fragments.len())]);
+            }
+
+            for auto_semicolons in [true, false] {
+                let bulk = lex(&source, auto_semicolons);
+                DECODE_EVERY_CHAR.set(true);
+                let scalar = lex(&source, auto_semicolons);
+                DECODE_EVERY_CHAR.set(false);
+                assert!(bulk == scalar, "{source:?}: {bulk:?} != {scalar:?}");
+            }
+        }
+    }
+}
@@ -1,4 +1,6 @@
-use crate::diagnostic::SourceSpan;
+use std::fmt;
+
+use crate::diagnostic::{SourceLocation, SourceSpan};
 
 /// Represents a lexeme of the Serqlane language.
 ///
@@ -21,6 +23,45 @@ impl Token {
     pub fn span(self) -> SourceSpan {
         self.span
     }
+
+    /// Creates a [`TokenKind::Eof`] token for the end of a source that is
+    /// `len` bytes long, with an empty span right there.
+    ///
+    /// The [`Lexer`](super::Lexer) does not yield this token as an
+    /// [`Iterator`], but consumers may need one to stand for the end.
+    pub fn eof(len: u32) -> Self {
+        Self {
+            kind: TokenKind::Eof,
+            span: SourceSpan::new(len, len),
+        }
+    }
+}
+
+/// Finds the token under a byte offset, such as the cursor in an editor.
+///
+/// `tokens` must be ordered as produced by the lexer. The token whose
+/// span contains `offset` is returned. If there is none, the token that
+/// ends right at `offset` is returned instead, so a cursor placed just
+/// after an identifier still finds it. On the boundary between two
+/// adjacent tokens, the one that starts there wins.
+///
+/// Tokens without any text, like implicit semicolons, are never found.
+/// An offset in whitespace or a comment that doesn't touch any token
+/// yields `None`.
+pub fn token_at(tokens: &[Token], offset: u32) -> Option<Token> {
+    let idx = tokens.partition_point(|t| t.span.end().offset() <= offset);
+    if let Some(&token) = tokens.get(idx)
+        && token.span.contains(SourceLocation::new(offset))
+    {
+        return Some(token);
+    }
+
+    tokens[..idx]
+        .iter()
+        .rev()
+        .take_while(|t| t.span.end().offset() == offset)
+        .find(|t| !t.span.is_empty())
+        .copied()
 }
 
 /// A low-level description of the types of tokens in a program.
@@ -45,6 +86,8 @@ pub enum TokenKind {
     Minus,
     /// `*`
     Star,
+    /// `**`
+    StarStar,
     /// `/`
     Slash,
     /// `%`
@@ -65,6 +108,8 @@ pub enum TokenKind {
     MinusEq,
     /// `*=`
     StarEq,
+    /// `**=`
+    StarStarEq,
     /// `/=`
     SlashEq,
     /// `%=`
@@ -73,6 +118,10 @@ pub enum TokenKind {
     AndEq,
     /// `|=`
     OrEq,
+    /// `&&=`
+    AndAndEq,
+    /// `||=`
+    OrOrEq,
     /// `^=`
     CaretEq,
     /// `<<=`
@@ -107,20 +156,42 @@ pub enum TokenKind {
     Tilde,
     /// `.`
     Dot,
+    /// `..`
+    DotDot,
+    /// `..=`
+    DotDotEq,
     /// `:`
     Colon,
+    /// `::`
+    ColonColon,
     /// `,`
     Comma,
     /// `;`
     Semicolon,
+    /// `?`
+    Question,
+    /// `->`
+    Arrow,
+    /// `=>`
+    FatArrow,
+    /// `#`
+    Pound,
+    /// `_`
+    Underscore,
 
     /// An identifier.
     Identifier,
+    /// A label, like `'outer`.
+    Label,
     /// A string literal.
     String,
+    /// A char literal.
+    Char,
     /// A number literal.
     Number,
 
+    /// `as`
+    As,
     /// `break`
     Break,
     /// `const`
@@ -139,16 +210,32 @@ pub enum TokenKind {
     Fn,
     /// `if`
     If,
+    /// `impl`
+    Impl,
+    /// `in`
+    In,
     /// `let`
     Let,
+    /// `loop`
+    Loop,
+    /// `match`
+    Match,
+    /// `mod`
+    Mod,
     /// `mut`
     Mut,
     /// `pub`
     Pub,
     /// `return`
     Return,
+    /// `struct`
+    Struct,
+    /// `trait`
+    Trait,
     /// `true`
     True,
+    /// `type`
+    Type,
     /// `while`
     While,
 
@@ -157,3 +244,305 @@ pub enum TokenKind {
     /// End of input was reached.
     Eof,
 }
+
+/// A coarse classification of [`TokenKind`]s, as needed for syntax
+/// highlighting.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum TokenCategory {
+    /// A keyword, including `true` and `false`.
+    Keyword,
+    /// An operator on values, like `+`, `<=`, `+=`, `..` or `?`.
+    Operator,
+    /// A delimiter or separator, like `(`, `,`, `.` or `->`.
+    Punctuation,
+    /// A string, char or number literal.
+    Literal,
+    /// An identifier or a label.
+    Identifier,
+    /// A token that is not part of the program itself, which is the end
+    /// of input or text that could not be lexed.
+    Trivia,
+}
+
+impl TokenKind {
+    /// All keyword kinds recognized by the lexer.
+    ///
+    /// This must be kept in sync with the keyword trie in `keywords.rs`.
+    pub const ALL_KEYWORDS: &[TokenKind] = &[
+        TokenKind::As,
+        TokenKind::Break,
+        TokenKind::Const,
+        TokenKind::Continue,
+        TokenKind::Else,
+        TokenKind::Enum,
+        TokenKind::False,
+        TokenKind::For,
+        TokenKind::Fn,
+        TokenKind::If,
+        TokenKind::Impl,
+        TokenKind::In,
+        TokenKind::Let,
+        TokenKind::Loop,
+        TokenKind::Match,
+        TokenKind::Mod,
+        TokenKind::Mut,
+        TokenKind::Pub,
+        TokenKind::Return,
+        TokenKind::Struct,
+        TokenKind::Trait,
+        TokenKind::True,
+        TokenKind::Type,
+        TokenKind::While,
+    ];
+
+    /// Gets the canonical source spelling of this token kind.
+    ///
+    /// Kinds without a fixed spelling, such as [`TokenKind::Identifier`],
+    /// return a short description of what they represent instead.
+    pub fn as_str(self) -> &'static str {
+        use TokenKind::*;
+        match self {
+            LeftParen => "(",
+            RightParen => ")",
+            LeftBrace => "{",
+            RightBrace => "}",
+            LeftBracket => "[",
+            RightBracket => "]",
+
+            Plus => "+",
+            Minus => "-",
+            Star => "*",
+            StarStar => "**",
+            Slash => "/",
+            Percent => "%",
+            And => "&",
+            Or => "|",
+            Caret => "^",
+            Shl => "<<",
+            Shr => ">>",
+            PlusEq => "+=",
+            MinusEq => "-=",
+            StarEq => "*=",
+            StarStarEq => "**=",
+            SlashEq => "/=",
+            PercentEq => "%=",
+            AndEq => "&=",
+            OrEq => "|=",
+            AndAndEq => "&&=",
+            OrOrEq => "||=",
+            CaretEq => "^=",
+            ShlEq => "<<=",
+            ShrEq => ">>=",
+            AndAnd => "&&",
+            OrOr => "||",
+            PlusPlus => "++",
+            MinusMinus => "--",
+            Lt => "<",
+            Gt => ">",
+            Eq => "=",
+            Bang => "!",
+            EqEq => "==",
+            BangEq => "!=",
+            LtEq => "<=",
+            GtEq => ">=",
+            Tilde => "~",
+            Dot => ".",
+            DotDot => "..",
+            DotDotEq => "..=",
+            Colon => ":",
+            ColonColon => "::",
+            Comma => ",",
+            Semicolon => ";",
+            Question => "?",
+            Arrow => "->",
+            FatArrow => "=>",
+            Pound => "#",
+            Underscore => "_",
+
+            Identifier => "identifier",
+            Label => "label",
+            String => "string literal",
+            Char => "char literal",
+            Number => "number literal",
+
+            As => "as",
+            Break => "break",
+            Const => "const",
+            Continue => "continue",
+            Else => "else",
+            Enum => "enum",
+            False => "false",
+            For => "for",
+            Fn => "fn",
+            If => "if",
+            Impl => "impl",
+            In => "in",
+            Let => "let",
+            Loop => "loop",
+            Match => "match",
+            Mod => "mod",
+            Mut => "mut",
+            Pub => "pub",
+            Return => "return",
+            Struct => "struct",
+            Trait => "trait",
+            True => "true",
+            Type => "type",
+            While => "while",
+
+            Error => "error",
+            Eof => "end of file",
+        }
+    }
+
+    /// Gets the [`TokenCategory`] this token kind belongs to.
+    pub fn category(self) -> TokenCategory {
+        use TokenKind::*;
+        // No wildcard here, so that every new kind must be given a
+        // category.
+        match self {
+            LeftParen | RightParen | LeftBrace | RightBrace | LeftBracket | RightBracket | Dot
+            | Colon | ColonColon | Comma | Semicolon | Arrow | FatArrow | Pound | Underscore => {
+                TokenCategory::Punctuation
+            }
+
+            Plus | Minus | Star | StarStar | Slash | Percent | And | Or | Caret | Shl | Shr
+            | PlusEq | MinusEq | StarEq | StarStarEq | SlashEq | PercentEq | AndEq | OrEq
+            | AndAndEq | OrOrEq | CaretEq | ShlEq | ShrEq | AndAnd | OrOr | PlusPlus
+            | MinusMinus | Lt | Gt | Eq | Bang | EqEq | BangEq | LtEq | GtEq | Tilde | DotDot
+            | DotDotEq | Question => TokenCategory::Operator,
+
+            Identifier | Label => TokenCategory::Identifier,
+            String | Char | Number => TokenCategory::Literal,
+
+            As | Break | Const | Continue | Else | Enum | False | For | Fn | If | Impl | In
+            | Let | Loop | Match | Mod | Mut | Pub | Return | Struct | Trait | True | Type
+            | While => TokenCategory::Keyword,
+
+            Error | Eof => TokenCategory::Trivia,
+        }
+    }
+
+    /// Checks whether this is one of the [`TokenKind::ALL_KEYWORDS`].
+    pub fn is_keyword(self) -> bool {
+        self.category() == TokenCategory::Keyword
+    }
+
+    /// Checks whether this is an operator, see [`TokenCategory::Operator`].
+    pub fn is_operator(self) -> bool {
+        self.category() == TokenCategory::Operator
+    }
+
+    /// Checks whether this is a string, char or number literal. `true`
+    /// and `false` are keywords instead.
+    pub fn is_literal(self) -> bool {
+        self.category() == TokenCategory::Literal
+    }
+}
+
+/// Prints the same text as [`TokenKind::as_str`], e.g. `(` for
+/// [`TokenKind::LeftParen`] or `number literal` for [`TokenKind::Number`].
+impl fmt::Display for TokenKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lexer::Lexer;
+
+    #[test]
+    fn all_keywords_round_trip() {
+        for &kind in TokenKind::ALL_KEYWORDS {
+            let mut lexer = Lexer::new(kind.as_str());
+            assert_eq!(lexer.next().map(Token::kind), Some(kind));
+            assert!(kind.is_keyword(), "{kind:?}");
+        }
+
+        // The other way around, every keyword in the trie of the lexer
+        // is listed.
+        let trie = include_str!("keywords.rs");
+        let trie = &trie[..trie.find("#[cfg(test)]").unwrap()];
+        let spellings: Vec<_> = trie
+            .split("kw(\"")
+            .skip(1)
+            .map(|rest| &rest[..rest.find('"').unwrap()])
+            .collect();
+        for spelling in &spellings {
+            let kind = Lexer::new(spelling).next().map(Token::kind).unwrap();
+            assert!(TokenKind::ALL_KEYWORDS.contains(&kind), "{spelling}");
+        }
+        assert_eq!(spellings.len(), TokenKind::ALL_KEYWORDS.len());
+    }
+
+    #[test]
+    fn token_at_offsets() {
+        let source = "let  total = a+b\n";
+        let (tokens, _) = Lexer::tokenize(source);
+        let at = |offset| token_at(&tokens, offset).map(|t| &source[t.span()]);
+
+        // The start, middle and end of `total`.
+        assert_eq!(at(5), Some("total"));
+        assert_eq!(at(7), Some("total"));
+        assert_eq!(at(10), Some("total"));
+        // Right after `let`, and in whitespace that touches no token.
+        assert_eq!(at(3), Some("let"));
+        assert_eq!(at(4), None);
+        // Between adjacent tokens, the one that starts there wins.
+        assert_eq!(at(14), Some("+"));
+        assert_eq!(at(15), Some("b"));
+        // The implicit `;` after `b` has no text.
+        assert_eq!(at(16), Some("b"));
+        assert_eq!(at(17), None);
+    }
+
+    #[test]
+    fn display_by_category() {
+        let cases = [
+            (TokenKind::Fn, TokenCategory::Keyword, "fn"),
+            (TokenKind::True, TokenCategory::Keyword, "true"),
+            (TokenKind::AndAnd, TokenCategory::Operator, "&&"),
+            (TokenKind::DotDotEq, TokenCategory::Operator, "..="),
+            (TokenKind::LeftParen, TokenCategory::Punctuation, "("),
+            (TokenKind::Arrow, TokenCategory::Punctuation, "->"),
+            (TokenKind::Number, TokenCategory::Literal, "number literal"),
+            (TokenKind::String, TokenCategory::Literal, "string literal"),
+            (
+                TokenKind::Identifier,
+                TokenCategory::Identifier,
+                "identifier",
+            ),
+            (TokenKind::Label, TokenCategory::Identifier, "label"),
+            (TokenKind::Eof, TokenCategory::Trivia, "end of file"),
+        ];
+        for (kind, category, text) in cases {
+            assert_eq!(kind.category(), category, "{kind:?}");
+            assert_eq!(kind.to_string(), text);
+        }
+    }
+
+    #[test]
+    fn classification() {
+        use TokenKind::*;
+        // Each kind with whether it's a keyword, an operator and a literal.
+        let cases = [
+            (Fn, (true, false, false)),
+            (False, (true, false, false)),
+            (Plus, (false, true, false)),
+            (LtEq, (false, true, false)),
+            (Question, (false, true, false)),
+            (Comma, (false, false, false)),
+            (Arrow, (false, false, false)),
+            (Number, (false, false, true)),
+            (Char, (false, false, true)),
+            (Identifier, (false, false, false)),
+            (Error, (false, false, false)),
+        ];
+        for (kind, classes) in cases {
+            let actual = (kind.is_keyword(), kind.is_operator(), kind.is_literal());
+            assert_eq!(actual, classes, "{kind:?}");
+        }
+    }
+}
@@ -0,0 +1,34 @@
+//! The compiler for the Serqlane programming language.
+//!
+//! Source code is first broken down into tokens by the [`Lexer`], then
+//! turned into an AST of [`ast::Item`]s by the [`Parser`], which the
+//! passes in [`sema`] analyze further. Every stage reports problems as
+//! [`Diagnostic`]s instead of stopping at the first one:
+//!
+//! ```
+//! use serqlane::{Lexer, Parser, TokenKind};
+//!
+//! let source = "fn main() {\n    let x = 1\n}";
+//!
+//! let (tokens, diagnostics) = Lexer::tokenize(source);
+//! assert!(diagnostics.is_empty());
+//! assert_eq!(tokens[0].kind(), TokenKind::Fn);
+//! assert_eq!(&source[tokens[1].span()], "main");
+//!
+//! let (ast, diagnostics) = Parser::new(source).parse();
+//! assert!(diagnostics.is_empty());
+//! assert_eq!(ast.items.len(), 1);
+//! ```
+
+pub mod ast;
+
+pub mod diagnostic;
+pub use diagnostic::{Code, Diagnostic, Label, Severity, SourceSpan};
+
+pub mod lexer;
+pub use lexer::{Lexer, Token, TokenKind};
+
+pub mod parser;
+pub use parser::Parser;
+
+pub mod sema;
@@ -1,16 +1,348 @@
-use std::fs;
+use std::{
+    env, fs,
+    io::{self, IsTerminal},
+    process,
+};
 
-mod ast;
+use serqlane::{
+    Diagnostic, Lexer, Parser, Severity, Token,
+    ast::{Ast, pretty, symbol::Interner},
+    diagnostic::{self, FileId, SourceMap},
+    sema,
+};
 
-mod diagnostic;
+const USAGE: &str = "\
+usage: serqlane [options] <file>...
+       serqlane fmt [options] <file>...
 
-mod lexer;
+`fmt` prints each file in its canonical formatting instead of compiling
+it.
 
-mod parser;
-use parser::*;
+options:
+    --emit=<kind>  what to print for each file, one of `ast` (default),
+                   `ast-json` or `tokens`
+    --no-color     never color diagnostics
+    -h, --help     print this message";
+
+// What the compiler prints once it is done.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Emit {
+    Ast,
+    AstJson,
+    Tokens,
+}
+
+struct Options {
+    format: bool,
+    emit: Emit,
+    no_color: bool,
+    paths: Vec<String>,
+}
+
+enum Command {
+    Help,
+    Compile(Options),
+}
+
+// Parses the command line arguments, without the program name.
+fn parse_args(args: impl IntoIterator<Item = String>) -> Result<Command, String> {
+    let mut options = Options {
+        format: false,
+        emit: Emit::Ast,
+        no_color: false,
+        paths: Vec::new(),
+    };
+
+    let mut args = args.into_iter().peekable();
+    if args.next_if(|arg| arg == "fmt").is_some() {
+        options.format = true;
+    }
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-h" | "--help" => return Ok(Command::Help),
+            "--no-color" => options.no_color = true,
+            // Everything after `--` is a path, even if it looks like an
+            // option.
+            "--" => options.paths.extend(args.by_ref()),
+            _ => {
+                if let Some(kind) = arg.strip_prefix("--emit=") {
+                    options.emit = match kind {
+                        "ast" => Emit::Ast,
+                        "ast-json" if cfg!(feature = "serde") => Emit::AstJson,
+                        "ast-json" => {
+                            return Err("`--emit=ast-json` requires the `serde` feature".into());
+                        }
+                        "tokens" => Emit::Tokens,
+                        _ => return Err(format!("unknown kind of output `{kind}`")),
+                    };
+                } else if arg.starts_with('-') {
+                    return Err(format!("unknown option `{arg}`"));
+                } else {
+                    options.paths.push(arg);
+                }
+            }
+        }
+    }
+
+    if options.paths.is_empty() {
+        return Err("no input files".into());
+    }
+    Ok(Command::Compile(options))
+}
 
 fn main() {
-    let x = fs::read_to_string("x.serq").expect("Cannot find 'x.serq'");
-    let mut parser = Parser::new(&x);
-    println!("{:?}", parser.parse());
+    let options = match parse_args(env::args().skip(1)) {
+        Ok(Command::Compile(options)) => options,
+        Ok(Command::Help) => {
+            println!("{USAGE}");
+            return;
+        }
+        Err(message) => {
+            eprintln!("error: {message}\n\n{USAGE}");
+            process::exit(2);
+        }
+    };
+
+    // Colors are only used when writing to a terminal, and can be
+    // turned off as described at https://no-color.org.
+    let color = !options.no_color
+        && env::var_os("NO_COLOR").is_none_or(|v| v.is_empty())
+        && io::stderr().is_terminal();
+
+    let mut sources = SourceMap::new();
+    let mut failed = false;
+    for path in &options.paths {
+        match fs::read_to_string(path) {
+            Ok(source) => {
+                let file = sources.add_file(path.as_str(), source);
+                failed |= if options.format {
+                    !format(&sources, file, color)
+                } else {
+                    !compile(&sources, file, options.emit, color)
+                };
+            }
+            Err(e) => {
+                eprintln!("error: cannot read `{path}`: {e}");
+                failed = true;
+            }
+        }
+    }
+
+    if failed {
+        process::exit(1);
+    }
+}
+
+// Compiles a single file and prints what `emit` asks for. Returns
+// whether the file compiled without errors.
+fn compile(sources: &SourceMap, file_id: FileId, emit: Emit, color: bool) -> bool {
+    let file = sources.file(file_id);
+
+    // The token stream is dumped as it is lexed, without parsing it.
+    let result = if emit == Emit::Tokens {
+        Lexer::try_new(file.source())
+            .map(|lexer| (Ast::default(), print_tokens(file.source(), lexer)))
+    } else {
+        Parser::try_new(file.source()).map(Parser::parse)
+    };
+    let (mut ast, mut diagnostics) = match result {
+        Ok(result) => result,
+        Err(e) => {
+            eprintln!("error: cannot compile `{}`: {e}", file.name());
+            return false;
+        }
+    };
+    // Semantic analysis expects a well-formed tree, which parser
+    // warnings don't get in the way of.
+    if emit != Emit::Tokens && diagnostics.iter().all(|d| d.severity() != Severity::Error) {
+        diagnostics.extend(analyze(file.source(), &mut ast));
+        diagnostic::dedup(&mut diagnostics);
+    }
+
+    // Warnings alone don't stop compilation.
+    if !report(sources, file_id, &diagnostics, color) {
+        return false;
+    }
+
+    match emit {
+        Emit::Ast => println!("{ast:#?}"),
+        Emit::AstJson => print_json(&ast),
+        Emit::Tokens => {}
+    }
+    true
+}
+
+// Runs the semantic passes over a parsed file and gets their diagnostics.
+//
+// The lints look at the program as it was written, so they run before
+// constant folding replaces expressions with their values.
+fn analyze(source: &str, ast: &mut Ast) -> Vec<Diagnostic> {
+    let mut interner = Interner::new();
+    let (decls, mut diagnostics) = sema::resolve(source, &mut interner, ast);
+    diagnostics.extend(sema::check_mutability(source, ast, &decls));
+    diagnostics.extend(sema::typeck(source, ast));
+    diagnostics.extend(sema::check_unreachable(ast));
+    diagnostics.extend(sema::check_unused(source, ast, &decls));
+    diagnostics.extend(sema::check_precedence(source, ast));
+    diagnostics.extend(sema::fold_constants(ast));
+    diagnostics
+}
+
+// Prints a file in its canonical formatting. Returns whether the file
+// could be parsed without errors.
+fn format(sources: &SourceMap, file_id: FileId, color: bool) -> bool {
+    let file = sources.file(file_id);
+    let source = file.source();
+    let (ast, diagnostics) = match Parser::try_new(source).map(Parser::parse) {
+        Ok(result) => result,
+        Err(e) => {
+            eprintln!("error: cannot format `{}`: {e}", file.name());
+            return false;
+        }
+    };
+    if !report(sources, file_id, &diagnostics, color) {
+        return false;
+    }
+
+    let mut lexer = Lexer::new(source);
+    lexer.by_ref().for_each(drop);
+    print!("{}", pretty::format(source, &ast, lexer.comments()));
+    true
+}
+
+// Prints diagnostics for a file. Returns whether none of them are
+// errors.
+fn report(sources: &SourceMap, file_id: FileId, diagnostics: &[Diagnostic], color: bool) -> bool {
+    let file = sources.file(file_id);
+    let lines = file.lines();
+    for diagnostic in diagnostics {
+        let rendered = if color {
+            diagnostic::render_colored(diagnostic, file.name(), &lines)
+        } else {
+            diagnostic::render(diagnostic, file.name(), &lines)
+        };
+        eprintln!("{rendered}");
+    }
+
+    diagnostics.iter().all(|d| d.severity() != Severity::Error)
+}
+
+// Prints every token on its own line, including implicit semicolons,
+// which have no text.
+fn print_tokens(source: &str, mut lexer: Lexer) -> Vec<Diagnostic> {
+    for token in lexer.by_ref() {
+        println!("{}", describe_token(source, token));
+    }
+
+    lexer.diagnostics().to_vec()
+}
+
+// Describes a token as `KIND @ start..end "text"`.
+fn describe_token(source: &str, token: Token) -> String {
+    let span = token.span();
+    format!(
+        "{:?} @ {}..{} {:?}",
+        token.kind(),
+        span.start().offset(),
+        span.end().offset(),
+        &source[span],
+    )
+}
+
+#[cfg(feature = "serde")]
+fn print_json(ast: &Ast) {
+    println!("{}", serde_json::to_string_pretty(ast).unwrap());
+}
+
+// `parse_args` rejects `--emit=ast-json` without the `serde` feature.
+#[cfg(not(feature = "serde"))]
+fn print_json(_: &Ast) {
+    unreachable!();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn token_lines() {
+        let source = "let s = \"a\\n\"\n";
+        let lines: Vec<_> = Lexer::new(source)
+            .map(|token| describe_token(source, token))
+            .collect();
+        assert_eq!(
+            lines,
+            [
+                r#"Let @ 0..3 "let""#,
+                r#"Identifier @ 4..5 "s""#,
+                r#"Eq @ 6..7 "=""#,
+                r#"String @ 8..13 "\"a\\n\"""#,
+                r#"Semicolon @ 13..13 """#,
+            ],
+        );
+    }
+
+    // Parses arguments separated by whitespace.
+    fn parse(args: &str) -> Result<Command, String> {
+        parse_args(args.split_whitespace().map(str::to_owned))
+    }
+
+    // Parses `args`, which must ask for files to be compiled.
+    fn options(args: &str) -> Options {
+        match parse(args) {
+            Ok(Command::Compile(options)) => options,
+            Ok(Command::Help) => panic!("{args}: unexpected help"),
+            Err(e) => panic!("{args}: {e}"),
+        }
+    }
+
+    #[test]
+    fn arguments() {
+        let o = options("a.serq b.serq");
+        assert!(!o.format && !o.no_color && o.emit == Emit::Ast);
+        assert_eq!(o.paths, ["a.serq", "b.serq"]);
+
+        let o = options("fmt --no-color a.serq");
+        assert!(o.format && o.no_color);
+        assert_eq!(o.paths, ["a.serq"]);
+
+        let o = options("--emit=tokens -- --emit=ast -h");
+        assert!(o.emit == Emit::Tokens);
+        assert_eq!(o.paths, ["--emit=ast", "-h"]);
+
+        // `fmt` is only a command in front of everything else.
+        assert_eq!(options("a.serq fmt").paths, ["a.serq", "fmt"]);
+        assert!(matches!(parse("a.serq --help"), Ok(Command::Help)));
+
+        for (args, error) in [
+            ("", "no input files"),
+            ("fmt", "no input files"),
+            ("-x a.serq", "unknown option `-x`"),
+            ("--emit=hir a.serq", "unknown kind of output `hir`"),
+        ] {
+            assert!(matches!(parse(args), Err(e) if e == error), "{args}");
+        }
+    }
+
+    // Parses `source`, which must be free of syntax errors, and gets the
+    // messages of the diagnostics from the semantic passes.
+    fn analyze_source(source: &str) -> Vec<String> {
+        let (mut ast, diagnostics) = Parser::new(source).parse();
+        assert!(diagnostics.is_empty(), "{source}: {diagnostics:?}");
+        analyze(source, &mut ast)
+            .iter()
+            .map(|d| d.message().to_owned())
+            .collect()
+    }
+
+    #[test]
+    fn lints_see_unfolded_code() {
+        // Folding drops `a`, which is still used in the source.
+        assert!(analyze_source("fn f(a: bool): bool {\n    false && a\n}").is_empty());
+        // Constant operands are only folded after the lints ran.
+        assert_eq!(
+            analyze_source("fn f(): bool {\n    1 & 3 == 1\n}"),
+            ["`&` binds tighter than `==`, which is easy to misread"],
+        );
+    }
 }
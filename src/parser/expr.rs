@@ -1,11 +1,14 @@
+use std::mem;
+
 use super::Parser;
 use crate::{
     ast::{
         Ident,
-        expr::{Expression, Literal, OperatorExpression},
-        stmt::Statement,
+        expr::{ExprId, ExpressionKind, FieldInit, IntType, Literal, OperatorExpression},
+        stmt::{Block, Statement, StatementKind},
     },
-    lexer::TokenKind,
+    diagnostic::{Code, Diagnostic, SourceSpan},
+    lexer::{Token, TokenKind, unescape_string},
 };
 
 // The following implements a simple Pratt parsing system.
@@ -30,7 +33,7 @@ use crate::{
 // encode this information at the type level for easy referencing.
 
 // -, !, ~, *, &
-fn prefix_binding_power(op: TokenKind) -> ((), u8) {
+pub(crate) fn prefix_binding_power(op: TokenKind) -> ((), u8) {
     use TokenKind::*;
     match op {
         Minus | Bang | Tilde | Star | And => ((), 23),
@@ -38,19 +41,37 @@ fn prefix_binding_power(op: TokenKind) -> ((), u8) {
     }
 }
 
-// [, (
-fn postfix_binding_power(op: TokenKind) -> Option<(u8, ())> {
+// [, (, ., ++, --, as
+//
+// Field access binds tightest of all, above calls and indexing. All of
+// them bind tighter than prefix operators, so `-a.b` is `-(a.b)` and
+// `*a.b()` is `*(a.b())`, and chains like `a.b.c` group to the left.
+//
+// Increment and decrement only exist in postfix form, so `++a` is
+// a syntax error rather than a double `+`.
+//
+// `as` is followed by a type rather than an expression, so it is
+// handled as a postfix operator. Like in Rust, it binds tighter than
+// any binary operator but looser than prefix operators, making
+// `a + b as int` a `a + (b as int)` and `-x as u8` a `(-x) as u8`.
+pub(crate) fn postfix_binding_power(op: TokenKind) -> Option<(u8, ())> {
     use TokenKind::*;
     match op {
-        LeftBracket | LeftParen => Some((25, ())),
+        Dot => Some((27, ())),
+        LeftBracket | LeftParen | PlusPlus | MinusMinus => Some((25, ())),
+        As => Some((21, ())),
         _ => None,
     }
 }
 
-// +, -, *, /, %, <<, >>, <, <=, >, >=, ==, !=, &, |, ^, &&, ||, =, +=, *=, -=, /=, %=, <<=, >>=, &=, |=, ^=, .
-fn infix_binding_power(op: TokenKind) -> Option<(u8, u8)> {
+// **, +, -, *, /, %, <<, >>, <, <=, >, >=, ==, !=, &, |, ^, &&, ||, .., ..=, =, +=, *=, -=, /=, %=, <<=, >>=, &=, |=, ^=, &&=, ||=, .
+pub(crate) fn infix_binding_power(op: TokenKind) -> Option<(u8, u8)> {
     use TokenKind::*;
     match op {
+        // Powers group to the right, so `2 ** 3 ** 2` is `2 ** 9`. They
+        // bind looser than prefix operators though, making `-2 ** 2` a
+        // `(-2) ** 2`.
+        StarStar => Some((22, 21)),
         Star | Slash | Percent => Some((19, 20)),
         Plus | Minus => Some((17, 18)),
         Shl | Shr => Some((15, 16)),
@@ -60,109 +81,537 @@ fn infix_binding_power(op: TokenKind) -> Option<(u8, u8)> {
         EqEq | BangEq | Lt | LtEq | Gt | GtEq => Some((7, 8)),
         AndAnd => Some((5, 6)),
         OrOr => Some((3, 4)),
-        Eq | PlusEq | MinusEq | StarEq | SlashEq | PercentEq | AndEq | OrEq | CaretEq | ShlEq
-        | ShrEq => Some((2, 1)),
+        // Ranges bind looser than any other binary operator but tighter
+        // than assignment, so `a = 0..n` assigns the whole range.
+        DotDot | DotDotEq => Some((2, 3)),
+        // Assignments group to the right, so `a.b = c[i] = 0` is
+        // `a.b = (c[i] = 0)`. Field accesses, indexing and dereferences
+        // bind tighter than anything here, keeping places in one piece.
+        Eq | PlusEq | MinusEq | StarEq | StarStarEq | SlashEq | PercentEq | AndEq | OrEq
+        | AndAndEq | OrOrEq | CaretEq | ShlEq | ShrEq => Some((2, 1)),
         _ => None,
     }
 }
 
+// Whether `kind` can be the first token of an expression.
+fn can_start_expression(kind: TokenKind) -> bool {
+    use TokenKind::*;
+    matches!(
+        kind,
+        Identifier
+            | String
+            | Char
+            | Number
+            | True
+            | False
+            | LeftParen
+            | LeftBrace
+            | LeftBracket
+            | Label
+            | Underscore
+            | If
+            | Loop
+            | Minus
+            | Bang
+            | Tilde
+            | Star
+            | StarStar
+            | And
+            | DotDot
+            | DotDotEq
+            | Return
+            | Break
+            | Continue
+    )
+}
+
+// Whether an expression of `kind` names a place that can be assigned
+// to. Errors count as places, since they were reported already.
+fn is_place(kind: &ExpressionKind) -> bool {
+    matches!(
+        kind,
+        ExpressionKind::Ident(_)
+            | ExpressionKind::Field { .. }
+            | ExpressionKind::Index { .. }
+            | ExpressionKind::Operator(OperatorExpression::Dereference { .. })
+            | ExpressionKind::Error
+    )
+}
+
+fn is_comparison(op: TokenKind) -> bool {
+    use TokenKind::*;
+    matches!(op, EqEq | BangEq | Lt | LtEq | Gt | GtEq)
+}
+
 impl<'src> Parser<'src> {
-    pub fn expression(&mut self) -> Expression {
+    pub fn expression(&mut self) -> ExprId {
         self.expression_(0)
     }
 
-    fn call_args(&mut self) -> Box<[Expression]> {
-        self.eat(TokenKind::LeftParen);
+    // Parses an expression that is directly followed by a block.
+    pub(super) fn condition(&mut self) -> ExprId {
+        let outer = mem::replace(&mut self.no_struct_literal, true);
+        let expr = self.expression_(0);
+        self.no_struct_literal = outer;
+        expr
+    }
+
+    // Parses an expression within delimiters, where struct literals are
+    // always allowed.
+    fn nested_expression(&mut self) -> ExprId {
+        let outer = mem::replace(&mut self.no_struct_literal, false);
+        let expr = self.expression_(0);
+        self.no_struct_literal = outer;
+        expr
+    }
+
+    pub(super) fn call_args(&mut self) -> Box<[ExprId]> {
+        self.open(TokenKind::LeftParen, "arguments");
 
         let mut args = Vec::new();
         while !self.at(TokenKind::RightParen) && !self.eof() {
-            let param = self.expression_(0);
-            if !self.at(TokenKind::RightParen) {
-                self.eat(TokenKind::Comma);
+            args.push(self.nested_expression());
+            if !self.list_separator(TokenKind::RightParen) {
+                break;
             }
-            args.push(param);
         }
-        self.eat(TokenKind::RightParen);
+        self.close(TokenKind::RightParen);
 
         args.into_boxed_slice()
     }
 
-    fn index_expr(&mut self) -> Expression {
-        self.eat(TokenKind::LeftBracket);
-        let expr = self.expression_(0);
-        self.eat(TokenKind::RightBracket);
-        expr
+    fn array(&mut self) -> ExpressionKind {
+        self.opened("array");
+        let mut elems = Vec::new();
+        while !self.at(TokenKind::RightBracket) && !self.eof() {
+            let elem = self.nested_expression();
+
+            // `[value; len]`, which can't be confused with an implicit
+            // semicolon right before the closing bracket.
+            if elems.is_empty()
+                && self.at(TokenKind::Semicolon)
+                && self.peek_nth(1) != TokenKind::RightBracket
+            {
+                self.next();
+                let len = self.nested_expression();
+                self.close(TokenKind::RightBracket);
+                return ExpressionKind::ArrayRepeat { value: elem, len };
+            }
+
+            elems.push(elem);
+            if !self.list_separator(TokenKind::RightBracket) {
+                break;
+            }
+        }
+        self.close(TokenKind::RightBracket);
+
+        ExpressionKind::Array(elems.into_boxed_slice())
     }
 
-    pub(super) fn block(&mut self) -> Box<[Statement]> {
-        let mut block = Vec::new();
+    // Parses `Name { field: value, ... }` after the name.
+    fn struct_literal(&mut self, name: Ident) -> ExpressionKind {
+        self.open(TokenKind::LeftBrace, "struct literal");
+
+        let mut fields = Vec::new();
+        while !self.at(TokenKind::RightBrace) && !self.eof() {
+            let name = self.ident();
+            self.eat(TokenKind::Colon);
+            let expr = self.nested_expression();
+            fields.push(FieldInit { name, expr });
+            if !self.list_separator(TokenKind::RightBrace) {
+                break;
+            }
+        }
+        self.close(TokenKind::RightBrace);
+
+        ExpressionKind::StructLiteral {
+            name,
+            fields: fields.into_boxed_slice(),
+        }
+    }
+
+    // Where a block follows, a struct literal can't be told apart from
+    // that block, as in `if p == Point { x: 1 } { ... }`. The name is
+    // taken as a struct literal anyway if what follows parses as one with
+    // at least one field and is followed by another block, but that is
+    // an error. `if x {} {}` stays an `if` followed by a block.
+    fn misplaced_struct_literal(&mut self, name: Ident, start: u32) -> Option<ExpressionKind> {
+        let literal = self.speculate(|p| match p.struct_literal(name) {
+            ExpressionKind::StructLiteral { fields, .. }
+                if fields.is_empty() || !p.at(TokenKind::LeftBrace) =>
+            {
+                None
+            }
+            literal => Some(literal),
+        })?;
+        let message = "a struct literal in front of a block must be put in parentheses";
+        self.error(
+            self.span_from(start),
+            Code::StructLiteralNotAllowed,
+            message,
+        );
+        Some(literal)
+    }
+
+    // Parses after `(`. Only a comma makes a tuple, so `(a)` is just
+    // a parenthesized expression while `(a,)` is a tuple of one. `()`
+    // is the unit literal.
+    fn paren(&mut self, start: u32) -> ExprId {
+        self.opened("parentheses");
+        if self.at(TokenKind::RightParen) {
+            self.close(TokenKind::RightParen);
+            return self.alloc(
+                ExpressionKind::Literal(Literal::Unit),
+                self.span_from(start),
+            );
+        }
+
+        // The parentheses of a grouping become part of its span.
+        let first = self.nested_expression();
+        if !self.at(TokenKind::Comma) {
+            self.close(TokenKind::RightParen);
+            self.exprs[first].span = self.span_from(start);
+            return first;
+        }
+        self.next();
+
+        let mut elems = vec![first];
+        while !self.at(TokenKind::RightParen) && !self.eof() {
+            elems.push(self.nested_expression());
+            if !self.list_separator(TokenKind::RightParen) {
+                break;
+            }
+        }
+        self.close(TokenKind::RightParen);
+
+        let kind = ExpressionKind::Tuple(elems.into_boxed_slice());
+        self.alloc(kind, self.span_from(start))
+    }
+
+    // Parses the comma-separated indices between `[` and `]`, of which
+    // there is at least one.
+    fn index_expr(&mut self) -> Box<[ExprId]> {
+        self.open(TokenKind::LeftBracket, "index");
+
+        let mut indices = vec![self.nested_expression()];
+        while self.list_separator(TokenKind::RightBracket)
+            && !self.at(TokenKind::RightBracket)
+            && !self.eof()
+        {
+            indices.push(self.nested_expression());
+        }
+        self.close(TokenKind::RightBracket);
+
+        indices.into_boxed_slice()
+    }
+
+    // Parses the statements of a block after its opening brace.
+    //
+    // Variables and expression statements must be followed by a `;`,
+    // which is usually the implicit one at the end of their line. Items
+    // that end in a `}`, `for` loops and expressions that end in a block
+    // of their own, like `if`, need none.
+    //
+    // An expression statement that ends the block without an explicit
+    // `;` becomes the block's value. Implicit semicolons are zero-width,
+    // which tells them apart from explicit ones.
+    pub(super) fn block(&mut self) -> Block {
+        // The opening brace was just consumed by the caller.
+        let open = SourceSpan::new(self.prev_end - 1, self.prev_end);
+        self.opened("block");
+        let outer = mem::replace(&mut self.no_struct_literal, false);
+
+        let mut stmts = Vec::new();
+        let mut tail = None;
         while !self.at(TokenKind::RightBrace) && !self.eof() {
             let stmt = self.statement();
-            if !self.at(TokenKind::RightBrace) {
-                self.eat(TokenKind::Semicolon);
+
+            let separated = match &stmt.node {
+                StatementKind::Item(item) => self.needs_separator(item),
+                StatementKind::For { .. } => false,
+                StatementKind::Expression(expr) => !self.exprs[*expr].is_block_like(),
+                StatementKind::Variable { .. } => true,
+            };
+
+            let mut explicit_semicolon = false;
+            if !self.recovering
+                && !self.at(TokenKind::RightBrace)
+                && (separated || self.at(TokenKind::Semicolon))
+            {
+                let semicolon = self.peek_token();
+                explicit_semicolon = self.eat(TokenKind::Semicolon) && !semicolon.span().is_empty();
+            }
+
+            if self.recovering {
+                self.synchronize();
+                explicit_semicolon = true;
+            }
+
+            match stmt.node {
+                StatementKind::Expression(expr)
+                    if !explicit_semicolon && self.at(TokenKind::RightBrace) =>
+                {
+                    tail = Some(expr);
+                }
+                kind => stmts.push(Statement::new(kind, stmt.span)),
+            }
+        }
+        self.close(TokenKind::RightBrace);
+        self.no_struct_literal = outer;
+
+        Block {
+            stmts: stmts.into_boxed_slice(),
+            tail,
+            span: SourceSpan::new(open.start().offset(), self.prev_end),
+        }
+    }
+
+    // Parses a block including its opening brace. If the brace is
+    // missing, an empty block is returned after reporting an error.
+    pub(super) fn braced_block(&mut self) -> Block {
+        if self.eat(TokenKind::LeftBrace) {
+            self.block()
+        } else {
+            Block {
+                stmts: Box::new([]),
+                tail: None,
+                span: SourceSpan::new(self.prev_end, self.prev_end),
             }
-            block.push(stmt);
         }
-        self.eat(TokenKind::RightBrace);
-        block.into_boxed_slice()
     }
 
-    fn expression_(&mut self, mbp: u8) -> Expression {
+    fn if_expr(&mut self) -> ExpressionKind {
+        let cond = self.condition();
+        let then_block = self.braced_block();
+
+        // An `else` on the line after the closing brace is preceded
+        // by an implicit semicolon, which must not end the `if`.
+        if self.at(TokenKind::Semicolon) && self.peek_nth(1) == TokenKind::Else {
+            self.next();
+        }
+
+        let else_block = if self.at(TokenKind::Else) {
+            self.next();
+            let start = self.start();
+            let kind = if self.at(TokenKind::If) {
+                self.next();
+                self.if_expr()
+            } else {
+                ExpressionKind::Block {
+                    label: None,
+                    block: self.braced_block(),
+                }
+            };
+            Some(self.alloc(kind, self.span_from(start)))
+        } else {
+            None
+        };
+
+        ExpressionKind::If {
+            cond,
+            then_block,
+            else_block,
+        }
+    }
+
+    // `return` and `break` may carry a value, but they can also end a
+    // statement on their own, e.g. `{ if c { return } else { 5 } }`.
+    //
+    // Since `return` terminates an expression, a bare `return` at the
+    // end of a line is always followed by an implicit semicolon. Thus,
+    // anything that closes the surrounding construct means no value.
+    fn diverging_value(&mut self) -> Option<ExprId> {
         use TokenKind::*;
+        match self.peek() {
+            Semicolon | Comma | RightParen | RightBracket | RightBrace | Eof => None,
+            _ => Some(self.expression_(0)),
+        }
+    }
+
+    // Parses the end of a range after its `..` or `..=`. Only `..` may
+    // leave it out, as in `a..` or a bare `..`, which takes anything that
+    // cannot start an expression as the end of the range.
+    fn range_end(&mut self, op: TokenKind, rbp: u8) -> Option<ExprId> {
+        let open = op == TokenKind::DotDot
+            && (!can_start_expression(self.peek())
+                || self.no_struct_literal && self.at(TokenKind::LeftBrace));
+        (!open).then(|| self.expression_(rbp))
+    }
 
-        let token = self.next().unwrap();
-        let text = self.text(token.span());
+    // Parses the expression that starts with `token`, other than one in
+    // parentheses, up to where operators take over.
+    fn primary(&mut self, token: Token, start: u32) -> ExpressionKind {
+        use TokenKind::*;
 
-        let mut lhs = match token.kind() {
-            // TODO: Strings, ...
-            Identifier => Expression::Ident(Ident::from(token)),
-            Number => parse_number(text),
-            b @ (True | False) => Expression::Literal(Literal::Bool(b == True)),
-            LeftParen => {
-                let expr = self.expression_(0);
-                self.eat(TokenKind::RightParen);
-                expr
+        match token.kind() {
+            Identifier if !self.no_struct_literal && self.at(LeftBrace) => {
+                self.struct_literal(Ident::from(token))
+            }
+            Identifier if self.at(LeftBrace) => self
+                .misplaced_struct_literal(Ident::from(token), start)
+                .unwrap_or_else(|| ExpressionKind::Ident(Ident::from(token))),
+            Identifier => ExpressionKind::Ident(Ident::from(token)),
+            String => self.string_literal(token),
+            Char => self.char_literal(token),
+            Number => self.number(token, false),
+            b @ (True | False) => ExpressionKind::Literal(Literal::Bool(b == True)),
+            LeftBrace => ExpressionKind::Block {
+                label: None,
+                block: self.block(),
+            },
+            Label => {
+                let label = Some(Ident::new(token.span()));
+                self.eat(Colon);
+                if self.at(Loop) {
+                    self.next();
+                    ExpressionKind::Loop {
+                        label,
+                        body: self.braced_block(),
+                    }
+                } else {
+                    ExpressionKind::Block {
+                        label,
+                        block: self.braced_block(),
+                    }
+                }
+            }
+            LeftBracket => self.array(),
+            If => self.if_expr(),
+            Loop => ExpressionKind::Loop {
+                label: None,
+                body: self.braced_block(),
+            },
+            // A literal that is negated directly may be one larger than
+            // the maximum of its type, as in `-128i8`. Postfix operators
+            // that bind tighter than `-` apply to the literal alone.
+            Minus
+                if self.at(Number)
+                    && postfix_binding_power(self.peek_nth(1))
+                        .is_none_or(|(lbp, ())| lbp < prefix_binding_power(Minus).1) =>
+            {
+                let token = self.next();
+                let literal = self.number(token, true);
+                let literal = self.alloc(literal, token.span());
+                ExpressionKind::Operator(OperatorExpression::prefix(Minus, literal))
             }
-            LeftBrace => Expression::Block(self.block()),
             op @ (Minus | Bang | Tilde | Star | And) => {
                 let ((), rbp) = prefix_binding_power(op);
                 let rhs = self.expression_(rbp);
-                Expression::Operator(OperatorExpression::prefix(op, rhs))
+                ExpressionKind::Operator(OperatorExpression::prefix(op, rhs))
+            }
+            // `**p` is lexed as a single token.
+            StarStar => {
+                let ((), rbp) = prefix_binding_power(Star);
+                let rhs = self.expression_(rbp);
+                let inner = OperatorExpression::prefix(Star, rhs);
+                let span = self.span_from(token.span().start().offset() + 1);
+                let inner = self.alloc(ExpressionKind::Operator(inner), span);
+                ExpressionKind::Operator(OperatorExpression::prefix(Star, inner))
+            }
+            // A range without a start, like `..n`.
+            op @ (DotDot | DotDotEq) => {
+                let (_, rbp) = infix_binding_power(op).unwrap();
+                ExpressionKind::Range {
+                    start: None,
+                    end: self.range_end(op, rbp),
+                    inclusive: op == DotDotEq,
+                }
+            }
+            Underscore => {
+                let message = "`_` can only be used in binding position";
+                self.error(token.span(), Code::UnexpectedToken, message);
+                ExpressionKind::Error
+            }
+            Return => ExpressionKind::Return(self.diverging_value()),
+            Break => ExpressionKind::Break {
+                label: self.at(Label).then(|| Ident::new(self.next().span())),
+                value: self.diverging_value(),
+            },
+            Continue => ExpressionKind::Continue {
+                label: self.at(Label).then(|| Ident::new(self.next().span())),
+            },
+            Match | While => {
+                let what = if token.kind() == Match {
+                    "expressions"
+                } else {
+                    "loops"
+                };
+                self.unsupported(&format!("`{}` {what}", token.kind()), token.span());
+                ExpressionKind::Error
+            }
+            kind => {
+                self.unexpected("expression", kind, token.span());
+                ExpressionKind::Error
             }
-            Return => Expression::Return(Box::new(self.expression_(0))),
-            _ => unimplemented!(),
+        }
+    }
+
+    fn expression_(&mut self, mbp: u8) -> ExprId {
+        use TokenKind::*;
+
+        // Don't consume tokens that close the surrounding construct, so
+        // that it still finds them after a missing expression. Neither
+        // are those that start the next declaration, which recovery would
+        // resume at, as in `let a = 1 +` followed by another `let`.
+        let start = self.start();
+        if let Semicolon | Comma | RightParen | RightBracket | RightBrace | Eof | Pub | Fn
+        | Struct | Const | Type | Mod | Let | Mut = self.peek()
+        {
+            self.unexpected_next("expression");
+            return self.alloc(ExpressionKind::Error, self.span_from(start));
+        }
+        let token = self.next();
+
+        let mut lhs = if token.kind() == LeftParen {
+            self.paren(start)
+        } else {
+            let kind = self.primary(token, start);
+            self.alloc(kind, self.span_from(start))
         };
+        // The span of `lhs` while it is a comparison built by this loop,
+        // as opposed to one in parentheses.
+        let mut comparison = None;
 
         loop {
-            let op = match self.peek() {
-                op @ (Plus | Minus | Star | Slash | Percent | Shl | Shr | And | Or | Caret
-                | EqEq | BangEq | Lt | LtEq | Gt | GtEq | AndAnd | OrOr | Eq | PlusEq
-                | MinusEq | StarEq | SlashEq | PercentEq | ShlEq | ShrEq | AndEq | OrEq
-                | CaretEq | LeftBracket | LeftParen) => op,
-                RightParen | RightBracket | Comma | Semicolon => break,
-                op => panic!("{op:?}"), // Syntax error.
-            };
+            // Tokens without a binding power end the expression and are
+            // left for the caller to deal with.
+            let op = self.peek();
 
             if let Some((lbp, ())) = postfix_binding_power(op) {
                 if lbp < mbp {
                     break;
                 }
 
-                if op == LeftParen {
+                let kind = if op == LeftParen {
                     let params = self.call_args();
-                    lhs = Expression::Call {
-                        func: Box::new(lhs),
-                        params,
-                    };
+                    ExpressionKind::Call { func: lhs, params }
                 } else if op == LeftBracket {
                     let idx = self.index_expr();
-                    lhs = Expression::Index {
-                        cont: Box::new(lhs),
-                        idx: Box::new(idx),
-                    };
+                    ExpressionKind::Index { cont: lhs, idx }
+                } else if op == Dot {
+                    self.next();
+                    let name = self.ident();
+                    if self.at(LeftParen) {
+                        ExpressionKind::MethodCall {
+                            receiver: lhs,
+                            method: name,
+                            args: self.call_args(),
+                        }
+                    } else {
+                        ExpressionKind::Field { expr: lhs, name }
+                    }
+                } else if op == As {
+                    self.next();
+                    ExpressionKind::Cast {
+                        expr: lhs,
+                        typ: self.typ(),
+                    }
                 } else {
                     self.next();
-                }
+                    ExpressionKind::Operator(OperatorExpression::postfix(lhs, op))
+                };
+                lhs = self.alloc(kind, self.span_from(start));
+                comparison = None;
 
                 continue;
             }
@@ -172,10 +621,42 @@ impl<'src> Parser<'src> {
                     break;
                 }
 
-                self.next();
-                let rhs = self.expression_(rbp);
-
-                lhs = Expression::Operator(OperatorExpression::infix(lhs, op, rhs));
+                let token = self.next();
+                // `a < b < c` would compare the `bool` from `a < b` with
+                // `c`, which is hardly ever what was meant.
+                if let Some(span) = comparison
+                    && is_comparison(op)
+                {
+                    let diagnostic =
+                        Diagnostic::error(token.span(), "comparison operators cannot be chained")
+                            .with_code(Code::ChainedComparison)
+                            .with_label(
+                                span,
+                                "split the comparison with `&&`, or put this one in parentheses",
+                            );
+                    self.diagnostics.push(diagnostic);
+                }
+                let kind = if let DotDot | DotDotEq = op {
+                    ExpressionKind::Range {
+                        start: Some(lhs),
+                        end: self.range_end(op, rbp),
+                        inclusive: op == DotDotEq,
+                    }
+                } else {
+                    let rhs = self.expression_(rbp);
+                    let op = OperatorExpression::infix(lhs, op, rhs);
+                    if let OperatorExpression::Assignment { .. }
+                    | OperatorExpression::CompoundAssignment { .. } = op
+                        && !is_place(&self.exprs[lhs].kind)
+                    {
+                        let message = "cannot assign to this expression";
+                        self.error(self.exprs[lhs].span, Code::InvalidAssignmentTarget, message);
+                    }
+                    ExpressionKind::Operator(op)
+                };
+                let span = self.span_from(start);
+                lhs = self.alloc(kind, span);
+                comparison = is_comparison(op).then_some(span);
                 continue;
             }
 
@@ -184,12 +665,947 @@ impl<'src> Parser<'src> {
 
         lhs
     }
+
+    // Replaces the escape sequences between the quotes of a string or
+    // char literal. An invalid one is reported with just its own span.
+    fn unescape_literal(&mut self, span: SourceSpan) -> Option<String> {
+        let text = self.text(span);
+        let start = span.start().offset() + 1;
+        match unescape_string(&text[1..text.len() - 1]) {
+            Ok(value) => Some(value),
+            Err(e) => {
+                let span = SourceSpan::new(start + e.offset() as u32, start + e.end() as u32);
+                self.error(span, Code::InvalidEscape, e.to_string());
+                None
+            }
+        }
+    }
+
+    fn string_literal(&mut self, token: Token) -> ExpressionKind {
+        match self.unescape_literal(token.span()) {
+            Some(value) => ExpressionKind::Literal(Literal::Str(value)),
+            None => ExpressionKind::Error,
+        }
+    }
+
+    // Parses a char literal, which must hold exactly one character once
+    // its escape sequence is replaced.
+    fn char_literal(&mut self, token: Token) -> ExpressionKind {
+        let span = token.span();
+        let Some(value) = self.unescape_literal(span) else {
+            return ExpressionKind::Error;
+        };
+
+        let mut chars = value.chars();
+        match (chars.next(), chars.next()) {
+            (Some(c), None) => ExpressionKind::Literal(Literal::Char(c)),
+            (first, _) => {
+                let message = match first {
+                    None => "empty char literal",
+                    Some(_) => "char literal must hold exactly one character",
+                };
+                self.error(span, Code::InvalidCharLiteral, message);
+                ExpressionKind::Error
+            }
+        }
+    }
+
+    // Parses a number literal. A `negated` literal is the operand of a
+    // `-`, which lets signed types fit one more value.
+    fn number(&mut self, token: Token, negated: bool) -> ExpressionKind {
+        // TODO: Handle more number formats and errors.
+        let span = token.span();
+        let (number, suffix) = split_suffix(self.text(span));
+
+        let typ = match suffix {
+            "" => None,
+            s => {
+                let typ = IntType::from_suffix(s);
+                if typ.is_none() {
+                    self.error(
+                        span,
+                        Code::InvalidNumberSuffix,
+                        format!("invalid suffix `{s}` for number literal"),
+                    );
+                }
+                typ
+            }
+        };
+
+        let literal = if number.contains(['.', 'e', 'E']) {
+            if let Some(typ) = typ {
+                self.error(
+                    span,
+                    Code::FloatWithIntegerSuffix,
+                    format!(
+                        "float literal cannot have integer suffix `{}`",
+                        typ.as_str()
+                    ),
+                );
+            }
+            Literal::Float(number.parse().unwrap())
+        } else {
+            // Literals without a suffix are at most `u64` in size, and
+            // there is no suffix for a larger type.
+            let Ok(value) = number.parse::<u64>() else {
+                let message = match typ {
+                    Some(typ) => format!("integer literal is out of range for `{}`", typ.as_str()),
+                    None => "integer literal is too large for `u64`".to_owned(),
+                };
+                self.error(span, Code::IntegerOutOfRange, message);
+                return ExpressionKind::Error;
+            };
+            let out_of_range = |t: &IntType| {
+                let max = t.max() + (negated && t.is_signed()) as u64;
+                value > max
+            };
+            if let Some(typ) = typ.filter(out_of_range) {
+                self.error(
+                    span,
+                    Code::IntegerOutOfRange,
+                    format!("integer literal is out of range for `{}`", typ.as_str()),
+                );
+            }
+            Literal::Int { value, typ }
+        };
+
+        ExpressionKind::Literal(literal)
+    }
 }
 
-fn parse_number(src: &str) -> Expression {
-    // TODO: Handle more number formats and errors.
-    src.parse::<u64>()
-        .map(Literal::Int)
-        .map(Expression::Literal)
-        .unwrap()
+// Splits a number literal into its numeric part and its type suffix.
+// The lexer guarantees that an exponent always has digits, so every
+// letter after the fraction that isn't an exponent marker starts the
+// suffix.
+fn split_suffix(src: &str) -> (&str, &str) {
+    let bytes = src.as_bytes();
+    let digits = |mut i: usize| {
+        while i < bytes.len() && bytes[i].is_ascii_digit() {
+            i += 1;
+        }
+        i
+    };
+
+    let mut i = digits(0);
+    if bytes.get(i) == Some(&b'.') {
+        i = digits(i + 1);
+    }
+    if matches!(bytes.get(i), Some(b'e' | b'E')) {
+        i += 1;
+        if matches!(bytes.get(i), Some(b'+' | b'-')) {
+            i += 1;
+        }
+        i = digits(i);
+    }
+
+    src.split_at(i)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        Parser,
+        ast::{
+            Ast, ItemKind, Pattern,
+            expr::{
+                CompoundAssignmentOperator, ExprId, ExpressionKind, Exprs, IntType, Literal,
+                OperatorExpression,
+            },
+            stmt::{Block, StatementKind},
+        },
+        diagnostic::{Code, Diagnostic, SourceLocation, SourceSpan},
+        lexer::{Lexer, TokenKind},
+    };
+
+    // Parses `source` as an expression, which must be free of errors.
+    fn parse(source: &str) -> (Exprs, ExprId) {
+        let (exprs, id, diagnostics) = Parser::new(source).parse_expr();
+        assert!(diagnostics.is_empty(), "{source}: {diagnostics:?}");
+        (exprs, id)
+    }
+
+    // Parses `source` as an expression and renders it as an S-expression
+    // that shows how it nests, like `(+ a (* b c))` for `a + b * c`.
+    // Operators are taken from the source between their operands.
+    fn tree(source: &str) -> String {
+        fn node(source: &str, exprs: &Exprs, head: &str, children: &[ExprId]) -> String {
+            let mut out = format!("({head}");
+            for &child in children {
+                out.push(' ');
+                out.push_str(&render(source, exprs, child));
+            }
+            out.push(')');
+            out
+        }
+
+        // Gets the text between two offsets, without any parentheses.
+        fn between(source: &str, start: SourceLocation, end: SourceLocation) -> &str {
+            let text = &source[start.offset() as usize..end.offset() as usize];
+            text.trim_matches(|c: char| c == '(' || c == ')' || c.is_whitespace())
+        }
+
+        fn render(source: &str, exprs: &Exprs, id: ExprId) -> String {
+            let expr = &exprs[id];
+            let span = expr.span;
+            match &expr.kind {
+                ExpressionKind::Operator(op) => match *op {
+                    OperatorExpression::ArithmeticLogical { lhs, rhs, .. }
+                    | OperatorExpression::Comparison { lhs, rhs, .. }
+                    | OperatorExpression::CompoundAssignment { lhs, rhs, .. }
+                    | OperatorExpression::Boolean { lhs, rhs, .. }
+                    | OperatorExpression::Assignment { lhs, rhs } => {
+                        let op = between(source, exprs[lhs].span.end(), exprs[rhs].span.start());
+                        node(source, exprs, op, &[lhs, rhs])
+                    }
+                    OperatorExpression::Negation { expr, .. }
+                    | OperatorExpression::AddressOf { expr }
+                    | OperatorExpression::Dereference { expr } => {
+                        let op = between(source, span.start(), exprs[expr].span.start());
+                        node(source, exprs, op, &[expr])
+                    }
+                    OperatorExpression::Increment { expr }
+                    | OperatorExpression::Decrement { expr } => {
+                        let op = between(source, exprs[expr].span.end(), span.end());
+                        node(source, exprs, op, &[expr])
+                    }
+                },
+                ExpressionKind::Call { func, params } => {
+                    node(source, exprs, "call", &[&[*func], &params[..]].concat())
+                }
+                ExpressionKind::Index { cont, idx } => {
+                    node(source, exprs, "index", &[&[*cont], &idx[..]].concat())
+                }
+                ExpressionKind::Field { expr, name } => {
+                    node(source, exprs, &format!(".{}", &source[name.span]), &[*expr])
+                }
+                ExpressionKind::MethodCall {
+                    receiver,
+                    method,
+                    args,
+                } => {
+                    let head = format!(".{}()", &source[method.span]);
+                    node(source, exprs, &head, &[&[*receiver], &args[..]].concat())
+                }
+                ExpressionKind::Cast { expr, typ } => node(
+                    source,
+                    exprs,
+                    &format!("as {}", &source[typ.span]),
+                    &[*expr],
+                ),
+                ExpressionKind::Range {
+                    start,
+                    end,
+                    inclusive,
+                } => {
+                    let head = if *inclusive { "..=" } else { ".." };
+                    let bounds: Vec<_> = start.iter().chain(end).copied().collect();
+                    node(source, exprs, head, &bounds)
+                }
+                ExpressionKind::Tuple(elems) => node(source, exprs, "tuple", elems),
+                ExpressionKind::Array(elems) => node(source, exprs, "array", elems),
+                _ => source[span].to_owned(),
+            }
+        }
+
+        let (exprs, id) = parse(source);
+        render(source, &exprs, id)
+    }
+
+    // Parses `source`, which must be free of errors and start with a
+    // function, and gets the tree along with the function's block.
+    fn parse_function(source: &str) -> (Ast, Block) {
+        let (ast, diagnostics) = Parser::new(source).parse();
+        assert!(diagnostics.is_empty(), "{source}: {diagnostics:?}");
+        let ItemKind::Function(function) = &ast.items[0].kind else {
+            panic!("expected a function");
+        };
+        let block = function.block.clone();
+        (ast, block)
+    }
+
+    #[test]
+    fn string_and_char_escapes() {
+        let (exprs, id, diagnostics) = Parser::new(r#""a\"b\n\u{41}""#).parse_expr();
+        assert!(diagnostics.is_empty());
+        let ExpressionKind::Literal(Literal::Str(value)) = &exprs[id].kind else {
+            panic!("expected a string literal");
+        };
+        assert_eq!(value, "a\"b\nA");
+
+        // Both kinds of literals reject the same escapes.
+        for source in [r#""\q""#, r"'\q'"] {
+            let (exprs, id, diagnostics) = Parser::new(source).parse_expr();
+            assert!(matches!(exprs[id].kind, ExpressionKind::Error));
+            assert_eq!(diagnostics.len(), 1, "{source}");
+            assert_eq!(diagnostics[0].code(), Some(Code::InvalidEscape));
+            assert_eq!(&source[diagnostics[0].span()], r"\q");
+        }
+    }
+
+    #[test]
+    fn diverging_branch_in_block() {
+        let source = "fn f(c: bool) {\n    let x = { if c { return } else { 5 } }\n}";
+        let (ast, diagnostics) = Parser::new(source).parse();
+        assert!(diagnostics.is_empty(), "{diagnostics:?}");
+
+        let ItemKind::Function(function) = &ast.items[0].kind else {
+            panic!("expected a function");
+        };
+        let StatementKind::Variable { expr, .. } = function.block.stmts[0].node else {
+            panic!("expected a variable");
+        };
+        let ExpressionKind::Block { block, .. } = &ast.exprs[expr].kind else {
+            panic!("expected a block");
+        };
+        let ExpressionKind::If {
+            then_block,
+            else_block: Some(else_block),
+            ..
+        } = &ast.exprs[block.tail.unwrap()].kind
+        else {
+            panic!("expected an `if` with an `else`");
+        };
+        let then_tail = then_block.tail.unwrap();
+        assert!(matches!(
+            ast.exprs[then_tail].kind,
+            ExpressionKind::Return(None)
+        ));
+        assert_eq!(&source[ast.exprs[*else_block].span], "{ 5 }");
+    }
+
+    #[test]
+    fn float_exponents() {
+        for (source, value) in [("1e10", 1e10), ("1.5e-3", 1.5e-3), ("1E+2", 100.0)] {
+            let (exprs, id, diagnostics) = Parser::new(source).parse_expr();
+            assert!(diagnostics.is_empty(), "{source}: {diagnostics:?}");
+            assert!(
+                matches!(exprs[id].kind, ExpressionKind::Literal(Literal::Float(v)) if v == value),
+                "{source}",
+            );
+        }
+
+        for source in ["1e", "1e+"] {
+            let (_, _, diagnostics) = Parser::new(source).parse_expr();
+            assert_eq!(diagnostics.len(), 1, "{source}");
+            assert_eq!(diagnostics[0].code(), Some(Code::MissingExponentDigits));
+            assert_eq!(&source[diagnostics[0].span()], source);
+        }
+
+        // Numbers can't start with a `.`, so this is `.` followed by `5e3`.
+        let (_, _, diagnostics) = Parser::new(".5e3").parse_expr();
+        assert_eq!(diagnostics[0].message(), "expected expression, found `.`");
+    }
+
+    #[test]
+    fn integer_suffixes() {
+        for (source, value, typ) in [("255u8", 255, IntType::U8), ("10i32", 10, IntType::I32)] {
+            let (exprs, id, diagnostics) = Parser::new(source).parse_expr();
+            assert!(diagnostics.is_empty(), "{source}: {diagnostics:?}");
+            assert!(
+                matches!(
+                    exprs[id].kind,
+                    ExpressionKind::Literal(Literal::Int { value: v, typ: Some(t) })
+                        if v == value && t == typ
+                ),
+                "{source}",
+            );
+        }
+
+        for (source, code) in [
+            ("256u8", Code::IntegerOutOfRange),
+            ("1.0u8", Code::FloatWithIntegerSuffix),
+        ] {
+            let (_, _, diagnostics) = Parser::new(source).parse_expr();
+            assert_eq!(diagnostics.len(), 1, "{source}");
+            assert_eq!(diagnostics[0].code(), Some(code), "{source}");
+        }
+    }
+
+    #[test]
+    fn if_else() {
+        let (exprs, id) = parse("if a { 1 }");
+        assert!(matches!(
+            exprs[id].kind,
+            ExpressionKind::If {
+                else_block: None,
+                ..
+            }
+        ));
+
+        let source = "if a { 1 } else if b { 2 } else { 3 }";
+        let (exprs, id) = parse(source);
+        let ExpressionKind::If {
+            else_block: Some(else_if),
+            ..
+        } = exprs[id].kind
+        else {
+            panic!("expected an `if` with an `else`");
+        };
+        assert_eq!(&source[exprs[else_if].span], "if b { 2 } else { 3 }");
+        let ExpressionKind::If {
+            cond,
+            else_block: Some(else_block),
+            ..
+        } = exprs[else_if].kind
+        else {
+            panic!("expected a nested `if` with an `else`");
+        };
+        assert_eq!(&source[exprs[cond].span], "b");
+        assert!(matches!(
+            exprs[else_block].kind,
+            ExpressionKind::Block { .. }
+        ));
+
+        let source = "fn f() {\n    let x = if a { 1 } else { 2 }\n}";
+        let (ast, diagnostics) = Parser::new(source).parse();
+        assert!(diagnostics.is_empty(), "{diagnostics:?}");
+        let ItemKind::Function(function) = &ast.items[0].kind else {
+            panic!("expected a function");
+        };
+        let StatementKind::Variable { expr, .. } = function.block.stmts[0].node else {
+            panic!("expected a variable");
+        };
+        assert_eq!(&source[ast.exprs[expr].span], "if a { 1 } else { 2 }");
+    }
+
+    #[test]
+    fn return_values() {
+        let source = "fn f() {\n    return\n    return 5\n    return a + b\n}";
+        let (ast, block) = parse_function(source);
+        // The last `return` is the tail of the block.
+        let exprs = block.stmts.iter().map(|stmt| match stmt.node {
+            StatementKind::Expression(expr) => expr,
+            _ => panic!("expected an expression statement"),
+        });
+        let values: Vec<_> = exprs
+            .chain(block.tail)
+            .map(|expr| match ast.exprs[expr].kind {
+                ExpressionKind::Return(value) => value.map(|value| &source[ast.exprs[value].span]),
+                _ => panic!("expected `return`"),
+            })
+            .collect();
+        assert_eq!(values, [None, Some("5"), Some("a + b")]);
+    }
+
+    #[test]
+    fn block_expressions() {
+        let source = "{ let a = 1; a + 1 }";
+        let (exprs, id) = parse(source);
+        let ExpressionKind::Block { label: None, block } = &exprs[id].kind else {
+            panic!("expected a block");
+        };
+        assert_eq!(block.stmts.len(), 1);
+        assert!(matches!(
+            block.stmts[0].node,
+            StatementKind::Variable { .. }
+        ));
+        assert_eq!(&source[exprs[block.tail.unwrap()].span], "a + 1");
+
+        let (exprs, id) = parse("{}");
+        let ExpressionKind::Block { block, .. } = &exprs[id].kind else {
+            panic!("expected a block");
+        };
+        assert!(block.stmts.is_empty() && block.tail.is_none());
+    }
+
+    #[test]
+    fn array_literals() {
+        // Gets the text of the elements of an array literal.
+        fn elements(source: &str) -> Vec<&str> {
+            let (exprs, id) = parse(source);
+            let ExpressionKind::Array(elems) = &exprs[id].kind else {
+                panic!("expected an array: {source}");
+            };
+            elems
+                .iter()
+                .map(|&elem| &source[exprs[elem].span])
+                .collect()
+        }
+
+        assert!(elements("[]").is_empty());
+        assert_eq!(elements("[1]"), ["1"]);
+        assert_eq!(elements("[1, 2, 3,]"), ["1", "2", "3"]);
+        assert_eq!(elements("[[1], [2]]"), ["[1]", "[2]"]);
+
+        let (exprs, id) = parse("[0; 5]");
+        assert!(matches!(exprs[id].kind, ExpressionKind::ArrayRepeat { .. }));
+    }
+
+    #[test]
+    fn string_literals() {
+        for (source, value) in [
+            (r#""hello""#, "hello"),
+            (r#""""#, ""),
+            (r#""say \"hi\"""#, r#"say "hi""#),
+        ] {
+            let (exprs, id) = parse(source);
+            let ExpressionKind::Literal(Literal::Str(s)) = &exprs[id].kind else {
+                panic!("expected a string literal: {source}");
+            };
+            assert_eq!(s, value);
+        }
+    }
+
+    #[test]
+    fn identifiers() {
+        let (exprs, id) = parse("x");
+        assert!(matches!(exprs[id].kind, ExpressionKind::Ident(_)));
+        assert_eq!(tree("x + y"), "(+ x y)");
+        assert_eq!(tree("foo(x)"), "(call foo x)");
+    }
+
+    #[test]
+    fn trailing_comma_in_calls() {
+        assert_eq!(tree("f(a, b,)"), "(call f a b)");
+        let (_, _, diagnostics) = Parser::new("f(,)").parse_expr();
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].message(), "expected expression, found `,`");
+    }
+
+    #[test]
+    fn postfix_increment_and_decrement() {
+        assert_eq!(tree("x++"), "(++ x)");
+        assert_eq!(tree("arr[i]++"), "(++ (index arr i))");
+        assert_eq!(tree("a++ - b--"), "(- (++ a) (-- b))");
+
+        // There are no prefix forms.
+        let (_, _, diagnostics) = Parser::new("++x").parse_expr();
+        assert_eq!(diagnostics[0].message(), "expected expression, found `++`");
+    }
+
+    #[test]
+    fn loops() {
+        let (exprs, id) = parse("loop {}");
+        let ExpressionKind::Loop { label: None, body } = &exprs[id].kind else {
+            panic!("expected a loop");
+        };
+        assert!(body.stmts.is_empty() && body.tail.is_none());
+
+        let (exprs, id) = parse("loop { break 5 }");
+        let ExpressionKind::Loop { body, .. } = &exprs[id].kind else {
+            panic!("expected a loop");
+        };
+        let tail = body.tail.unwrap();
+        assert!(matches!(
+            exprs[tail].kind,
+            ExpressionKind::Break {
+                label: None,
+                value: Some(_)
+            }
+        ));
+    }
+
+    #[test]
+    fn casts() {
+        assert_eq!(tree("x as u8"), "(as u8 x)");
+        // `as` binds tighter than any binary operator.
+        assert_eq!(tree("a + b as int"), "(+ a (as int b))");
+        assert_eq!(tree("p as &int"), "(as &int p)");
+    }
+
+    #[test]
+    fn tuples() {
+        assert_eq!(tree("(1, 2, 3)"), "(tuple 1 2 3)");
+        assert_eq!(tree("(1,)"), "(tuple 1)");
+        let (exprs, id) = parse("(1)");
+        assert!(matches!(
+            exprs[id].kind,
+            ExpressionKind::Literal(Literal::Int { value: 1, .. })
+        ));
+    }
+
+    #[test]
+    fn struct_literals() {
+        let source = "Point { x: 1, y: a + b, }";
+        let (exprs, id) = parse(source);
+        let ExpressionKind::StructLiteral { name, fields } = &exprs[id].kind else {
+            panic!("expected a struct literal");
+        };
+        assert_eq!(&source[name.span], "Point");
+        let fields: Vec<_> = fields
+            .iter()
+            .map(|f| (&source[f.name.span], &source[exprs[f.expr].span]))
+            .collect();
+        assert_eq!(fields, [("x", "1"), ("y", "a + b")]);
+
+        assert_eq!(tree("f(Point { x: 1 })"), "(call f Point { x: 1 })");
+
+        // The block of an `if` is never taken for the fields of a literal.
+        let (exprs, id) = parse("if p { x }");
+        let ExpressionKind::If { cond, .. } = exprs[id].kind else {
+            panic!("expected an `if`");
+        };
+        assert!(matches!(exprs[cond].kind, ExpressionKind::Ident(_)));
+    }
+
+    #[test]
+    fn block_and_expect() {
+        let source = "{ let x = 1; x }";
+        let mut parser = Parser::new(source);
+        assert!(parser.expect(TokenKind::LeftBrace).is_some());
+        let block = parser.block();
+        assert!(parser.diagnostics.is_empty(), "{:?}", parser.diagnostics);
+        assert_eq!(&source[block.span], source);
+        assert!(matches!(
+            block.stmts[0].node,
+            StatementKind::Variable { .. }
+        ));
+        assert_eq!(&source[parser.exprs[block.tail.unwrap()].span], "x");
+
+        // Only the implicit `;` at the end of the line is left.
+        assert!(parser.expect(TokenKind::RightBrace).is_none());
+        assert_eq!(parser.diagnostics.len(), 1);
+        assert!(parser.expect(TokenKind::Semicolon).is_some());
+        assert!(parser.eof());
+    }
+
+    #[test]
+    fn spans_cover_operands() {
+        let source = "(a) + b * c";
+        let (exprs, id) = parse(source);
+        assert_eq!(&source[exprs[id].span()], source);
+        let ExpressionKind::Operator(OperatorExpression::ArithmeticLogical { lhs, rhs, .. }) =
+            exprs[id].kind
+        else {
+            panic!("expected a binary operator");
+        };
+        // Parentheses are part of the span of what they enclose.
+        assert_eq!(&source[exprs[lhs].span()], "(a)");
+        assert_eq!(&source[exprs[rhs].span()], "b * c");
+        // Parentheses add no node of their own.
+        assert_eq!(exprs.len(), 5);
+    }
+
+    #[test]
+    fn integer_overflow() {
+        let (exprs, id) = parse("18446744073709551615");
+        assert!(matches!(
+            exprs[id].kind,
+            ExpressionKind::Literal(Literal::Int {
+                value: u64::MAX,
+                typ: None
+            })
+        ));
+
+        let (exprs, id, diagnostics) = Parser::new("18446744073709551616").parse_expr();
+        assert!(matches!(exprs[id].kind, ExpressionKind::Error));
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].code(), Some(Code::IntegerOutOfRange));
+        assert_eq!(
+            diagnostics[0].message(),
+            "integer literal is too large for `u64`"
+        );
+        assert_eq!(diagnostics[0].span(), SourceSpan::new(0, 20));
+
+        // There are no base prefixes, so the letter starts an invalid suffix.
+        let (_, _, diagnostics) = Parser::new("0x").parse_expr();
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].code(), Some(Code::InvalidNumberSuffix));
+    }
+
+    #[test]
+    fn index_expressions() {
+        assert_eq!(tree("a[i]"), "(index a i)");
+        assert_eq!(tree("a[i, j]"), "(index a i j)");
+        assert_eq!(tree("a[i,]"), "(index a i)");
+        assert_eq!(tree("a[b[c], d]"), "(index a (index b c) d)");
+    }
+
+    #[test]
+    fn method_calls() {
+        assert_eq!(tree("a.b()"), "(.b() a)");
+        assert_eq!(tree("a.b(1, 2)"), "(.b() a 1 2)");
+        assert_eq!(tree("a.b().c()"), "(.c() (.b() a))");
+        // Without arguments it stays a field access.
+        assert_eq!(tree("a.b"), "(.b a)");
+    }
+
+    #[test]
+    fn statement_termination() {
+        // Gets the text of the statements and the tail of a block.
+        fn parts(source: &str) -> (Vec<&str>, Option<&str>, Vec<Diagnostic>) {
+            let (exprs, id, diagnostics) = Parser::new(source).parse_expr();
+            let ExpressionKind::Block { block, .. } = &exprs[id].kind else {
+                panic!("expected a block: {source}");
+            };
+            let stmts = block
+                .stmts
+                .iter()
+                .map(|stmt| &source[stmt.span()])
+                .collect();
+            let tail = block.tail.map(|tail| &source[exprs[tail].span]);
+            (stmts, tail, diagnostics)
+        }
+
+        let (stmts, tail, diagnostics) = parts("{ let x = 1; x }");
+        assert!(diagnostics.is_empty(), "{diagnostics:?}");
+        assert_eq!((stmts, tail), (vec!["let x = 1"], Some("x")));
+
+        let (stmts, tail, diagnostics) = parts("{ f(); g() }");
+        assert!(diagnostics.is_empty(), "{diagnostics:?}");
+        assert_eq!((stmts, tail), (vec!["f()"], Some("g()")));
+
+        // Block-like expressions end their statement without a `;`.
+        let (stmts, tail, diagnostics) = parts("{ loop { break } f() }");
+        assert!(diagnostics.is_empty(), "{diagnostics:?}");
+        assert_eq!((stmts, tail), (vec!["loop { break }"], Some("f()")));
+
+        // `while` is rejected, but needs no `;` before the `}` either.
+        let (_, tail, diagnostics) = parts("{ f(); while c { g() } }");
+        assert_eq!(tail, None);
+        assert_eq!(diagnostics.len(), 1, "{diagnostics:?}");
+        assert_eq!(diagnostics[0].code(), Some(Code::UnsupportedSyntax));
+
+        let (_, _, diagnostics) = parts("{ f() g() }");
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].message(), "expected `;`, found identifier");
+    }
+
+    #[test]
+    fn field_access_precedence() {
+        assert_eq!(tree("-a.b"), "(- (.b a))");
+        assert_eq!(tree("a.b.c"), "(.c (.b a))");
+        assert_eq!(tree("a.b()"), "(.b() a)");
+        assert_eq!(tree("*a.b"), "(* (.b a))");
+    }
+
+    #[test]
+    fn exponents() {
+        assert_eq!(tree("2 ** 3 ** 2"), "(** 2 (** 3 2))");
+        assert_eq!(tree("a ** b * c"), "(* (** a b) c)");
+        assert_eq!(tree("x **= 2"), "(**= x 2)");
+        let (exprs, id) = parse("x **= 2");
+        assert!(matches!(
+            exprs[id].kind,
+            ExpressionKind::Operator(OperatorExpression::CompoundAssignment {
+                op: CompoundAssignmentOperator::Pow,
+                ..
+            })
+        ));
+
+        // Only adjacent stars are an exponent.
+        assert_eq!(tree("a * *b"), "(* a (* b))");
+        assert_eq!(tree("a ** b"), "(** a b)");
+        let (tokens, _) = Lexer::tokenize("a * *b");
+        assert_eq!(tokens[1].kind(), TokenKind::Star);
+        assert_eq!(tokens[2].kind(), TokenKind::Star);
+    }
+
+    #[test]
+    fn call_and_infix_spans() {
+        let source = "f(a, (b))";
+        let (exprs, id) = parse(source);
+        assert_eq!(&source[exprs[id].span()], source);
+        let ExpressionKind::Call { func, ref params } = exprs[id].kind else {
+            panic!("expected a call");
+        };
+        assert_eq!(&source[exprs[func].span()], "f");
+        assert_eq!(&source[exprs[params[1]].span()], "(b)");
+
+        let source = "g(x)[0] + a.b(1) * 2";
+        let (exprs, id) = parse(source);
+        assert_eq!(&source[exprs[id].span()], source);
+        let ExpressionKind::Operator(OperatorExpression::ArithmeticLogical { lhs, rhs, .. }) =
+            exprs[id].kind
+        else {
+            panic!("expected a binary operator");
+        };
+        assert_eq!(&source[exprs[lhs].span()], "g(x)[0]");
+        assert_eq!(&source[exprs[rhs].span()], "a.b(1) * 2");
+    }
+
+    #[test]
+    fn chained_comparisons() {
+        let source = "a < b < c";
+        let (_, _, diagnostics) = Parser::new(source).parse_expr();
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].code(), Some(Code::ChainedComparison));
+        assert_eq!(
+            diagnostics[0].message(),
+            "comparison operators cannot be chained"
+        );
+        assert_eq!(&source[diagnostics[0].span()], "<");
+        assert_eq!(&source[diagnostics[0].labels()[0].span], "a < b");
+
+        assert_eq!(tree("(a < b) == c"), "(== (< a b) c)");
+        assert_eq!(tree("a < b && b < c"), "(&& (< a b) (< b c))");
+    }
+
+    #[test]
+    fn char_escapes() {
+        for (source, value) in [(r"'\x41'", 'A'), (r"'\u{1F600}'", '😀')] {
+            let (exprs, id) = parse(source);
+            assert!(
+                matches!(exprs[id].kind, ExpressionKind::Literal(Literal::Char(c)) if c == value),
+                "{source}",
+            );
+        }
+
+        for (source, escape, message) in [
+            (
+                r"'\u{D800}'",
+                r"\u{D800}",
+                r"`\u{D800}` is not a valid unicode character",
+            ),
+            (
+                r"'\x80'",
+                r"\x80",
+                r"`\x80` is out of range, hex escapes go up to `\x7F`",
+            ),
+        ] {
+            let (exprs, id, diagnostics) = Parser::new(source).parse_expr();
+            assert!(matches!(exprs[id].kind, ExpressionKind::Error));
+            assert_eq!(diagnostics.len(), 1, "{source}");
+            assert_eq!(diagnostics[0].code(), Some(Code::InvalidEscape));
+            assert_eq!(diagnostics[0].message(), message);
+            assert_eq!(&source[diagnostics[0].span()], escape);
+        }
+    }
+
+    #[test]
+    fn standalone_expressions() {
+        assert_eq!(tree("1 + 2 * 3"), "(+ 1 (* 2 3))");
+
+        let source = "1 + 2 extra";
+        let (exprs, id, diagnostics) = Parser::new(source).parse_expr();
+        assert_eq!(&source[exprs[id].span], "1 + 2");
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(
+            diagnostics[0].message(),
+            "expected end of input, found identifier"
+        );
+        assert_eq!(&source[diagnostics[0].span()], "extra");
+    }
+
+    #[test]
+    fn boolean_compound_assignment() {
+        use TokenKind::*;
+        let (tokens, _) = Lexer::tokenize("x &&= a; x ||= b; a && = b");
+        let kinds: Vec<_> = tokens.iter().map(|token| token.kind()).collect();
+        assert_eq!(
+            kinds,
+            [
+                Identifier, AndAndEq, Identifier, Semicolon, Identifier, OrOrEq, Identifier,
+                Semicolon, Identifier, AndAnd, Eq, Identifier, Semicolon, Eof
+            ],
+        );
+
+        for (source, short_circuit) in [
+            ("x &&= a", CompoundAssignmentOperator::LogicalAnd),
+            ("x ||= a", CompoundAssignmentOperator::LogicalOr),
+        ] {
+            let (exprs, id) = parse(source);
+            let ExpressionKind::Operator(OperatorExpression::CompoundAssignment { op, .. }) =
+                exprs[id].kind
+            else {
+                panic!("expected a compound assignment: {source}");
+            };
+            assert_eq!(op, short_circuit);
+            assert!(op.is_short_circuit());
+        }
+        assert_eq!(tree("x ||= a && b"), "(||= x (&& a b))");
+    }
+
+    #[test]
+    fn range_expressions() {
+        // Gets which bounds a range has, and whether it is inclusive.
+        fn bounds(source: &str) -> (bool, bool, bool) {
+            let (exprs, id) = parse(source);
+            let ExpressionKind::Range {
+                start,
+                end,
+                inclusive,
+            } = exprs[id].kind
+            else {
+                panic!("expected a range: {source}");
+            };
+            (start.is_some(), end.is_some(), inclusive)
+        }
+
+        assert_eq!(bounds("0..10"), (true, true, false));
+        assert_eq!(bounds("..10"), (false, true, false));
+        assert_eq!(bounds("5.."), (true, false, false));
+        assert_eq!(bounds("0..=10"), (true, true, true));
+
+        assert_eq!(tree("1 + 2 .. 3 + 4"), "(.. (+ 1 2) (+ 3 4))");
+        assert_eq!(tree("f(0..n)"), "(call f (.. 0 n))");
+
+        let (_, _, diagnostics) = Parser::new("0..=").parse_expr();
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(
+            diagnostics[0].message(),
+            "expected expression, found end of file"
+        );
+    }
+
+    #[test]
+    fn assignment_targets() {
+        for (source, target) in [("5 = x", "5"), ("(a + b) = c", "(a + b)"), ("5 += 1", "5")] {
+            let (_, _, diagnostics) = Parser::new(source).parse_expr();
+            assert_eq!(diagnostics.len(), 1, "{source}");
+            assert_eq!(diagnostics[0].code(), Some(Code::InvalidAssignmentTarget));
+            assert_eq!(diagnostics[0].message(), "cannot assign to this expression");
+            assert_eq!(&source[diagnostics[0].span()], target);
+        }
+
+        assert_eq!(tree("a.b = x"), "(= (.b a) x)");
+        assert_eq!(tree("*p = x"), "(= (* p) x)");
+        assert_eq!(tree("a[i] = x"), "(= (index a i) x)");
+    }
+
+    #[test]
+    fn wildcards() {
+        let (_, block) = parse_function("fn f() {\n    let _ = g()\n}");
+        assert!(matches!(
+            block.stmts[0].node,
+            StatementKind::Variable {
+                pattern: Pattern::Wildcard(_),
+                ..
+            }
+        ));
+
+        for source in ["x + _", "_ = x"] {
+            let (_, _, diagnostics) = Parser::new(source).parse_expr();
+            assert_eq!(diagnostics.len(), 1, "{source}");
+            assert_eq!(
+                diagnostics[0].message(),
+                "`_` can only be used in binding position"
+            );
+            assert_eq!(&source[diagnostics[0].span()], "_");
+        }
+
+        // There are no `match` arms to put a `_` in yet.
+        let (_, _, diagnostics) = Parser::new("match x { _ => 1 }").parse_expr();
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].code(), Some(Code::UnsupportedSyntax));
+    }
+
+    #[test]
+    fn end_of_file_instead_of_expression() {
+        for source in ["", "-", "1 *", "if", "x = "] {
+            let (exprs, id, diagnostics) = Parser::new(source).parse_expr();
+            assert_eq!(diagnostics.len(), 1, "{source:?}: {diagnostics:?}");
+            assert_eq!(
+                diagnostics[0].message(),
+                "expected expression, found end of file"
+            );
+            // The span is empty and sits right at the end of the input.
+            let end = source.len() as u32;
+            assert_eq!(
+                diagnostics[0].span(),
+                SourceSpan::new(end, end),
+                "{source:?}"
+            );
+            assert_eq!(exprs[id].span.start(), SourceLocation::new(0));
+        }
+    }
+
+    #[test]
+    fn chained_assignments() {
+        assert_eq!(tree("a = b = c"), "(= a (= b c))");
+        assert_eq!(tree("a.b = c"), "(= (.b a) c)");
+        assert_eq!(tree("a.b = c.d = e"), "(= (.b a) (= (.d c) e))");
+        assert_eq!(tree("a[i] = b[j] = 0"), "(= (index a i) (= (index b j) 0))");
+        assert_eq!(tree("*p = a[i] += 1"), "(= (* p) (+= (index a i) 1))");
+    }
 }
@@ -2,36 +2,277 @@
 //!
 //! Given a piece of source code, the parser will tokenize it using
 //! the [`crate::lexer`] module and validate the syntax of the program.
-//! On success, a list of [`Item`]s encoding the program's structure
-//! is returned.
+//! A list of [`Item`]s encoding the program's structure is returned,
+//! together with the [`Diagnostic`]s for all problems that were found.
+//!
+//! Syntax errors don't abort parsing. Instead, a [`Diagnostic`] is
+//! recorded and the parser skips ahead to the next statement or item,
+//! so that all independent errors in a file are reported in one run.
+//! Parts of the tree that could not be parsed are represented by error
+//! placeholders, such as `ExpressionKind::Error`.
 //!
 //! Implementation-wise, this is a hand-rolled recursive descent parser
 //! using a Pratt parsing scheme to handle expressions and precedence.
 //! The resulting tree is then subject to semantic analysis.
 
-use std::iter::Peekable;
+use std::ops::Range;
 
 use crate::{
-    ast::{Ident, Item},
-    diagnostic::SourceSpan,
-    lexer::{Lexer, Token, TokenKind},
+    ast::{
+        Ast, Attribute, Ident, Item, ItemKind, Pattern, Visibility,
+        expr::{ExprId, Expression, ExpressionKind, Exprs},
+        stmt::StructFields,
+    },
+    diagnostic::{self, Code, Diagnostic, SourceSpan},
+    lexer::{Lexer, Lookahead, SourceTooLarge, Token, TokenKind},
 };
 
 mod expr;
 mod stmt;
+mod typ;
+
+pub(crate) use expr::{infix_binding_power, postfix_binding_power, prefix_binding_power};
 
 #[derive(Clone, Debug)]
 pub struct Parser<'src> {
     source: &'src str,
-    lexer: Peekable<Lexer<'src>>,
+    lexer: Lookahead<'src>,
+    diagnostics: Vec<Diagnostic>,
+    // The arena for all expressions parsed so far.
+    exprs: Exprs,
+
+    // Set after a syntax error until the parser has synchronized to a
+    // point where it can resume. Further syntax errors are suppressed
+    // in the meantime, as they are most likely follow-up errors.
+    recovering: bool,
+
+    // Set while parsing an expression that is directly followed by a
+    // block, such as the condition of an `if`. A struct literal would
+    // be ambiguous with that block there, so `if a {}` never parses
+    // `a {}` as one. Delimiters like parentheses lift the restriction.
+    no_struct_literal: bool,
+
+    // The end offset of the last consumed token, which is where the
+    // span of a node ends once its last token was consumed.
+    prev_end: u32,
+
+    // The number of comments from the lexer that were already checked
+    // for doc comments.
+    comments_checked: usize,
+
+    // The delimited constructs being parsed, innermost last.
+    delimited: Vec<Delimited>,
+
+    // Set once the end of the source was reported as unexpected. Every
+    // construct that is still open would repeat that error otherwise.
+    reported_eof: bool,
+}
+
+// A construct between delimiters, like the parameters of a function.
+// Running into the end of the source before it is closed is reported
+// with a pointer to where it was opened.
+#[derive(Clone, Copy, Debug)]
+struct Delimited {
+    // What is being parsed, such as "function parameters".
+    what: &'static str,
+    // The opening delimiter, unless it was missing.
+    open: Option<SourceSpan>,
+}
+
+// The state of a parser to go back to when a speculative parse fails.
+// The arena and the diagnostics only grow, so their lengths suffice.
+struct Checkpoint<'src> {
+    lexer: Lookahead<'src>,
+    diagnostics: usize,
+    exprs: usize,
+    recovering: bool,
+    no_struct_literal: bool,
+    prev_end: u32,
+    comments_checked: usize,
+    delimited: usize,
+    reported_eof: bool,
+}
+
+// Describes a token kind for use in error messages.
+fn describe(kind: TokenKind) -> String {
+    match kind {
+        TokenKind::Identifier
+        | TokenKind::Label
+        | TokenKind::String
+        | TokenKind::Char
+        | TokenKind::Number
+        | TokenKind::Error
+        | TokenKind::Eof => kind.to_string(),
+        _ => format!("`{kind}`"),
+    }
+}
+
+// Whether a comment is a `///` doc comment. Like in Rust, a comment
+// starting with four or more slashes is a regular one.
+fn is_doc_comment(comment: &str) -> bool {
+    comment.starts_with("///") && !comment.starts_with("////")
 }
 
 impl<'src> Parser<'src> {
+    /// Creates a new [`Parser`] over a given string of source code.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `source` is 4GiB or larger, like [`Lexer::new`]. Use
+    /// [`Parser::try_new`] for sources that are not known to be small
+    /// enough.
     pub fn new(source: &'src str) -> Self {
-        Self {
+        Self::try_new(source).unwrap_or_else(|e| panic!("{e}"))
+    }
+
+    /// Creates a new [`Parser`] like [`Parser::new`], but returns an
+    /// error instead of panicking if `source` is too large to be lexed.
+    pub fn try_new(source: &'src str) -> Result<Self, SourceTooLarge> {
+        Ok(Self {
             source,
-            lexer: Lexer::new(source).peekable(),
+            lexer: Lexer::try_new(source)?.lookahead(),
+            diagnostics: Vec::new(),
+            exprs: Exprs::new(),
+            recovering: false,
+            no_struct_literal: false,
+            prev_end: 0,
+            comments_checked: 0,
+            delimited: Vec::new(),
+            reported_eof: false,
+        })
+    }
+
+    fn checkpoint(&self) -> Checkpoint<'src> {
+        Checkpoint {
+            lexer: self.lexer.clone(),
+            diagnostics: self.diagnostics.len(),
+            exprs: self.exprs.len(),
+            recovering: self.recovering,
+            no_struct_literal: self.no_struct_literal,
+            prev_end: self.prev_end,
+            comments_checked: self.comments_checked,
+            delimited: self.delimited.len(),
+            reported_eof: self.reported_eof,
+        }
+    }
+
+    // Puts the parser back into the state of `checkpoint`, dropping all
+    // diagnostics and expressions from after it.
+    fn restore(&mut self, checkpoint: Checkpoint<'src>) {
+        self.lexer = checkpoint.lexer;
+        self.diagnostics.truncate(checkpoint.diagnostics);
+        self.exprs.truncate(checkpoint.exprs);
+        self.recovering = checkpoint.recovering;
+        self.no_struct_literal = checkpoint.no_struct_literal;
+        self.prev_end = checkpoint.prev_end;
+        self.comments_checked = checkpoint.comments_checked;
+        self.delimited.truncate(checkpoint.delimited);
+        self.reported_eof = checkpoint.reported_eof;
+    }
+
+    // Tries a parse with `f`, which counts as failed if it returns `None`
+    // or runs into a syntax error. A failed parse is undone entirely, as
+    // if `f` never ran.
+    fn speculate<T>(&mut self, f: impl FnOnce(&mut Self) -> Option<T>) -> Option<T> {
+        let checkpoint = self.checkpoint();
+        let result = f(self)
+            .filter(|_| !self.recovering && self.diagnostics.len() == checkpoint.diagnostics);
+        if result.is_none() {
+            self.restore(checkpoint);
+        }
+        result
+    }
+
+    fn alloc(&mut self, kind: ExpressionKind, span: SourceSpan) -> ExprId {
+        self.exprs.alloc(Expression::new(kind, span))
+    }
+
+    fn error(&mut self, span: SourceSpan, code: Code, message: impl Into<String>) {
+        let diagnostic = Diagnostic::error(span, message).with_code(code);
+        self.diagnostics.push(diagnostic);
+    }
+
+    // Reports a syntax error about `found` and enters recovery mode.
+    //
+    // Error tokens were already reported by the lexer, so they are not
+    // reported a second time. Running into the end of the source within
+    // delimiters is reported as leaving the innermost of them unclosed
+    // instead, and only once.
+    fn unexpected(&mut self, expected: &str, found: TokenKind, span: SourceSpan) {
+        let reported = self.recovering
+            || found == TokenKind::Error
+            || (found == TokenKind::Eof && self.reported_eof);
+        self.recovering = true;
+        if reported {
+            return;
+        }
+        self.reported_eof = found == TokenKind::Eof;
+
+        let Some(&Delimited { what, open }) =
+            self.delimited.last().filter(|_| found == TokenKind::Eof)
+        else {
+            let message = format!("expected {expected}, found {}", describe(found));
+            self.error(span, Code::UnexpectedToken, message);
+            return;
+        };
+
+        let message = format!("unexpected end of file while parsing {what}");
+        let mut diagnostic = Diagnostic::error(span, message).with_code(Code::UnclosedDelimiter);
+        if let Some(open) = open {
+            let unclosed = format!("unclosed `{}` opened here", self.text(open));
+            diagnostic = diagnostic.with_label(open, unclosed);
+        }
+        self.diagnostics.push(diagnostic);
+    }
+
+    // Reports a syntax error about the next token without consuming it.
+    fn unexpected_next(&mut self, expected: &str) {
+        let mut found = self.peek();
+        let span = self.peek_span();
+        // The implicit semicolon after the last line is just the end of
+        // the source as far as errors are concerned.
+        if found == TokenKind::Semicolon && span.is_empty() && self.peek_nth(1) == TokenKind::Eof {
+            found = TokenKind::Eof;
         }
+        self.unexpected(expected, found, span);
+    }
+
+    // Reports the keyword at `span` as starting `what`, a feature the
+    // language reserves but doesn't support yet, and enters recovery mode
+    // to skip it.
+    fn unsupported(&mut self, what: &str, span: SourceSpan) {
+        let reported = self.recovering;
+        self.recovering = true;
+        if !reported {
+            let message = format!("{what} are not supported yet");
+            self.error(span, Code::UnsupportedSyntax, message);
+        }
+    }
+
+    // Skips tokens until parsing can resume after a syntax error, which
+    // is after the next `;` or before a `}` or the start of an item or
+    // variable declaration. Nested blocks are skipped as a whole.
+    fn synchronize(&mut self) {
+        use TokenKind::*;
+
+        let mut depth = 0usize;
+        loop {
+            match self.peek() {
+                Eof => break,
+                Semicolon if depth == 0 => {
+                    self.next();
+                    break;
+                }
+                RightBrace if depth == 0 => break,
+                Pub | Fn | Struct | Const | Type | Mod | Let | Mut if depth == 0 => break,
+                LeftBrace => depth += 1,
+                RightBrace => depth -= 1,
+                _ => {}
+            }
+            self.next();
+        }
+
+        self.recovering = false;
     }
 
     fn text(&self, span: SourceSpan) -> &'src str {
@@ -39,12 +280,30 @@ impl<'src> Parser<'src> {
     }
 
     fn peek(&mut self) -> TokenKind {
+        self.peek_nth(0)
+    }
+
+    // Gets the next token without consuming it, including its span. At
+    // the end of the source, this is an `Eof` token with an empty span.
+    fn peek_token(&mut self) -> Token {
         self.lexer
             .peek()
+            .unwrap_or_else(|| Token::eof(self.source.len() as u32))
+    }
+
+    fn peek_nth(&mut self, n: usize) -> TokenKind {
+        self.lexer
+            .peek_nth(n)
             .map(|t| t.kind())
             .unwrap_or(TokenKind::Eof)
     }
 
+    // Gets the span of the next token, or an empty span at the end of
+    // the source if no more tokens are left.
+    fn peek_span(&mut self) -> SourceSpan {
+        self.peek_token().span()
+    }
+
     fn at(&mut self, token: TokenKind) -> bool {
         self.peek() == token
     }
@@ -53,40 +312,549 @@ impl<'src> Parser<'src> {
         self.at(TokenKind::Eof)
     }
 
-    fn next(&mut self) -> Option<Token> {
-        self.lexer.next()
+    // Consumes the next token. Past the end of the source, this keeps
+    // returning an `Eof` token like `peek_token`.
+    fn next(&mut self) -> Token {
+        let Some(token) = self.lexer.next() else {
+            return Token::eof(self.source.len() as u32);
+        };
+        // Implicit semicolons are not part of any node.
+        if !token.span().is_empty() {
+            self.prev_end = Range::<u32>::from(token.span()).end;
+        }
+        token
     }
 
-    fn eat(&mut self, token: TokenKind) {
-        let actual = self.peek();
-        if actual == token {
-            self.next();
+    // Whether the next token is on a later line than the last consumed
+    // one, or there is none.
+    fn at_line_break(&mut self) -> bool {
+        let next = self.start() as usize;
+        self.eof() || self.source[self.prev_end as usize..next].contains('\n')
+    }
+
+    // Whether a `;` is required between `item` and whatever follows it.
+    // Items that end in a `}` are terminated by that brace already. A
+    // type alias may end in the `>` of generic arguments, which gets no
+    // implicit `;` as it could be a comparison continued on the next
+    // line, so a line break ends the alias as well.
+    fn needs_separator(&mut self, item: &Item) -> bool {
+        match &item.kind {
+            ItemKind::Function(_) => false,
+            ItemKind::Struct(s) => !matches!(s.fields, StructFields::Named(_)),
+            ItemKind::Const(_) => true,
+            ItemKind::TypeAlias(_) => !self.at_line_break(),
+            ItemKind::Module(m) => m.items.is_none(),
+        }
+    }
+
+    // Gets the start offset of the next token.
+    fn start(&mut self) -> u32 {
+        Range::<u32>::from(self.peek_span()).start
+    }
+
+    // Spans from `start` to the end of the last consumed token. This is
+    // empty if nothing was consumed since `start`.
+    fn span_from(&self, start: u32) -> SourceSpan {
+        SourceSpan::new(start, self.prev_end.max(start))
+    }
+
+    // Consumes and returns the next token if it is of the given kind
+    // and reports a syntax error otherwise.
+    fn expect(&mut self, token: TokenKind) -> Option<Token> {
+        if self.at(token) {
+            Some(self.next())
         } else {
-            panic!("unexpected token: {actual:?}");
+            self.unexpected_next(&describe(token));
+            None
         }
     }
 
+    // Like `expect`, but only returns whether the token was consumed.
+    fn eat(&mut self, token: TokenKind) -> bool {
+        self.expect(token).is_some()
+    }
+
+    // Consumes the delimiter that opens `what`, like the `(` in front
+    // of function parameters. Until the matching `close`, running into
+    // the end of the source is reported as leaving `what` unclosed.
+    fn open(&mut self, token: TokenKind, what: &'static str) {
+        let span = self.peek_span();
+        let open = self.eat(token).then_some(span);
+        self.delimited.push(Delimited { what, open });
+    }
+
+    // Like `open`, for the delimiter that was consumed last.
+    fn opened(&mut self, what: &'static str) {
+        let open = SourceSpan::new(self.prev_end - 1, self.prev_end);
+        self.delimited.push(Delimited {
+            what,
+            open: Some(open),
+        });
+    }
+
+    // Consumes the delimiter that closes the innermost construct opened
+    // by `open`, like `eat`.
+    fn close(&mut self, token: TokenKind) -> bool {
+        let closed = self.eat(token);
+        self.delimited.pop();
+        closed
+    }
+
+    // On error, this returns an empty placeholder identifier at the
+    // position of the unexpected token.
     fn ident(&mut self) -> Ident {
-        match self.next() {
-            Some(t) if t.kind() == TokenKind::Identifier => Ident::from(t),
-            t => panic!("expected identifier, got: {t:?}"),
+        if self.at(TokenKind::Identifier) {
+            Ident::from(self.next())
+        } else {
+            self.unexpected_next("identifier");
+            let start = self.start();
+            Ident::new(SourceSpan::new(start, start))
+        }
+    }
+
+    // Parses a name to bind a value to, or `_` to discard it.
+    fn pattern(&mut self) -> Pattern {
+        if self.at(TokenKind::Underscore) {
+            Pattern::Wildcard(self.next().span())
+        } else {
+            Pattern::Ident(self.ident())
+        }
+    }
+
+    // Consumes the `,` after an element of a list that is closed by
+    // `close`. The comma is optional after the last element, and so is
+    // the implicit semicolon a newline before `close` may produce.
+    //
+    // Returns `false` on a syntax error, after which the list should
+    // not be parsed any further.
+    fn list_separator(&mut self, close: TokenKind) -> bool {
+        if self.at(TokenKind::Semicolon) && self.peek_nth(1) == close {
+            self.next();
+        } else if !self.at(close) {
+            return self.eat(TokenKind::Comma);
+        }
+        true
+    }
+
+    // Parses `#[name]` or `#[name(args)]`.
+    fn attribute(&mut self) -> Attribute {
+        let start = self.start();
+        self.eat(TokenKind::Pound);
+        self.open(TokenKind::LeftBracket, "attribute");
+        let name = self.ident();
+        let args = self.at(TokenKind::LeftParen).then(|| self.call_args());
+        self.close(TokenKind::RightBracket);
+        let span = self.span_from(start);
+
+        // The `]` at the end of a line is followed by an implicit
+        // semicolon, which must not separate the attribute from its item.
+        if self.at(TokenKind::Semicolon) && self.peek_span().is_empty() {
+            self.next();
+        }
+
+        Attribute { name, args, span }
+    }
+
+    // Gets the doc comments that document whatever starts at the next
+    // token. These are the ones on their own lines directly in front of
+    // it, with nothing but whitespace in between. Any other doc comment
+    // skipped since the last call is reported.
+    fn doc_comments(&mut self) -> Vec<SourceSpan> {
+        let start = self.start();
+        let mut comments = self.comments_until(start);
+
+        let mut first = comments.len();
+        let mut end = start as usize;
+        while let Some(&span) = first.checked_sub(1).and_then(|i| comments.get(i)) {
+            let range = Range::<usize>::from(span);
+            let line_start = self.source[..range.start].rfind('\n').map_or(0, |i| i + 1);
+            if !is_doc_comment(self.text(span))
+                || !self.source[range.end..end].trim().is_empty()
+                || !self.source[line_start..range.start].trim().is_empty()
+            {
+                break;
+            }
+            first -= 1;
+            end = range.start;
+        }
+
+        let docs = comments.split_off(first);
+        self.dangling_doc_comments(&comments);
+        docs
+    }
+
+    // Takes the comments that end before `offset` and were not checked
+    // for doc comments yet.
+    fn comments_until(&mut self, offset: u32) -> Vec<SourceSpan> {
+        let comments = &self.lexer.comments()[self.comments_checked..];
+        let len = comments
+            .iter()
+            .take_while(|&&span| Range::<u32>::from(span).end <= offset)
+            .count();
+        self.comments_checked += len;
+        comments[..len].to_vec()
+    }
+
+    // Warns about the doc comments among `comments`, which don't
+    // document any item.
+    fn dangling_doc_comments(&mut self, comments: &[SourceSpan]) {
+        for &span in comments {
+            if is_doc_comment(self.text(span)) {
+                let message =
+                    "doc comment does not document an item, it must come directly before one";
+                self.diagnostics.push(Diagnostic::warning(span, message));
+            }
         }
     }
 
     pub(super) fn item(&mut self) -> Option<Item> {
-        if self.at(TokenKind::Fn) {
-            Some(Item::Function(self.function()))
+        let docs = self.doc_comments();
+        let start = self.start();
+        let mut attrs = Vec::new();
+        while self.at(TokenKind::Pound) {
+            attrs.push(self.attribute());
+        }
+
+        let visibility = if self.at(TokenKind::Pub) {
+            self.next();
+            Visibility::Public
         } else {
-            None
+            Visibility::Private
+        };
+
+        // Only a named `fn` declares an item.
+        let kind = if self.at(TokenKind::Fn) && self.peek_nth(1) == TokenKind::Identifier {
+            ItemKind::Function(self.function(visibility))
+        } else if self.at(TokenKind::Struct) {
+            ItemKind::Struct(self.struct_decl(visibility))
+        } else if self.at(TokenKind::Const) {
+            ItemKind::Const(self.const_decl(visibility))
+        } else if self.at(TokenKind::Type) {
+            ItemKind::TypeAlias(self.type_alias(visibility))
+        } else if self.at(TokenKind::Mod) {
+            ItemKind::Module(self.module(visibility))
+        } else if let kind @ (TokenKind::Enum | TokenKind::Impl | TokenKind::Trait) = self.peek() {
+            let what = if kind == TokenKind::Impl {
+                "blocks"
+            } else {
+                "items"
+            };
+            let span = self.peek_span();
+            self.unsupported(&format!("`{kind}` {what}"), span);
+            return None;
+        } else {
+            // Attributes and `pub` are only valid on items.
+            if visibility == Visibility::Public {
+                self.unexpected_next("item after `pub`");
+            } else if !attrs.is_empty() {
+                self.unexpected_next("item after attributes");
+            }
+            self.dangling_doc_comments(&docs);
+            return None;
+        };
+
+        // `/// text` becomes `text`, and an empty `///` an empty line.
+        let docs = (!docs.is_empty()).then(|| {
+            let lines: Vec<_> = docs
+                .iter()
+                .map(|&span| {
+                    let text = &self.text(span)["///".len()..];
+                    text.strip_prefix(' ').unwrap_or(text)
+                })
+                .collect();
+            lines.join("\n")
+        });
+
+        Some(
+            Item::new(kind, self.span_from(start))
+                .with_attrs(attrs)
+                .with_docs(docs),
+        )
+    }
+
+    /// Parses the entire source into an [`Ast`].
+    ///
+    /// Syntax errors don't stop parsing. The returned vector holds the
+    /// diagnostics from both the lexer and the parser in source order,
+    /// so the program is only valid if none of them is an error.
+    ///
+    /// The text of the `///` doc comments in front of an item is stored
+    /// in [`Item::docs`]. Doc comments anywhere else are warned about.
+    ///
+    /// A source without any items, including one made up of only
+    /// whitespace and comments, is a valid, empty program.
+    pub fn parse(mut self) -> (Ast, Vec<Diagnostic>) {
+        let items = self.items(TokenKind::Eof);
+        let diagnostics = self.finish();
+
+        let ast = Ast {
+            items,
+            exprs: self.exprs,
+        };
+        (ast, diagnostics)
+    }
+
+    /// Parses the entire source as a single expression, for tools that
+    /// work on expressions rather than programs.
+    ///
+    /// Returns the arena holding the expression and its operands, and
+    /// the id of the expression in there. Like with [`Parser::parse`],
+    /// the diagnostics include all syntax errors. Anything after the
+    /// expression is one of them.
+    pub fn parse_expr(mut self) -> (Exprs, ExprId, Vec<Diagnostic>) {
+        let expr = self.expression();
+        // Only the implicit `;` at the end of the last line may follow.
+        if self.at(TokenKind::Semicolon) && self.peek_span().is_empty() {
+            self.next();
+        }
+        if !self.eof() {
+            self.unexpected_next("end of input");
         }
+        let diagnostics = self.finish();
+
+        (self.exprs, expr, diagnostics)
+    }
+
+    // Collects the diagnostics once the whole source was parsed.
+    fn finish(&mut self) -> Vec<Diagnostic> {
+        let comments = self.comments_until(u32::MAX);
+        self.dangling_doc_comments(&comments);
+
+        let mut diagnostics = self.lexer.diagnostics().to_vec();
+        diagnostics.append(&mut self.diagnostics);
+        diagnostic::dedup(&mut diagnostics);
+        diagnostics
     }
 
-    pub fn parse(&mut self) -> Vec<Item> {
+    // Parses items up to `close`, which is left for the caller.
+    fn items(&mut self, close: TokenKind) -> Vec<Item> {
         let mut items = Vec::new();
-        while !self.eof() {
-            items.push(self.item().expect("expected item"));
-            self.eat(TokenKind::Semicolon);
+        while !self.at(close) && !self.eof() {
+            match self.item() {
+                Some(item) => {
+                    if !self.recovering
+                        && (self.needs_separator(&item) || self.at(TokenKind::Semicolon))
+                    {
+                        self.eat(TokenKind::Semicolon);
+                    }
+                    items.push(item);
+                }
+                None => {
+                    // Consume the offending token so we make progress
+                    // even if synchronization would stop right at it.
+                    self.unexpected_next("item");
+                    self.next();
+                }
+            }
+
+            if self.recovering {
+                self.synchronize();
+            }
         }
+
         items
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        Parser,
+        ast::{ItemKind, stmt::StatementKind},
+        diagnostic::{Code, Severity, SourceSpan},
+        lexer::TokenKind,
+    };
+
+    // Parses `source` and gets the spanned text and the message of each
+    // diagnostic.
+    fn errors(source: &str) -> Vec<(&str, String)> {
+        let (_, diagnostics) = Parser::new(source).parse();
+        diagnostics
+            .iter()
+            .map(|d| (&source[d.span()], d.message().to_owned()))
+            .collect()
+    }
+
+    #[test]
+    fn recovers_after_broken_statements() {
+        let source = "fn f() {\n    let a = 1 +\n    let b = (2 * )\n    let c = 3\n}";
+        let (ast, diagnostics) = Parser::new(source).parse();
+        let errors: Vec<_> = diagnostics
+            .iter()
+            .map(|d| (&source[d.span()], d.message()))
+            .collect();
+        assert_eq!(
+            errors,
+            [
+                ("let", "expected expression, found `let`"),
+                (")", "expected expression, found `)`"),
+            ],
+        );
+
+        let ItemKind::Function(function) = &ast.items[0].kind else {
+            panic!("expected a function");
+        };
+        let [.., c] = &function.block.stmts[..] else {
+            panic!("expected statements");
+        };
+        let StatementKind::Variable { expr, .. } = c.node else {
+            panic!("expected a variable");
+        };
+        assert_eq!(function.block.stmts.len(), 3);
+        assert_eq!(&source[ast.exprs[expr].span], "3");
+    }
+
+    #[test]
+    fn reports_errors_alongside_the_tree() {
+        let source = "fn main() {\n    let = 1\n}\nfn g() {}";
+        let (ast, diagnostics) = Parser::new(source).parse();
+        assert_eq!(ast.items.len(), 2);
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].severity(), Severity::Error);
+        assert_eq!(
+            errors(source),
+            [("=", "expected identifier, found `=`".to_owned())]
+        );
+    }
+
+    #[test]
+    fn reports_every_error() {
+        let source = "fn f() {\n    let a = 1 @ 2\n    let = 3\n    g(,)\n}";
+        let spans: Vec<_> = errors(source).into_iter().map(|(span, _)| span).collect();
+        // The first one comes from the lexer, the others from the parser.
+        assert_eq!(spans, ["@", "=", ","]);
+    }
+
+    #[test]
+    fn empty_programs_and_blocks() {
+        for source in ["", "  \n\t\n", "// a\n/* b */\n"] {
+            let (ast, diagnostics) = Parser::new(source).parse();
+            assert!(ast.items.is_empty(), "{source:?}");
+            assert!(diagnostics.is_empty(), "{source:?}: {diagnostics:?}");
+        }
+
+        let (ast, diagnostics) = Parser::new("fn f() {}").parse();
+        assert!(diagnostics.is_empty(), "{diagnostics:?}");
+        let ItemKind::Function(function) = &ast.items[0].kind else {
+            panic!("expected a function");
+        };
+        assert!(function.block.stmts.is_empty());
+        assert!(function.block.tail.is_none());
+    }
+
+    #[test]
+    fn doc_comments() {
+        let (ast, diagnostics) = Parser::new("/// One\n/// two\nfn f() {}").parse();
+        assert!(diagnostics.is_empty(), "{diagnostics:?}");
+        assert_eq!(ast.items[0].docs.as_deref(), Some("One\ntwo"));
+
+        let source = "fn f() {}\n/// dangling";
+        let (ast, diagnostics) = Parser::new(source).parse();
+        assert_eq!(ast.items[0].docs, None);
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].severity(), Severity::Warning);
+        assert_eq!(&source[diagnostics[0].span()], "/// dangling");
+    }
+
+    #[test]
+    fn unexpected_end_of_file() {
+        // Gets the message of the only diagnostic for `source`, with the
+        // text of its label, if any.
+        fn error(source: &str) -> (String, Option<&str>) {
+            let (_, diagnostics) = Parser::new(source).parse();
+            assert_eq!(diagnostics.len(), 1, "{source}: {diagnostics:?}");
+            let label = diagnostics[0]
+                .labels()
+                .first()
+                .map(|label| &source[label.span]);
+            (diagnostics[0].message().to_owned(), label)
+        }
+
+        let parameters = "unexpected end of file while parsing function parameters";
+        assert_eq!(error("fn f("), (parameters.to_owned(), Some("(")));
+        assert_eq!(error("fn f(a: int"), (parameters.to_owned(), Some("(")));
+
+        let block = "unexpected end of file while parsing block";
+        assert_eq!(
+            error("fn f() {\n    let x = 1\n"),
+            (block.to_owned(), Some("{"))
+        );
+        // The open block is what's reported for the missing operand.
+        assert_eq!(error("fn f() { 1 +"), (block.to_owned(), Some("{")));
+
+        let (_, _, diagnostics) = Parser::new("1 +").parse_expr();
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(
+            diagnostics[0].message(),
+            "expected expression, found end of file"
+        );
+    }
+
+    #[test]
+    fn peek_span_does_not_consume() {
+        let source = "let  x = 1";
+        let mut parser = Parser::new(source);
+        assert_eq!(&source[parser.peek_span()], "let");
+        assert_eq!(&source[parser.peek_span()], "let");
+        assert_eq!(parser.peek_token().kind(), TokenKind::Let);
+        assert_eq!(&source[parser.next().span()], "let");
+        assert_eq!(&source[parser.peek_span()], "x");
+
+        while !parser.eof() {
+            parser.next();
+        }
+        // Past the end, the span is empty and sits at the end.
+        assert_eq!(parser.peek_span(), SourceSpan::new(10, 10));
+    }
+
+    #[test]
+    fn failed_speculation_leaves_no_trace() {
+        let source = "(1 + ) x";
+        let mut parser = Parser::new(source);
+        let parsed = parser.speculate(|p| Some(p.expression()));
+        assert!(parsed.is_none());
+        assert!(parser.diagnostics.is_empty(), "{:?}", parser.diagnostics);
+        assert_eq!(parser.exprs.len(), 0);
+        assert!(!parser.recovering);
+        assert!(parser.delimited.is_empty());
+        assert_eq!(&source[parser.peek_span()], "(");
+
+        // Giving up without an error is undone the same way.
+        let parsed = parser.speculate(|p| {
+            p.next();
+            None::<()>
+        });
+        assert!(parsed.is_none());
+        assert_eq!(&source[parser.peek_span()], "(");
+
+        // A successful one keeps its progress.
+        let mut parser = Parser::new("(1 + 2) x");
+        assert!(parser.speculate(|p| Some(p.expression())).is_some());
+        assert!(parser.diagnostics.is_empty());
+        assert_eq!(parser.peek_token().kind(), TokenKind::Identifier);
+    }
+
+    #[test]
+    fn unsupported_keywords() {
+        for (source, message) in [
+            ("enum E { A }", "`enum` items are not supported yet"),
+            ("impl T {}", "`impl` blocks are not supported yet"),
+            ("trait T {}", "`trait` items are not supported yet"),
+            (
+                "fn f() {\n    match x { _ => 1 }\n}",
+                "`match` expressions are not supported yet",
+            ),
+            (
+                "fn f() {\n    while c {}\n}",
+                "`while` loops are not supported yet",
+            ),
+        ] {
+            let (_, diagnostics) = Parser::new(source).parse();
+            assert_eq!(diagnostics.len(), 1, "{source}: {diagnostics:?}");
+            assert_eq!(diagnostics[0].code(), Some(Code::UnsupportedSyntax));
+            assert_eq!(diagnostics[0].message(), message);
+            let keyword = message.split('`').nth(1).unwrap();
+            assert_eq!(&source[diagnostics[0].span()], keyword);
+        }
+    }
+}
@@ -1,60 +1,506 @@
 use super::Parser;
 use crate::{
-    ast::stmt::{Function, FunctionArg, Statement},
+    ast::{
+        Ident, Visibility,
+        stmt::{
+            Const, Function, FunctionArg, Module, Statement, StatementKind, Struct, StructField,
+            StructFields, TypeAlias,
+        },
+    },
+    diagnostic::Code,
     lexer::TokenKind,
 };
 
 impl<'src> Parser<'src> {
     pub(super) fn statement(&mut self) -> Statement {
-        if self.at(TokenKind::Let) || self.at(TokenKind::Mut) {
-            let kw = self.next().unwrap();
-            let ident = self.ident();
+        let start = self.start();
+        let kind = if self.at(TokenKind::Let) || self.at(TokenKind::Mut) {
+            let kw = self.next();
+            let pattern = self.pattern();
             self.eat(TokenKind::Eq);
             let expr = self.expression();
-            Statement::Variable {
-                ident,
+            StatementKind::Variable {
+                pattern,
                 expr,
                 mutable: kw.kind() == TokenKind::Mut,
             }
+        } else if self.at(TokenKind::For) {
+            self.for_loop(None)
+        } else if self.at(TokenKind::Label)
+            && self.peek_nth(1) == TokenKind::Colon
+            && self.peek_nth(2) == TokenKind::For
+        {
+            let label = Ident::new(self.next().span());
+            self.next();
+            self.for_loop(Some(label))
         } else if let Some(item) = self.item() {
-            Statement::Item(item)
+            StatementKind::Item(item)
+        } else {
+            StatementKind::Expression(self.expression())
+        };
+
+        Statement::new(kind, self.span_from(start))
+    }
+
+    fn for_loop(&mut self, label: Option<Ident>) -> StatementKind {
+        self.eat(TokenKind::For);
+        let binding = self.pattern();
+
+        if self.at(TokenKind::In) {
+            self.next();
         } else {
-            Statement::Expression(self.expression())
+            self.error(
+                binding.span(),
+                Code::UnexpectedToken,
+                "expected `in` after `for` binding",
+            );
+        }
+
+        let iter = self.condition();
+        let body = self.braced_block();
+
+        StatementKind::For {
+            label,
+            binding,
+            iter,
+            body,
         }
     }
 
-    pub(super) fn function(&mut self) -> Function {
+    pub(super) fn function(&mut self, visibility: Visibility) -> Function {
         self.eat(TokenKind::Fn);
         let name = self.ident();
 
+        let generics = self.generics();
+
         let mut args = Vec::new();
-        self.eat(TokenKind::LeftParen);
+        self.open(TokenKind::LeftParen, "function parameters");
         while !self.at(TokenKind::RightParen) && !self.eof() {
-            let name = self.ident();
+            let pattern = self.pattern();
             self.eat(TokenKind::Colon);
-            let typ = self.ident();
-            if !self.at(TokenKind::RightParen) {
-                self.eat(TokenKind::Comma);
+            let typ = self.typ();
+            args.push(FunctionArg { pattern, typ });
+            if !self.list_separator(TokenKind::RightParen) {
+                break;
             }
-            args.push(FunctionArg { name, typ });
         }
-        self.eat(TokenKind::RightParen);
+        self.close(TokenKind::RightParen);
 
-        let ret = if self.at(TokenKind::Colon) {
+        // The `: T` spelling of return types is still accepted while
+        // existing code transitions to `-> T`.
+        let ret = if self.at(TokenKind::Arrow) || self.at(TokenKind::Colon) {
             self.next();
-            Some(self.ident())
+            Some(self.typ())
         } else {
             None
         };
 
-        self.eat(TokenKind::LeftBrace);
-        let block = self.block();
+        let block = self.braced_block();
 
         Function {
+            visibility,
             name,
+            generics,
             args: args.into_boxed_slice(),
             ret,
             block,
         }
     }
+
+    // Parses the optional type parameters of an item. `<` and `>`
+    // delimit them here, they are never comparisons in this position.
+    fn generics(&mut self) -> Box<[Ident]> {
+        let mut generics = Vec::new();
+        if self.at(TokenKind::Lt) {
+            self.open(TokenKind::Lt, "generic parameters");
+            while !self.at(TokenKind::Gt) && !self.eof() {
+                generics.push(self.ident());
+                if !self.list_separator(TokenKind::Gt) {
+                    break;
+                }
+            }
+            self.close(TokenKind::Gt);
+        }
+
+        generics.into_boxed_slice()
+    }
+
+    pub(super) fn struct_decl(&mut self, visibility: Visibility) -> Struct {
+        self.eat(TokenKind::Struct);
+        let name = self.ident();
+
+        let fields = if self.at(TokenKind::LeftParen) {
+            self.open(TokenKind::LeftParen, "struct fields");
+            let mut types = Vec::new();
+            while !self.at(TokenKind::RightParen) && !self.eof() {
+                types.push(self.typ());
+                if !self.list_separator(TokenKind::RightParen) {
+                    break;
+                }
+            }
+            self.close(TokenKind::RightParen);
+            StructFields::Tuple(types.into_boxed_slice())
+        } else {
+            self.open(TokenKind::LeftBrace, "struct fields");
+            let mut fields = Vec::new();
+            while !self.at(TokenKind::RightBrace) && !self.eof() {
+                let name = self.ident();
+                self.eat(TokenKind::Colon);
+                let typ = self.typ();
+                fields.push(StructField { name, typ });
+                if !self.list_separator(TokenKind::RightBrace) {
+                    break;
+                }
+            }
+            self.close(TokenKind::RightBrace);
+            StructFields::Named(fields.into_boxed_slice())
+        };
+
+        Struct {
+            visibility,
+            name,
+            fields,
+        }
+    }
+
+    pub(super) fn const_decl(&mut self, visibility: Visibility) -> Const {
+        self.eat(TokenKind::Const);
+        let name = self.ident();
+
+        let typ = if self.at(TokenKind::Colon) {
+            self.next();
+            Some(self.typ())
+        } else {
+            None
+        };
+
+        let expr = if self.at(TokenKind::Eq) {
+            self.next();
+            Some(self.expression())
+        } else {
+            self.error(
+                name.span,
+                Code::MissingConstInitializer,
+                "`const` declaration is missing an initializer",
+            );
+            None
+        };
+
+        Const {
+            visibility,
+            name,
+            typ,
+            expr,
+        }
+    }
+
+    pub(super) fn type_alias(&mut self, visibility: Visibility) -> TypeAlias {
+        self.eat(TokenKind::Type);
+        let name = self.ident();
+        let generics = self.generics();
+        self.eat(TokenKind::Eq);
+        let typ = self.typ();
+
+        TypeAlias {
+            visibility,
+            name,
+            generics,
+            typ,
+        }
+    }
+
+    pub(super) fn module(&mut self, visibility: Visibility) -> Module {
+        self.eat(TokenKind::Mod);
+        let name = self.ident();
+
+        // `mod name;` leaves its `;` to the caller, like other items.
+        let items = if self.at(TokenKind::LeftBrace) {
+            self.open(TokenKind::LeftBrace, "module");
+            let items = self.items(TokenKind::RightBrace);
+            self.close(TokenKind::RightBrace);
+            Some(items.into_boxed_slice())
+        } else {
+            if !self.at(TokenKind::Semicolon) {
+                self.unexpected_next("`;` or `{`");
+            }
+            None
+        };
+
+        Module {
+            visibility,
+            name,
+            items,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        Parser,
+        ast::{
+            Item, ItemKind, Visibility,
+            stmt::{StatementKind, StructFields},
+        },
+        diagnostic::Code,
+    };
+
+    // Parses `source`, which must be free of errors, and gets its items.
+    fn items(source: &str) -> Vec<Item> {
+        let (ast, diagnostics) = Parser::new(source).parse();
+        assert!(diagnostics.is_empty(), "{source}: {diagnostics:?}");
+        ast.items
+    }
+
+    #[test]
+    fn return_types() {
+        for source in ["fn f() -> int {}", "fn f(): int {}"] {
+            let ItemKind::Function(function) = &items(source)[0].kind else {
+                panic!("expected a function");
+            };
+            assert_eq!(&source[function.ret.as_ref().unwrap().span()], "int");
+        }
+    }
+
+    #[test]
+    fn structs() {
+        let source = "struct Point { x: int, y: int, }\nstruct Unit {}\nstruct Pair(int, int)";
+        let fields: Vec<_> = items(source)
+            .into_iter()
+            .map(|item| match item.kind {
+                ItemKind::Struct(s) => match s.fields {
+                    StructFields::Named(fields) => fields
+                        .iter()
+                        .map(|f| format!("{}: {}", &source[f.name.span], &source[f.typ.span]))
+                        .collect::<Vec<_>>(),
+                    StructFields::Tuple(fields) => {
+                        fields.iter().map(|f| source[f.span].to_owned()).collect()
+                    }
+                },
+                _ => panic!("expected a struct"),
+            })
+            .collect();
+        assert_eq!(
+            fields,
+            [vec!["x: int", "y: int"], vec![], vec!["int", "int"]]
+        );
+
+        let source = "struct Point { x, y: int }";
+        let (ast, diagnostics) = Parser::new(source).parse();
+        assert_eq!(ast.items.len(), 1);
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].message(), "expected `:`, found `,`");
+    }
+
+    #[test]
+    fn consts() {
+        let source = "const MAX: int = 100\nfn f() {\n    const MAX: int = 100\n}";
+        let items = items(source);
+        let ItemKind::Const(c) = &items[0].kind else {
+            panic!("expected a constant");
+        };
+        assert_eq!(&source[c.name.span], "MAX");
+        assert!(c.typ.is_some() && c.expr.is_some());
+
+        let ItemKind::Function(function) = &items[1].kind else {
+            panic!("expected a function");
+        };
+        let StatementKind::Item(item) = &function.block.stmts[0].node else {
+            panic!("expected an item statement");
+        };
+        assert!(matches!(item.kind, ItemKind::Const(_)));
+
+        let source = "const MAX: int";
+        let (_, diagnostics) = Parser::new(source).parse();
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].code(), Some(Code::MissingConstInitializer));
+    }
+
+    #[test]
+    fn trailing_comma_in_parameters() {
+        let source = "fn g(x: int, y: int,) {}";
+        let ItemKind::Function(function) = &items(source)[0].kind else {
+            panic!("expected a function");
+        };
+        assert_eq!(function.args.len(), 2);
+
+        let (_, diagnostics) = Parser::new("fn g(,) {}").parse();
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].message(), "expected identifier, found `,`");
+    }
+
+    #[test]
+    fn generic_parameters() {
+        let source = "fn id<T>(x: T): T {}\nfn pair<T, U,>(t: T, u: U) {}";
+        let generics: Vec<Vec<_>> = items(source)
+            .iter()
+            .map(|item| match &item.kind {
+                ItemKind::Function(f) => f.generics.iter().map(|g| &source[g.span]).collect(),
+                _ => panic!("expected a function"),
+            })
+            .collect();
+        assert_eq!(generics, [vec!["T"], vec!["T", "U"]]);
+
+        // Expressions still take `<` and `>` for comparisons.
+        let (_, _, diagnostics) = Parser::new("a < b > c").parse_expr();
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].code(), Some(Code::ChainedComparison));
+    }
+
+    #[test]
+    fn visibility() {
+        let source = "pub fn f() {}\nfn g() {}";
+        let visibilities: Vec<_> = items(source)
+            .iter()
+            .map(|item| match &item.kind {
+                ItemKind::Function(f) => f.visibility,
+                _ => panic!("expected a function"),
+            })
+            .collect();
+        assert_eq!(visibilities, [Visibility::Public, Visibility::Private]);
+
+        let (_, diagnostics) = Parser::new("fn f() {\n    pub let x = 1\n}").parse();
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(
+            diagnostics[0].message(),
+            "expected item after `pub`, found `let`"
+        );
+    }
+
+    #[test]
+    fn nested_functions() {
+        // No `;` is needed after the `}` of the nested function.
+        let source = "fn main() { fn helper(): int { 1 } helper() }";
+        let ItemKind::Function(main) = &items(source)[0].kind else {
+            panic!("expected a function");
+        };
+        let StatementKind::Item(item) = &main.block.stmts[0].node else {
+            panic!("expected an item statement");
+        };
+        let ItemKind::Function(helper) = &item.kind else {
+            panic!("expected a nested function");
+        };
+        assert_eq!(&source[helper.name.span], "helper");
+        assert!(main.block.tail.is_some());
+    }
+
+    #[test]
+    fn item_and_statement_spans() {
+        let function = "pub fn f() {\n    let x = 1 + 2\n    mut y = x;\n}";
+        let source = &format!("\n{function}\n")[..];
+        let items = items(source);
+        assert_eq!(&source[items[0].span()], function);
+
+        let ItemKind::Function(f) = &items[0].kind else {
+            panic!("expected a function");
+        };
+        let stmts: Vec<_> = f.block.stmts.iter().map(|s| &source[s.span()]).collect();
+        assert_eq!(stmts, ["let x = 1 + 2", "mut y = x"]);
+    }
+
+    #[test]
+    fn type_aliases() {
+        let source = "type Id = int\ntype Vec2<T> = (T, T)";
+        let aliases: Vec<_> = items(source)
+            .into_iter()
+            .map(|item| match item.kind {
+                ItemKind::TypeAlias(alias) => alias,
+                _ => panic!("expected a type alias"),
+            })
+            .collect();
+        assert_eq!(&source[aliases[0].name.span], "Id");
+        assert_eq!(&source[aliases[0].typ.span()], "int");
+        assert_eq!(&source[aliases[1].name.span], "Vec2");
+        assert_eq!(&source[aliases[1].generics[0].span], "T");
+        assert_eq!(&source[aliases[1].typ.span()], "(T, T)");
+
+        let (ast, diagnostics) = Parser::new("type Id int").parse();
+        assert!(matches!(ast.items[0].kind, ItemKind::TypeAlias(_)));
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].message(), "expected `=`, found identifier");
+    }
+
+    #[test]
+    fn modules() {
+        let source = "mod m {\n    fn a() {}\n    fn b() {}\n}\nmod foo;";
+        let items = items(source);
+        let ItemKind::Module(inline) = &items[0].kind else {
+            panic!("expected a module");
+        };
+        let names: Vec<_> = inline
+            .items
+            .as_deref()
+            .unwrap()
+            .iter()
+            .map(|item| match &item.kind {
+                ItemKind::Function(function) => &source[function.name.span],
+                _ => panic!("expected a function"),
+            })
+            .collect();
+        assert_eq!(names, ["a", "b"]);
+        let ItemKind::Module(external) = &items[1].kind else {
+            panic!("expected a module");
+        };
+        assert_eq!(&source[external.name.span], "foo");
+        assert!(external.items.is_none());
+
+        for (source, found) in [("mod foo fn f() {}", "`fn`"), ("mod foo }", "`}`")] {
+            let (_, diagnostics) = Parser::new(source).parse();
+            assert_eq!(
+                diagnostics[0].message(),
+                format!("expected `;` or `{{`, found {found}"),
+            );
+        }
+    }
+
+    #[test]
+    fn attributes() {
+        let source = "#[inline]\n#[test]\nfn f() {}";
+        let item = &items(source)[0];
+        assert!(matches!(item.kind, ItemKind::Function(_)));
+        let names: Vec<_> = item
+            .attrs
+            .iter()
+            .map(|attr| &source[attr.name.span])
+            .collect();
+        assert_eq!(names, ["inline", "test"]);
+        assert_eq!(&source[item.attrs[0].span], "#[inline]");
+        assert!(item.attrs.iter().all(|attr| attr.args.is_none()));
+
+        let source = "#[cfg(test)]\nfn f() {}";
+        let item = &items(source)[0];
+        assert_eq!(&source[item.attrs[0].name.span], "cfg");
+        assert_eq!(item.attrs[0].args.as_ref().map(|args| args.len()), Some(1));
+        assert_eq!(&source[item.attrs[0].span], "#[cfg(test)]");
+    }
+
+    #[test]
+    fn for_loops() {
+        let source = "fn f() {\n    for x in items {}\n    for i in 0..n {}\n}";
+        let (ast, diagnostics) = Parser::new(source).parse();
+        assert!(diagnostics.is_empty(), "{diagnostics:?}");
+        let ItemKind::Function(f) = &ast.items[0].kind else {
+            panic!("expected a function");
+        };
+        let loops: Vec<_> = f
+            .block
+            .stmts
+            .iter()
+            .map(|stmt| match &stmt.node {
+                StatementKind::For { binding, iter, .. } => {
+                    (&source[binding.span()], &source[ast.exprs[*iter].span])
+                }
+                _ => panic!("expected a `for` loop"),
+            })
+            .collect();
+        assert_eq!(loops, [("x", "items"), ("i", "0..n")]);
+
+        let (ast, diagnostics) = Parser::new("fn f() {\n    for x items {}\n}").parse();
+        assert_eq!(ast.items.len(), 1);
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(
+            diagnostics[0].message(),
+            "expected `in` after `for` binding"
+        );
+    }
 }
@@ -0,0 +1,302 @@
+use super::Parser;
+use crate::{
+    ast::{Spanned, TypeNode},
+    lexer::TokenKind,
+};
+
+impl<'src> Parser<'src> {
+    // Parses a type, such as `int`, `&[u8]` or `(Vec<int>, bool)`.
+    //
+    // A `<` right after a type name always starts generic arguments, so
+    // comparing the result of a cast needs parentheses: `(x as u8) < y`.
+    pub(super) fn typ(&mut self) -> Spanned<TypeNode> {
+        let start = self.start();
+        let typ = match self.peek() {
+            TokenKind::And => {
+                self.next();
+                TypeNode::Reference(Box::new(self.typ()))
+            }
+            // `&&T` is lexed as a single token.
+            TokenKind::AndAnd => {
+                self.next();
+                let inner = self.typ();
+                let inner = Spanned::new(
+                    TypeNode::Reference(Box::new(inner)),
+                    self.span_from(start + 1),
+                );
+                TypeNode::Reference(Box::new(inner))
+            }
+            TokenKind::Star => {
+                self.next();
+                TypeNode::Pointer(Box::new(self.typ()))
+            }
+            // So is `**T`.
+            TokenKind::StarStar => {
+                self.next();
+                let inner = self.typ();
+                let inner = Spanned::new(
+                    TypeNode::Pointer(Box::new(inner)),
+                    self.span_from(start + 1),
+                );
+                TypeNode::Pointer(Box::new(inner))
+            }
+            TokenKind::LeftBracket => {
+                self.open(TokenKind::LeftBracket, "array type");
+                let elem = Box::new(self.typ());
+                let typ = if self.at(TokenKind::Semicolon) {
+                    self.next();
+                    let len = self.expression();
+                    TypeNode::Array { elem, len }
+                } else {
+                    TypeNode::Slice(elem)
+                };
+                self.close(TokenKind::RightBracket);
+                typ
+            }
+            TokenKind::LeftParen => return self.tuple_type(),
+            TokenKind::Identifier => {
+                let name = self.ident();
+                let args = if self.at(TokenKind::Lt) {
+                    self.generic_args()
+                } else {
+                    Box::new([])
+                };
+                TypeNode::Named { name, args }
+            }
+            _ => {
+                // Still yields a placeholder name after the error.
+                self.unexpected_next("type");
+                TypeNode::Named {
+                    name: self.ident(),
+                    args: Box::new([]),
+                }
+            }
+        };
+
+        Spanned::new(typ, self.span_from(start))
+    }
+
+    // Like for expressions, only a comma makes a tuple type. `(T)` is
+    // just `T`, and keeps the span without the parentheses.
+    fn tuple_type(&mut self) -> Spanned<TypeNode> {
+        let start = self.start();
+        self.open(TokenKind::LeftParen, "tuple type");
+        if self.at(TokenKind::RightParen) {
+            self.close(TokenKind::RightParen);
+            return Spanned::new(TypeNode::Tuple(Box::new([])), self.span_from(start));
+        }
+
+        let first = self.typ();
+        if !self.at(TokenKind::Comma) {
+            self.close(TokenKind::RightParen);
+            return first;
+        }
+        self.next();
+
+        let mut elems = vec![first];
+        while !self.at(TokenKind::RightParen) && !self.eof() {
+            elems.push(self.typ());
+            if !self.list_separator(TokenKind::RightParen) {
+                break;
+            }
+        }
+        self.close(TokenKind::RightParen);
+
+        let elems = elems.into_boxed_slice();
+        Spanned::new(TypeNode::Tuple(elems), self.span_from(start))
+    }
+
+    // Parses `<A, B>` after a type name. A `>>` at the end of nested
+    // arguments closes two lists at once, so it is split up first. So
+    // are `>>=` and `>=`, whose `=` may follow the type.
+    fn generic_args(&mut self) -> Box<[Spanned<TypeNode>]> {
+        self.open(TokenKind::Lt, "generic arguments");
+
+        let mut args = Vec::new();
+        loop {
+            self.lexer.split_shr();
+            if self.at(TokenKind::Gt) || self.eof() {
+                break;
+            }
+
+            args.push(self.typ());
+            self.lexer.split_shr();
+            if !self.list_separator(TokenKind::Gt) {
+                break;
+            }
+        }
+        self.close(TokenKind::Gt);
+
+        args.into_boxed_slice()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        Parser,
+        ast::{
+            ItemKind, Spanned, TypeNode,
+            expr::{ArithmeticLogicalOperator, ExpressionKind, OperatorExpression},
+        },
+    };
+
+    // Parses `typ` as the target of a type alias and gets the text of its
+    // span and those of all types nested in it, outermost first.
+    fn spans(typ: &str) -> Vec<String> {
+        fn walk<'a>(source: &'a str, typ: &Spanned<TypeNode>, out: &mut Vec<&'a str>) {
+            out.push(&source[typ.span()]);
+            match &**typ {
+                TypeNode::Named { args: elems, .. } | TypeNode::Tuple(elems) => {
+                    elems.iter().for_each(|elem| walk(source, elem, out));
+                }
+                TypeNode::Reference(inner)
+                | TypeNode::Pointer(inner)
+                | TypeNode::Slice(inner)
+                | TypeNode::Array { elem: inner, .. } => walk(source, inner, out),
+            }
+        }
+
+        let source = format!("type T = {typ}");
+        let (ast, diagnostics) = Parser::new(&source).parse();
+        assert!(diagnostics.is_empty(), "{source}: {diagnostics:?}");
+        let ItemKind::TypeAlias(alias) = &ast.items[0].kind else {
+            panic!("expected a type alias");
+        };
+
+        let mut out = Vec::new();
+        walk(&source, &alias.typ, &mut out);
+        out.into_iter().map(str::to_owned).collect()
+    }
+
+    #[test]
+    fn nested_spans() {
+        assert_eq!(
+            spans("&[(Vec<u8>, bool); 4]"),
+            [
+                "&[(Vec<u8>, bool); 4]",
+                "[(Vec<u8>, bool); 4]",
+                "(Vec<u8>, bool)",
+                "Vec<u8>",
+                "u8",
+                "bool",
+            ],
+        );
+        // `&&` and `**` are single tokens, but still two types.
+        assert_eq!(spans("&&*u8"), ["&&*u8", "&*u8", "*u8", "u8"]);
+        assert_eq!(spans("**u8"), ["**u8", "*u8", "u8"]);
+        // Parentheses around a single type are not part of it.
+        assert_eq!(spans("((u8))"), ["u8"]);
+        assert_eq!(spans("()"), ["()"]);
+    }
+
+    #[test]
+    fn references_and_slices() {
+        let source = "fn f(p: &int, xs: [int]): &bool {}";
+        let (ast, diagnostics) = Parser::new(source).parse();
+        assert!(diagnostics.is_empty(), "{diagnostics:?}");
+        let ItemKind::Function(function) = &ast.items[0].kind else {
+            panic!("expected a function");
+        };
+        assert!(matches!(*function.args[0].typ, TypeNode::Reference(_)));
+        assert!(matches!(*function.args[1].typ, TypeNode::Slice(_)));
+        assert!(matches!(
+            **function.ret.as_ref().unwrap(),
+            TypeNode::Reference(_)
+        ));
+
+        assert_eq!(spans("&[int]"), ["&[int]", "[int]", "int"]);
+    }
+
+    #[test]
+    fn type_forms() {
+        let source = "type T = (int, Vec<int>, &int, *int, [int], [int; 3], (int,), ())";
+        let (ast, diagnostics) = Parser::new(source).parse();
+        assert!(diagnostics.is_empty(), "{diagnostics:?}");
+        let ItemKind::TypeAlias(alias) = &ast.items[0].kind else {
+            panic!("expected a type alias");
+        };
+        let TypeNode::Tuple(elems) = &*alias.typ else {
+            panic!("expected a tuple type");
+        };
+
+        let forms: Vec<_> = elems
+            .iter()
+            .map(|elem| match &**elem {
+                TypeNode::Named { args, .. } if args.is_empty() => "named",
+                TypeNode::Named { .. } => "generic",
+                TypeNode::Reference(_) => "reference",
+                TypeNode::Pointer(_) => "pointer",
+                TypeNode::Slice(_) => "slice",
+                TypeNode::Array { .. } => "array",
+                TypeNode::Tuple(elems) if elems.is_empty() => "unit",
+                TypeNode::Tuple(_) => "tuple",
+            })
+            .collect();
+        assert_eq!(
+            forms,
+            [
+                "named",
+                "generic",
+                "reference",
+                "pointer",
+                "slice",
+                "array",
+                "tuple",
+                "unit"
+            ],
+        );
+    }
+
+    #[test]
+    fn tuple_and_array_types() {
+        // Let annotations aren't parsed yet, so arrays go in a parameter.
+        let source = "fn f(p: (int, bool), xs: [int; 3]): () {}";
+        let (ast, diagnostics) = Parser::new(source).parse();
+        assert!(diagnostics.is_empty(), "{diagnostics:?}");
+        let ItemKind::Function(function) = &ast.items[0].kind else {
+            panic!("expected a function");
+        };
+        assert!(matches!(&*function.args[0].typ, TypeNode::Tuple(elems) if elems.len() == 2));
+        assert!(matches!(*function.args[1].typ, TypeNode::Array { .. }));
+        assert!(matches!(
+            &**function.ret.as_ref().unwrap(),
+            TypeNode::Tuple(elems) if elems.is_empty()
+        ));
+
+        assert_eq!(spans("[int; 3]"), ["[int; 3]", "int"]);
+        assert_eq!(
+            spans("([int], bool)"),
+            ["([int], bool)", "[int]", "int", "bool"]
+        );
+    }
+
+    #[test]
+    fn nested_generic_arguments() {
+        assert_eq!(spans("Vec<Vec<int>>"), ["Vec<Vec<int>>", "Vec<int>", "int"]);
+        assert_eq!(
+            spans("Vec<Vec<Vec<int>>>"),
+            ["Vec<Vec<Vec<int>>>", "Vec<Vec<int>>", "Vec<int>", "int"],
+        );
+
+        // A line break ends an alias even though `>` gets no implicit `;`.
+        let (_, diagnostics) = Parser::new("type T = Vec<Vec<int>>\nfn f() {}").parse();
+        assert!(diagnostics.is_empty(), "{diagnostics:?}");
+
+        // Outside of types, `>>` is still a shift.
+        let source = "fn f(a: int, b: int): Vec<Vec<int>> {\n    a >> b\n}";
+        let (ast, diagnostics) = Parser::new(source).parse();
+        assert!(diagnostics.is_empty(), "{diagnostics:?}");
+        let ItemKind::Function(function) = &ast.items[0].kind else {
+            panic!("expected a function");
+        };
+        let tail = &ast.exprs[function.block.tail.unwrap()];
+        assert!(matches!(
+            tail.kind,
+            ExpressionKind::Operator(OperatorExpression::ArithmeticLogical {
+                op: ArithmeticLogicalOperator::Shr,
+                ..
+            })
+        ));
+    }
+}
@@ -0,0 +1,383 @@
+use std::ops::RangeInclusive;
+
+use crate::{
+    ast::{
+        Ast,
+        expr::{
+            ArithmeticLogicalOperator, BooleanOperator, ComparisonOperator, ExprId, Expression,
+            ExpressionKind, Exprs, IntType, Literal, NegationOperator, OperatorExpression,
+        },
+        ty::Type,
+        visit::{VisitorMut, walk_expr_mut},
+    },
+    diagnostic::{Code, Diagnostic, SourceSpan},
+};
+
+/// Replaces operator expressions on integer and boolean literals with
+/// the literal they evaluate to, so `2 + 3 * 4` becomes `14`.
+///
+/// Literals cannot be negative, so a negative integer is represented
+/// as a negated literal instead, like `-5`. Such a negation is treated
+/// as a constant in its own right, which makes `-128i8` a valid `i8`
+/// even though `128i8` alone is not.
+///
+/// Operations that overflow their type or divide by zero are reported
+/// and left as they are. Unsuffixed integers have the type inferred for
+/// the operation by type checking, which is `int` by default. `&&` and `||` are folded as soon as their left
+/// operand decides the result, as in `false && x`.
+///
+/// This should run after [`super::typeck`], whose types it relies on.
+/// It silently skips operands whose types don't match.
+pub fn fold_constants(ast: &mut Ast) -> Vec<Diagnostic> {
+    let mut folder = Folder {
+        diagnostics: Vec::new(),
+    };
+    for item in &mut ast.items {
+        folder.visit_item_mut(&mut ast.exprs, item);
+    }
+
+    folder.diagnostics
+}
+
+struct Folder {
+    diagnostics: Vec<Diagnostic>,
+}
+
+// The value of a constant expression.
+#[derive(Clone, Copy)]
+enum Constant {
+    Bool(bool),
+    // Unlike an integer literal, this may be negative.
+    Int { value: i128, typ: Option<IntType> },
+}
+
+// Gets the value of an expression if it is a literal or a negated
+// integer literal.
+fn constant(exprs: &Exprs, expr: ExprId) -> Option<Constant> {
+    match &exprs[expr].kind {
+        ExpressionKind::Literal(Literal::Bool(b)) => Some(Constant::Bool(*b)),
+        &ExpressionKind::Literal(Literal::Int { value, typ }) => Some(Constant::Int {
+            value: value as i128,
+            typ,
+        }),
+        ExpressionKind::Operator(OperatorExpression::Negation {
+            op: NegationOperator::Negation,
+            expr,
+        }) => match exprs[*expr].kind {
+            ExpressionKind::Literal(Literal::Int { value, typ }) => Some(Constant::Int {
+                value: -(value as i128),
+                typ,
+            }),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+// Gets the range of values of an integer type.
+fn range(typ: IntType) -> RangeInclusive<i128> {
+    if typ.is_signed() {
+        -(typ.max() as i128) - 1..=typ.max() as i128
+    } else {
+        0..=typ.max() as i128
+    }
+}
+
+// Raises `a` to the power of `b`, which is not negative. Returns `None`
+// if the result does not fit into an `i128`.
+fn pow(a: i128, b: i128) -> Option<i128> {
+    match (a, u32::try_from(b)) {
+        (_, Ok(b)) => a.checked_pow(b),
+        // Only these bases survive exponents this large.
+        (0 | 1, Err(_)) => Some(a),
+        (-1, Err(_)) => Some(if b % 2 == 0 { 1 } else { -1 }),
+        _ => None,
+    }
+}
+
+impl Folder {
+    fn error(&mut self, span: SourceSpan, code: Code, message: &str) {
+        let diagnostic = Diagnostic::error(span, message).with_code(code);
+        self.diagnostics.push(diagnostic);
+    }
+
+    fn overflow(&mut self, op: &str, span: SourceSpan) {
+        let message = format!("this `{op}` operation will overflow");
+        self.error(span, Code::ArithmeticOverflow, &message);
+    }
+
+    fn fold(
+        &mut self,
+        exprs: &Exprs,
+        op: &OperatorExpression,
+        span: SourceSpan,
+        ty: Option<Type>,
+    ) -> Option<Constant> {
+        // The type of unsuffixed integers, which have `int` by default.
+        let inferred = match ty {
+            Some(Type::Int(typ)) => typ,
+            _ => IntType::I64,
+        };
+        match op {
+            OperatorExpression::ArithmeticLogical { lhs, op, rhs } => {
+                match (constant(exprs, *lhs)?, constant(exprs, *rhs)?) {
+                    (Constant::Int { value: a, typ: ta }, Constant::Int { value: b, typ: tb }) => {
+                        self.arithmetic(*op, (a, ta), (b, tb), inferred, span)
+                    }
+                    (Constant::Bool(a), Constant::Bool(b)) => match op {
+                        ArithmeticLogicalOperator::And => Some(Constant::Bool(a & b)),
+                        ArithmeticLogicalOperator::Or => Some(Constant::Bool(a | b)),
+                        ArithmeticLogicalOperator::Xor => Some(Constant::Bool(a ^ b)),
+                        _ => None,
+                    },
+                    _ => None,
+                }
+            }
+            OperatorExpression::Comparison { lhs, op, rhs } => {
+                let ordering = match (constant(exprs, *lhs)?, constant(exprs, *rhs)?) {
+                    (Constant::Int { value: a, typ: ta }, Constant::Int { value: b, typ: tb })
+                        if ta.is_none() || tb.is_none() || ta == tb =>
+                    {
+                        a.cmp(&b)
+                    }
+                    (Constant::Bool(a), Constant::Bool(b)) => a.cmp(&b),
+                    _ => return None,
+                };
+                let result = match op {
+                    ComparisonOperator::Eq => ordering.is_eq(),
+                    ComparisonOperator::NotEq => ordering.is_ne(),
+                    ComparisonOperator::Lt => ordering.is_lt(),
+                    ComparisonOperator::Gt => ordering.is_gt(),
+                    ComparisonOperator::LtEq => ordering.is_le(),
+                    ComparisonOperator::GtEq => ordering.is_ge(),
+                };
+                Some(Constant::Bool(result))
+            }
+            OperatorExpression::Boolean { lhs, op, rhs } => {
+                let Constant::Bool(a) = constant(exprs, *lhs)? else {
+                    return None;
+                };
+                match (op, a) {
+                    (BooleanOperator::And, false) | (BooleanOperator::Or, true) => {
+                        Some(Constant::Bool(a))
+                    }
+                    _ => match constant(exprs, *rhs)? {
+                        Constant::Bool(b) => Some(Constant::Bool(b)),
+                        _ => None,
+                    },
+                }
+            }
+            // A negated literal already is as folded as it gets.
+            OperatorExpression::Negation {
+                op: NegationOperator::Negation,
+                expr,
+            } if matches!(exprs[*expr].kind, ExpressionKind::Literal(_)) => None,
+            OperatorExpression::Negation { op, expr } => match (op, constant(exprs, *expr)?) {
+                (NegationOperator::LogicalNot, Constant::Bool(b)) => Some(Constant::Bool(!b)),
+                // Unsigned integers cannot be negated at all, which is
+                // reported by type checking.
+                (NegationOperator::Negation, Constant::Int { value, typ })
+                    if typ.unwrap_or(inferred).is_signed() =>
+                {
+                    if range(typ.unwrap_or(inferred)).contains(&-value) {
+                        Some(Constant::Int { value: -value, typ })
+                    } else {
+                        self.overflow(op.as_str(), span);
+                        None
+                    }
+                }
+                // The complement depends on the width of the type, so it
+                // is only known for suffixed integers.
+                (
+                    NegationOperator::BitwiseNot,
+                    Constant::Int {
+                        value,
+                        typ: Some(typ),
+                    },
+                ) => Some(Constant::Int {
+                    value: if typ.is_signed() {
+                        !value
+                    } else {
+                        !value & typ.max() as i128
+                    },
+                    typ: Some(typ),
+                }),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    fn arithmetic(
+        &mut self,
+        op: ArithmeticLogicalOperator,
+        (a, ta): (i128, Option<IntType>),
+        (b, tb): (i128, Option<IntType>),
+        inferred: IntType,
+        span: SourceSpan,
+    ) -> Option<Constant> {
+        use ArithmeticLogicalOperator::*;
+
+        let typ = match (ta, tb) {
+            (Some(ta), Some(tb)) if ta != tb => return None,
+            (ta, tb) => ta.or(tb),
+        };
+        let width = typ.unwrap_or(inferred);
+        let bits = width.bits();
+
+        let result = match op {
+            Plus => a.checked_add(b),
+            Minus => a.checked_sub(b),
+            Multiply => a.checked_mul(b),
+            Divide | Modulo if b == 0 => {
+                let message = match op {
+                    Divide => "attempt to divide by zero",
+                    _ => "attempt to calculate the remainder with a divisor of zero",
+                };
+                self.error(span, Code::DivisionByZero, message);
+                return None;
+            }
+            Divide => Some(a / b),
+            Modulo => Some(a % b),
+            // There is no integer result for a negative exponent.
+            Pow if b < 0 => return None,
+            Pow => pow(a, b),
+            And => Some(a & b),
+            Or => Some(a | b),
+            Xor => Some(a ^ b),
+            Shl | Shr if !(0..bits as i128).contains(&b) => {
+                self.overflow(op.as_str(), span);
+                return None;
+            }
+            // Bits shifted out on the left are lost, just like at
+            // runtime.
+            Shl => {
+                let mut result = (a << b) & ((1i128 << bits) - 1);
+                if width.is_signed() && result >= 1i128 << (bits - 1) {
+                    result -= 1i128 << bits;
+                }
+                return range(width)
+                    .contains(&result)
+                    .then_some(Constant::Int { value: result, typ });
+            }
+            Shr => Some(a >> b),
+        };
+
+        match result {
+            Some(value) if range(width).contains(&value) => Some(Constant::Int { value, typ }),
+            _ => {
+                self.overflow(op.as_str(), span);
+                None
+            }
+        }
+    }
+}
+
+impl VisitorMut for Folder {
+    fn visit_expr_mut(&mut self, exprs: &mut Exprs, id: ExprId) {
+        walk_expr_mut(self, exprs, id);
+
+        let expr = &exprs[id];
+        let ExpressionKind::Operator(op) = &expr.kind else {
+            return;
+        };
+        let (span, ty) = (expr.span, expr.ty);
+        exprs[id].kind = match self.fold(exprs, op, span, ty) {
+            Some(Constant::Bool(b)) => ExpressionKind::Literal(Literal::Bool(b)),
+            Some(Constant::Int { value, typ }) => {
+                let literal = ExpressionKind::Literal(Literal::Int {
+                    value: value.unsigned_abs() as u64,
+                    typ,
+                });
+                if value < 0 {
+                    let mut literal = Expression::new(literal, span);
+                    literal.ty = ty;
+                    ExpressionKind::Operator(OperatorExpression::Negation {
+                        op: NegationOperator::Negation,
+                        expr: exprs.alloc(literal),
+                    })
+                } else {
+                    literal
+                }
+            }
+            None => return,
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        Parser,
+        ast::{pretty::pretty_print, symbol::Interner},
+        sema::{resolve, typeck},
+    };
+
+    // Checks and folds `source`, which must be free of other errors, and
+    // gets the folded program printed back along with the messages of
+    // all diagnostics from folding.
+    fn fold(source: &str) -> (String, Vec<String>) {
+        let (mut ast, diagnostics) = Parser::new(source).parse();
+        assert!(diagnostics.is_empty(), "{source}: {diagnostics:?}");
+        let (_, diagnostics) = resolve(source, &mut Interner::new(), &mut ast);
+        assert!(diagnostics.is_empty(), "{source}: {diagnostics:?}");
+        let diagnostics = typeck(source, &mut ast);
+        assert!(diagnostics.is_empty(), "{source}: {diagnostics:?}");
+
+        let diagnostics = fold_constants(&mut ast);
+        let messages = diagnostics.iter().map(|d| d.message().to_owned()).collect();
+        (pretty_print(source, &ast), messages)
+    }
+
+    #[test]
+    fn folds_literals() {
+        let (folded, errors) = fold("const A: int = 2 + 3 * 4\nconst B: bool = 1 < 2 && !false");
+        assert!(errors.is_empty(), "{errors:?}");
+        assert!(folded.contains("const A: int = 14"), "{folded}");
+        assert!(folded.contains("const B: bool = true"), "{folded}");
+
+        let (_, errors) = fold("const A: int = 1 / (2 - 2)");
+        assert_eq!(errors, ["attempt to divide by zero"]);
+
+        // Only the constant operand of `+` is folded.
+        let (folded, errors) = fold("fn f(a: int): int {\n    a + 2 * 3\n}");
+        assert!(errors.is_empty(), "{errors:?}");
+        assert!(folded.contains("a + 6"), "{folded}");
+    }
+
+    #[test]
+    fn negative_literals() {
+        let (folded, errors) = fold("const A: int = -1");
+        assert!(errors.is_empty(), "{errors:?}");
+        assert!(folded.contains("const A: int = -1"), "{folded}");
+
+        // `i64::MIN` only fits because the `-` is applied to the literal.
+        let (folded, errors) = fold("const A: int = -9223372036854775808");
+        assert!(errors.is_empty(), "{errors:?}");
+        assert!(
+            folded.contains("const A: int = -9223372036854775808"),
+            "{folded}"
+        );
+
+        let (folded, errors) = fold("const A: int = - -5");
+        assert!(errors.is_empty(), "{errors:?}");
+        assert!(folded.contains("const A: int = 5"), "{folded}");
+    }
+
+    #[test]
+    fn unsuffixed_overflow() {
+        // Unsuffixed integers are `int`s by default.
+        let (_, errors) = fold("const A: int = 9223372036854775807 + 1");
+        assert_eq!(errors, ["this `+` operation will overflow"]);
+        let (_, errors) = fold("const A: int = -9223372036854775807 - 2");
+        assert_eq!(errors, ["this `-` operation will overflow"]);
+
+        let (folded, errors) = fold("const A: int = 1 << 63");
+        assert!(errors.is_empty(), "{errors:?}");
+        assert!(
+            folded.contains("const A: int = -9223372036854775808"),
+            "{folded}"
+        );
+    }
+}
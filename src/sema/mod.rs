@@ -0,0 +1,28 @@
+//! Implements the semantic analysis of a parsed program.
+//!
+//! The passes in this module run on the AST produced by the
+//! [`crate::parser`] module. They check the program for problems that
+//! are not a matter of syntax and fill in what later stages need to
+//! know about it, reporting [`crate::diagnostic::Diagnostic`]s along
+//! the way.
+
+mod resolve;
+pub use resolve::{Declaration, DeclarationKind, Declarations, resolve};
+
+mod typeck;
+pub use typeck::typeck;
+
+mod fold;
+pub use fold::fold_constants;
+
+mod unreachable;
+pub use unreachable::check_unreachable;
+
+mod mutability;
+pub use mutability::check_mutability;
+
+mod unused;
+pub use unused::check_unused;
+
+mod precedence;
+pub use precedence::check_precedence;
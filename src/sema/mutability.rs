@@ -0,0 +1,117 @@
+use super::{DeclarationKind, Declarations};
+use crate::{
+    ast::{
+        Ast, Ident,
+        expr::{ExprId, ExpressionKind, Exprs, OperatorExpression},
+        visit::{Visitor, walk_expr},
+    },
+    diagnostic::{Code, Diagnostic, SourceSpan},
+};
+
+/// Reports assignments to bindings that were not declared with `mut`.
+///
+/// This covers both `x = ...` and compound assignments like `x += ...`,
+/// as well as assignments to elements of such a binding, as in
+/// `x[0] = ...`. The bindings of `for` loops are immutable as well.
+///
+/// This must run after [`super::resolve`], whose [`Declarations`] it
+/// uses to find out how each binding was declared.
+pub fn check_mutability(source: &str, ast: &Ast, decls: &Declarations) -> Vec<Diagnostic> {
+    let mut checker = Checker {
+        source,
+        decls,
+        diagnostics: Vec::new(),
+    };
+    for item in &ast.items {
+        checker.visit_item(&ast.exprs, item);
+    }
+
+    checker.diagnostics
+}
+
+struct Checker<'a> {
+    source: &'a str,
+    decls: &'a Declarations,
+    diagnostics: Vec<Diagnostic>,
+}
+
+// Gets the binding whose value is changed when assigning to `place`.
+fn binding(exprs: &Exprs, place: ExprId) -> Option<&Ident> {
+    match &exprs[place].kind {
+        ExpressionKind::Ident(ident) => Some(ident),
+        ExpressionKind::Index { cont, .. } | ExpressionKind::Field { expr: cont, .. } => {
+            binding(exprs, *cont)
+        }
+        _ => None,
+    }
+}
+
+impl Checker<'_> {
+    fn assignment(&mut self, exprs: &Exprs, place: ExprId, span: SourceSpan) {
+        let Some(ident) = binding(exprs, place) else {
+            return;
+        };
+        let Some(id) = ident.decl else {
+            return;
+        };
+
+        let decl = self.decls.get(id);
+        if decl.kind == (DeclarationKind::Variable { mutable: false }) {
+            let name = &self.source[ident.span];
+            let message = format!("cannot assign to immutable binding `{name}`");
+            let label = format!("`{name}` is declared as immutable here");
+            let diagnostic = Diagnostic::error(span, message)
+                .with_code(Code::ImmutableAssignment)
+                .with_label(decl.span, label);
+            self.diagnostics.push(diagnostic);
+        }
+    }
+}
+
+impl Visitor for Checker<'_> {
+    fn visit_expr(&mut self, exprs: &Exprs, expr: ExprId) {
+        if let ExpressionKind::Operator(
+            OperatorExpression::Assignment { lhs, .. }
+            | OperatorExpression::CompoundAssignment { lhs, .. },
+        ) = &exprs[expr].kind
+        {
+            self.assignment(exprs, *lhs, exprs[expr].span);
+        }
+
+        walk_expr(self, exprs, expr);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Parser, ast::symbol::Interner, sema::resolve};
+
+    // Checks `body` as the body of a function and gets the messages of
+    // all errors.
+    fn errors(body: &str) -> Vec<String> {
+        let source = format!("fn f() {{\n{body}\n}}");
+        let (mut ast, diagnostics) = Parser::new(&source).parse();
+        assert!(diagnostics.is_empty(), "{source}: {diagnostics:?}");
+        let (decls, diagnostics) = resolve(&source, &mut Interner::new(), &mut ast);
+        assert!(diagnostics.is_empty(), "{source}: {diagnostics:?}");
+
+        check_mutability(&source, &ast, &decls)
+            .iter()
+            .map(|d| d.message().to_owned())
+            .collect()
+    }
+
+    #[test]
+    fn assignments() {
+        assert_eq!(
+            errors("let x = 1; x = 2"),
+            ["cannot assign to immutable binding `x`"]
+        );
+        assert!(errors("mut x = 1; x = 2").is_empty());
+        assert_eq!(
+            errors("let x = 1; x += 1"),
+            ["cannot assign to immutable binding `x`"]
+        );
+    }
+}
@@ -0,0 +1,133 @@
+use crate::{
+    ast::{
+        Ast,
+        expr::{ArithmeticLogicalOperator, ExprId, ExpressionKind, Exprs, OperatorExpression},
+        visit::{Visitor, walk_expr},
+    },
+    diagnostic::Diagnostic,
+};
+
+/// Warns about bitwise operators that are mixed with comparisons without
+/// parentheses.
+///
+/// `&`, `|` and `^` bind tighter than comparisons, so `a & b == c` is
+/// `(a & b) == c`. That is easily misread, so the warning suggests
+/// spelling out the parentheses, which silences it.
+pub fn check_precedence(source: &str, ast: &Ast) -> Vec<Diagnostic> {
+    let mut checker = Checker {
+        source,
+        diagnostics: Vec::new(),
+    };
+    for item in &ast.items {
+        checker.visit_item(&ast.exprs, item);
+    }
+
+    checker.diagnostics
+}
+
+struct Checker<'a> {
+    source: &'a str,
+    diagnostics: Vec<Diagnostic>,
+}
+
+// Gets the operator of `expr` if it is a bitwise operation that is not
+// in parentheses of its own. These become part of its span, which then
+// starts before its left operand.
+fn bare_bitwise(exprs: &Exprs, expr: ExprId) -> Option<ArithmeticLogicalOperator> {
+    use ArithmeticLogicalOperator::*;
+    match exprs[expr].kind {
+        ExpressionKind::Operator(OperatorExpression::ArithmeticLogical {
+            lhs,
+            op: op @ (And | Or | Xor),
+            ..
+        }) if exprs[expr].span.start() == exprs[lhs].span.start() => Some(op),
+        _ => None,
+    }
+}
+
+impl Visitor for Checker<'_> {
+    fn visit_expr(&mut self, exprs: &Exprs, expr: ExprId) {
+        if let ExpressionKind::Operator(OperatorExpression::Comparison { lhs, op, rhs }) =
+            exprs[expr].kind
+        {
+            for operand in [lhs, rhs] {
+                let Some(bitwise) = bare_bitwise(exprs, operand) else {
+                    continue;
+                };
+                let span = exprs[operand].span;
+                let message = format!(
+                    "`{}` binds tighter than `{}`, which is easy to misread",
+                    bitwise.as_str(),
+                    op.as_str()
+                );
+                let label = format!(
+                    "add parentheses to make this clear: `({})`",
+                    &self.source[span]
+                );
+                let diagnostic =
+                    Diagnostic::warning(exprs[expr].span, message).with_label(span, label);
+                self.diagnostics.push(diagnostic);
+            }
+        }
+
+        walk_expr(self, exprs, expr);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Lexer, Parser, ast::pretty::format};
+
+    // Gets the warnings for `body` as the body of a function, with the
+    // text of their spans and labels.
+    fn warnings(body: &str) -> Vec<(String, String, String)> {
+        let source = format!("fn f(a: int, b: int, c: int): bool {{\n    {body}\n}}");
+        let source = &source[..];
+        let (ast, diagnostics) = Parser::new(source).parse();
+        assert!(diagnostics.is_empty(), "{source}: {diagnostics:?}");
+        check_precedence(source, &ast)
+            .iter()
+            .map(|d| {
+                let label = &d.labels()[0];
+                (
+                    source[d.span()].to_owned(),
+                    d.message().to_owned(),
+                    label.message.clone(),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn bitwise_and_comparison() {
+        assert_eq!(
+            warnings("a & b == c"),
+            [(
+                "a & b == c".to_owned(),
+                "`&` binds tighter than `==`, which is easy to misread".to_owned(),
+                "add parentheses to make this clear: `(a & b)`".to_owned(),
+            )],
+        );
+        assert!(warnings("(a & b) == c").is_empty());
+        assert!(warnings("a == (b | c)").is_empty());
+    }
+
+    #[test]
+    fn formatting_keeps_silencing_parentheses() {
+        let source = "fn f(a: int, b: int): bool {\n    (a & b) == 1 || a == (b ^ 2)\n}";
+        let (ast, diagnostics) = Parser::new(source).parse();
+        assert!(diagnostics.is_empty(), "{diagnostics:?}");
+        let mut lexer = Lexer::new(source);
+        lexer.by_ref().for_each(drop);
+        let formatted = format(source, &ast, lexer.comments());
+        assert!(
+            formatted.contains("(a & b) == 1 || a == (b ^ 2)"),
+            "{formatted}"
+        );
+
+        let (ast, diagnostics) = Parser::new(&formatted).parse();
+        assert!(diagnostics.is_empty(), "{diagnostics:?}");
+        assert!(check_precedence(&formatted, &ast).is_empty());
+    }
+}
@@ -0,0 +1,570 @@
+use std::{collections::HashMap, mem};
+
+use crate::{
+    ast::{
+        Ast, DeclId, Ident, Item, ItemKind, Pattern,
+        expr::{ExprId, ExpressionKind, Exprs},
+        stmt::{Block, Statement, StatementKind},
+        symbol::{Interner, Symbol},
+        visit::{VisitorMut, walk_block_mut, walk_expr_mut, walk_item_mut, walk_stmt_mut},
+    },
+    diagnostic::{Code, Diagnostic, SourceSpan},
+};
+
+/// Something a name can be declared as.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DeclarationKind {
+    /// A `let` or `mut` variable, or the binding of a `for` loop.
+    Variable {
+        mutable: bool,
+    },
+    Parameter,
+    Function,
+    Struct,
+    Const,
+    TypeAlias,
+    Module,
+    /// The label of a block or loop, as in `'b: { ... }`. Only the
+    /// labels of loops can be targeted by `continue`.
+    Label {
+        on_loop: bool,
+    },
+}
+
+impl DeclarationKind {
+    /// Whether the declaration is an item, as opposed to a local.
+    pub fn is_item(self) -> bool {
+        matches!(
+            self,
+            Self::Function | Self::Struct | Self::Const | Self::TypeAlias | Self::Module
+        )
+    }
+}
+
+/// A declared name, as found by [`resolve`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Declaration {
+    pub kind: DeclarationKind,
+    // The span of the declared name.
+    pub span: SourceSpan,
+}
+
+/// The table of all declarations in a program, indexed by [`DeclId`].
+#[derive(Clone, Debug, Default)]
+pub struct Declarations {
+    decls: Vec<Declaration>,
+}
+
+impl Declarations {
+    /// Gets the [`Declaration`] for a [`DeclId`] from this table.
+    pub fn get(&self, id: DeclId) -> &Declaration {
+        &self.decls[id.0 as usize]
+    }
+
+    /// Iterates over all declarations in the order they were found.
+    pub fn iter(&self) -> impl Iterator<Item = (DeclId, &Declaration)> {
+        self.decls
+            .iter()
+            .enumerate()
+            .map(|(i, d)| (DeclId(i as u32), d))
+    }
+
+    /// Gets the number of declarations in the table.
+    pub fn len(&self) -> usize {
+        self.decls.len()
+    }
+
+    /// Whether the table holds no declarations at all.
+    pub fn is_empty(&self) -> bool {
+        self.decls.is_empty()
+    }
+
+    fn push(&mut self, decl: Declaration) -> DeclId {
+        let id = DeclId(self.decls.len() as u32);
+        self.decls.push(decl);
+        id
+    }
+}
+
+/// Resolves every name in `ast` to its declaration.
+///
+/// The [`Ident`]s of both declarations and uses get their `symbol` field
+/// set to their name interned into `interner`, and their `decl` field
+/// set to an index into the returned [`Declarations`]. Names that don't
+/// refer to anything in scope are reported as diagnostics and keep no
+/// declaration.
+///
+/// Items are visible in their whole enclosing block (or the whole
+/// program) regardless of order, and their names must be unique within
+/// it. Locals are only visible after their
+/// declaration, until the end of the enclosing block, and may shadow
+/// earlier names. A function body cannot see the locals of functions it
+/// is nested in.
+///
+/// Labels are resolved the same way and are visible within their block
+/// or loop. A `break` or `continue` without a label must be in a loop,
+/// and `continue` with one must target a loop.
+pub fn resolve(
+    source: &str,
+    interner: &mut Interner,
+    ast: &mut Ast,
+) -> (Declarations, Vec<Diagnostic>) {
+    let mut resolver = Resolver {
+        source,
+        interner,
+        scopes: vec![Scope::new(true)],
+        out_of_scope: HashMap::new(),
+        in_loop: false,
+        decls: Declarations::default(),
+        diagnostics: Vec::new(),
+    };
+
+    for item in &mut ast.items {
+        resolver.declare_item(item);
+    }
+    for item in &mut ast.items {
+        resolver.visit_item_mut(&mut ast.exprs, item);
+    }
+
+    (resolver.decls, resolver.diagnostics)
+}
+
+struct Scope {
+    names: HashMap<Symbol, DeclId>,
+    // Set for the scopes of items. Locals in enclosing scopes are not
+    // visible past such a scope.
+    item: bool,
+}
+
+impl Scope {
+    fn new(item: bool) -> Self {
+        Self {
+            names: HashMap::new(),
+            item,
+        }
+    }
+}
+
+struct Resolver<'a> {
+    source: &'a str,
+    interner: &'a mut Interner,
+    scopes: Vec<Scope>,
+    // The last local declared under each name whose scope has ended,
+    // to point out when a name is used outside of its block.
+    out_of_scope: HashMap<Symbol, DeclId>,
+    // Whether the innermost function body is within a loop right now.
+    in_loop: bool,
+    decls: Declarations,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl Resolver<'_> {
+    fn intern(&mut self, name: &mut Ident) -> Symbol {
+        let symbol = self.interner.intern(&self.source[name.span]);
+        name.symbol = Some(symbol);
+        symbol
+    }
+
+    fn declare(&mut self, name: &mut Ident, kind: DeclarationKind) {
+        let id = self.decls.push(Declaration {
+            kind,
+            span: name.span,
+        });
+        name.decl = Some(id);
+
+        let symbol = self.intern(name);
+        let scope = self.scopes.last_mut().unwrap();
+        scope.names.insert(symbol, id);
+    }
+
+    // A `_` declares nothing.
+    fn declare_pattern(&mut self, pattern: &mut Pattern, kind: DeclarationKind) {
+        if let Pattern::Ident(name) = pattern {
+            self.declare(name, kind);
+        }
+    }
+
+    fn declare_item(&mut self, item: &mut Item) {
+        let (name, kind) = match &mut item.kind {
+            ItemKind::Function(function) => (&mut function.name, DeclarationKind::Function),
+            ItemKind::Struct(s) => (&mut s.name, DeclarationKind::Struct),
+            ItemKind::Const(c) => (&mut c.name, DeclarationKind::Const),
+            ItemKind::TypeAlias(alias) => (&mut alias.name, DeclarationKind::TypeAlias),
+            ItemKind::Module(module) => (&mut module.name, DeclarationKind::Module),
+        };
+        let symbol = self.intern(name);
+        let scope = self.scopes.last().unwrap();
+        let previous = scope.names.get(&symbol).copied();
+        self.declare(name, kind);
+
+        // Items share one namespace per scope, and there is no
+        // overloading. Uses keep referring to the first definition.
+        if let Some(previous) = previous {
+            let scope = self.scopes.last_mut().unwrap();
+            scope.names.insert(symbol, previous);
+
+            let text = &self.source[name.span];
+            let message = format!("`{text}` is defined multiple times");
+            let diagnostic = Diagnostic::error(name.span, message)
+                .with_code(Code::DuplicateDefinition)
+                .with_label(self.decls.get(previous).span, "previously defined here");
+            self.diagnostics.push(diagnostic);
+        }
+    }
+
+    fn lookup(&self, name: Symbol) -> Option<DeclId> {
+        let mut crossed_item = false;
+        for scope in self.scopes.iter().rev() {
+            if let Some(&id) = scope.names.get(&name)
+                && (!crossed_item || self.decls.get(id).kind.is_item())
+            {
+                return Some(id);
+            }
+            crossed_item |= scope.item;
+        }
+        None
+    }
+
+    fn resolve_name(&mut self, ident: &mut Ident) {
+        let text = &self.source[ident.span];
+        // Placeholders for missing names were already reported.
+        if text.is_empty() {
+            return;
+        }
+
+        let symbol = self.intern(ident);
+        ident.decl = self.lookup(symbol);
+        if ident.decl.is_none() {
+            let message = format!("cannot find `{text}` in this scope");
+            let mut diagnostic =
+                Diagnostic::error(ident.span, message).with_code(Code::UnresolvedName);
+            if let Some(&id) = self.out_of_scope.get(&symbol) {
+                let label = format!("`{text}` is declared here, but not in scope");
+                diagnostic = diagnostic.with_label(self.decls.get(id).span, label);
+            }
+            self.diagnostics.push(diagnostic);
+        }
+    }
+
+    fn looped(&mut self, in_loop: bool, f: impl FnOnce(&mut Self)) {
+        let outer = mem::replace(&mut self.in_loop, in_loop);
+        f(self);
+        self.in_loop = outer;
+    }
+
+    fn check_in_loop(&mut self, keyword: &str, span: SourceSpan) {
+        if !self.in_loop {
+            let message = format!("`{keyword}` outside of a loop");
+            let diagnostic = Diagnostic::error(span, message).with_code(Code::BreakOutsideLoop);
+            self.diagnostics.push(diagnostic);
+        }
+    }
+
+    fn scoped(&mut self, item: bool, f: impl FnOnce(&mut Self)) {
+        self.scopes.push(Scope::new(item));
+        f(self);
+
+        let scope = self.scopes.pop().unwrap();
+        for (name, id) in scope.names {
+            if !self.decls.get(id).kind.is_item() {
+                self.out_of_scope.insert(name, id);
+            }
+        }
+    }
+}
+
+impl VisitorMut for Resolver<'_> {
+    fn visit_item_mut(&mut self, exprs: &mut Exprs, item: &mut Item) {
+        self.scoped(true, |r| match &mut item.kind {
+            ItemKind::Function(function) => {
+                for arg in &mut function.args {
+                    r.declare_pattern(&mut arg.pattern, DeclarationKind::Parameter);
+                }
+                r.looped(false, |r| r.visit_block_mut(exprs, &mut function.block));
+            }
+            // Like in a block, the items of a module can be used before
+            // they are declared. Without paths, they are not visible
+            // outside of it yet.
+            ItemKind::Module(module) => {
+                for item in module.items.iter_mut().flatten() {
+                    r.declare_item(item);
+                }
+                for item in module.items.iter_mut().flatten() {
+                    r.visit_item_mut(exprs, item);
+                }
+            }
+            _ => walk_item_mut(r, exprs, item),
+        });
+    }
+
+    fn visit_stmt_mut(&mut self, exprs: &mut Exprs, stmt: &mut Statement) {
+        match &mut stmt.node {
+            // The initializer is resolved first, so `let x = x` refers
+            // to an earlier `x`.
+            StatementKind::Variable {
+                pattern,
+                expr,
+                mutable,
+            } => {
+                self.visit_expr_mut(exprs, *expr);
+                let kind = DeclarationKind::Variable { mutable: *mutable };
+                self.declare_pattern(pattern, kind);
+            }
+            StatementKind::For {
+                label,
+                binding,
+                iter,
+                body,
+            } => {
+                self.visit_expr_mut(exprs, *iter);
+                self.scoped(false, |r| {
+                    if let Some(label) = label {
+                        r.declare(label, DeclarationKind::Label { on_loop: true });
+                    }
+                    let kind = DeclarationKind::Variable { mutable: false };
+                    r.declare_pattern(binding, kind);
+                    r.looped(true, |r| r.visit_block_mut(exprs, body));
+                });
+            }
+            _ => walk_stmt_mut(self, exprs, stmt),
+        }
+    }
+
+    fn visit_block_mut(&mut self, exprs: &mut Exprs, block: &mut Block) {
+        self.scoped(false, |r| {
+            for stmt in &mut block.stmts {
+                if let StatementKind::Item(item) = &mut stmt.node {
+                    r.declare_item(item);
+                }
+            }
+            walk_block_mut(r, exprs, block);
+        });
+    }
+
+    fn visit_expr_mut(&mut self, exprs: &mut Exprs, expr: ExprId) {
+        match &mut exprs[expr].kind {
+            ExpressionKind::Ident(ident) => self.resolve_name(ident),
+            ExpressionKind::StructLiteral { name, .. } => {
+                self.resolve_name(name);
+                walk_expr_mut(self, exprs, expr);
+            }
+            ExpressionKind::Block { label: Some(_), .. } => exprs.with_kind(expr, |exprs, kind| {
+                let ExpressionKind::Block {
+                    label: Some(label),
+                    block,
+                } = kind
+                else {
+                    unreachable!();
+                };
+                self.scoped(false, |r| {
+                    r.declare(label, DeclarationKind::Label { on_loop: false });
+                    r.visit_block_mut(exprs, block);
+                });
+            }),
+            ExpressionKind::Loop { .. } => exprs.with_kind(expr, |exprs, kind| {
+                let ExpressionKind::Loop { label, body } = kind else {
+                    unreachable!();
+                };
+                self.scoped(false, |r| {
+                    if let Some(label) = label {
+                        r.declare(label, DeclarationKind::Label { on_loop: true });
+                    }
+                    r.looped(true, |r| r.visit_block_mut(exprs, body));
+                });
+            }),
+            ExpressionKind::Break { label, .. } => {
+                match label {
+                    Some(label) => self.resolve_name(label),
+                    None => self.check_in_loop("break", exprs[expr].span),
+                }
+                walk_expr_mut(self, exprs, expr);
+            }
+            ExpressionKind::Continue { label } => match label {
+                Some(label) => {
+                    self.resolve_name(label);
+                    let on_block = label.decl.is_some_and(|id| {
+                        self.decls.get(id).kind == DeclarationKind::Label { on_loop: false }
+                    });
+                    if on_block {
+                        let text = &self.source[label.span];
+                        let message =
+                            format!("`continue` cannot target `{text}`, which labels a block");
+                        let diagnostic = Diagnostic::error(label.span, message)
+                            .with_code(Code::BreakOutsideLoop);
+                        self.diagnostics.push(diagnostic);
+                    }
+                }
+                None => self.check_in_loop("continue", exprs[expr].span),
+            },
+            _ => walk_expr_mut(self, exprs, expr),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        Parser,
+        ast::{
+            expr::OperatorExpression,
+            visit::{Visitor, walk_expr},
+        },
+    };
+
+    // Resolves `body` as the body of a function and gets the messages of
+    // all errors.
+    fn errors(body: &str) -> Vec<String> {
+        let source = format!("fn f() {{\n{body}\n}}");
+        let (mut ast, diagnostics) = Parser::new(&source).parse();
+        assert!(diagnostics.is_empty(), "{source}: {diagnostics:?}");
+
+        let (_, diagnostics) = resolve(&source, &mut Interner::new(), &mut ast);
+        diagnostics.iter().map(|d| d.message().to_owned()).collect()
+    }
+
+    // Resolves `body` as the body of a function, which must succeed,
+    // and gets the name of each use of a local along with the line its
+    // declaration is on. The function itself is on line 1.
+    fn uses(body: &str) -> Vec<(String, u32)> {
+        struct Uses<'a> {
+            source: &'a str,
+            decls: &'a Declarations,
+            out: Vec<(String, u32)>,
+        }
+
+        impl Visitor for Uses<'_> {
+            fn visit_expr(&mut self, exprs: &Exprs, expr: ExprId) {
+                if let ExpressionKind::Ident(ident) = &exprs[expr].kind {
+                    let decl = self.decls.get(ident.decl.unwrap());
+                    let (line, _) = decl.span.start().as_line_and_column(self.source);
+                    self.out.push((self.source[ident.span].to_owned(), line));
+                }
+                walk_expr(self, exprs, expr);
+            }
+        }
+
+        let source = format!("fn f(p: int) {{\n{body}\n}}");
+        let (mut ast, diagnostics) = Parser::new(&source).parse();
+        assert!(diagnostics.is_empty(), "{source}: {diagnostics:?}");
+        let (decls, diagnostics) = resolve(&source, &mut Interner::new(), &mut ast);
+        assert!(diagnostics.is_empty(), "{source}: {diagnostics:?}");
+
+        let mut uses = Uses {
+            source: &source,
+            decls: &decls,
+            out: Vec::new(),
+        };
+        for item in &ast.items {
+            uses.visit_item(&ast.exprs, item);
+        }
+        uses.out
+    }
+
+    #[test]
+    fn resolves_locals() {
+        let expected = [
+            ("p".to_owned(), 1),
+            ("f".to_owned(), 1),
+            ("a".to_owned(), 2),
+        ];
+        assert_eq!(uses("let a = p\nf(a)"), expected);
+
+        assert_eq!(errors("x\nlet x = 1"), ["cannot find `x` in this scope"]);
+        assert_eq!(errors("g()"), ["cannot find `g` in this scope"]);
+    }
+
+    #[test]
+    fn shadowing() {
+        let body = "let x = 1\n{\nlet x = x\nx\n}\nx";
+        let lines: Vec<_> = uses(body).into_iter().map(|(_, line)| line).collect();
+        // The initializer of the inner `x` still sees the outer one.
+        assert_eq!(lines, [2, 4, 2]);
+    }
+
+    #[test]
+    fn labeled_blocks() {
+        let body = "let v = 'b: {\n if true { break 'b 1 }\n 2\n }";
+        assert!(errors(body).is_empty());
+        assert_eq!(errors("{ break }"), ["`break` outside of a loop"]);
+        assert_eq!(errors("'b: { break }"), ["`break` outside of a loop"]);
+    }
+
+    #[test]
+    fn labeled_loops() {
+        let body = "'a: loop {\n 'b: for i in 0..3 {\n continue 'a\n break 'b\n }\n break 'a\n }";
+        assert!(errors(body).is_empty());
+        assert_eq!(
+            errors("'b: { loop { continue 'b } }"),
+            ["`continue` cannot target `'b`, which labels a block"],
+        );
+        assert_eq!(
+            errors("'a: loop {}\nbreak 'a"),
+            ["cannot find `'a` in this scope"],
+        );
+        assert_eq!(errors("continue"), ["`continue` outside of a loop"]);
+    }
+
+    #[test]
+    fn undeclared_variables() {
+        assert_eq!(
+            errors("let x = 1\nx + y"),
+            ["cannot find `y` in this scope"]
+        );
+        assert_eq!(
+            errors("{\n let y = 1\n }\n {\n y\n }"),
+            ["cannot find `y` in this scope"],
+        );
+    }
+
+    #[test]
+    fn duplicate_functions() {
+        // Functions can't be overloaded, so the parameters don't matter.
+        let source = "fn f(a: int) {}\nfn f() {}";
+        let (mut ast, _) = Parser::new(source).parse();
+        let (_, diagnostics) = resolve(source, &mut Interner::new(), &mut ast);
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].message(), "`f` is defined multiple times");
+        assert_eq!(diagnostics[0].span(), SourceSpan::new(19, 20));
+        let label = &diagnostics[0].labels()[0];
+        assert_eq!(
+            (label.span, &label.message[..]),
+            (SourceSpan::new(3, 4), "previously defined here")
+        );
+
+        let source = "fn f() {}\nfn g() {}";
+        let (mut ast, _) = Parser::new(source).parse();
+        let (_, diagnostics) = resolve(source, &mut Interner::new(), &mut ast);
+        assert!(diagnostics.is_empty());
+    }
+
+    #[test]
+    fn interns_names() {
+        let source = "fn f(x: int): int {\n    let y = x\n    x + y\n}";
+        let (mut ast, _) = Parser::new(source).parse();
+        let mut interner = Interner::new();
+        let (_, diagnostics) = resolve(source, &mut interner, &mut ast);
+        assert!(diagnostics.is_empty());
+        // `f`, `x` and `y`, however often they occur.
+        assert_eq!(interner.len(), 3);
+
+        let ItemKind::Function(function) = &ast.items[0].kind else {
+            panic!("expected a function");
+        };
+        let x = function.args[0].pattern.ident().unwrap().symbol.unwrap();
+        assert_eq!(interner.resolve(x), "x");
+        assert_eq!(interner.intern("x"), x);
+
+        // Names are compared by symbol, without going back to the source.
+        let tail = function.block.tail.unwrap();
+        let ExpressionKind::Operator(OperatorExpression::ArithmeticLogical { lhs, .. }) =
+            ast.exprs[tail].kind
+        else {
+            panic!("expected `x + y`");
+        };
+        let ExpressionKind::Ident(ident) = &ast.exprs[lhs].kind else {
+            panic!("expected `x`");
+        };
+        assert_eq!(ident.symbol, Some(x));
+    }
+}
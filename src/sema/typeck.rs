@@ -0,0 +1,632 @@
+use std::{collections::HashMap, mem};
+
+use crate::{
+    ast::{
+        Ast, DeclId, Item, ItemKind, TypeNode,
+        expr::{
+            ArithmeticLogicalOperator, CompoundAssignmentOperator, ExprId, ExpressionKind, Exprs,
+            IntType, Literal, NegationOperator, OperatorExpression,
+        },
+        stmt::{Block, StatementKind},
+        ty::Type,
+    },
+    diagnostic::{Code, Diagnostic, SourceSpan},
+};
+
+/// Infers and checks the types of all expressions in `ast`.
+///
+/// This runs after [`super::resolve`], whose declarations it relies on
+/// to find the types of names. Every expression gets its `ty` field set,
+/// and type errors are reported as diagnostics.
+///
+/// Only the primitive types are checked for now. `int` is another name
+/// for `i64`. Everything else, such as structs, references and arrays,
+/// has [`Type::Unknown`], which is compatible with any type.
+pub fn typeck(source: &str, ast: &mut Ast) -> Vec<Diagnostic> {
+    let mut checker = Checker {
+        source,
+        exprs: &mut ast.exprs,
+        types: HashMap::new(),
+        returns: HashMap::new(),
+        ret: None,
+        statement: false,
+        diagnostics: Vec::new(),
+    };
+
+    for item in &ast.items {
+        checker.declare_item(item);
+    }
+    for item in &ast.items {
+        checker.item(item);
+    }
+
+    checker.diagnostics
+}
+
+// The operand types a binary operator accepts. Both operands must also
+// have the same type.
+#[derive(Clone, Copy)]
+enum Operands {
+    Numeric,
+    IntegerOrBool,
+    // Shifts, which take any two integers.
+    Shift,
+    // `&&=` and `||=`, which only take `bool`s.
+    Bool,
+}
+
+impl From<ArithmeticLogicalOperator> for Operands {
+    fn from(op: ArithmeticLogicalOperator) -> Self {
+        use ArithmeticLogicalOperator::*;
+        match op {
+            Plus | Minus | Multiply | Divide | Modulo | Pow => Self::Numeric,
+            And | Or | Xor => Self::IntegerOrBool,
+            Shl | Shr => Self::Shift,
+        }
+    }
+}
+
+impl From<CompoundAssignmentOperator> for Operands {
+    fn from(op: CompoundAssignmentOperator) -> Self {
+        use CompoundAssignmentOperator::*;
+        match op {
+            Plus | Minus | Multiply | Divide | Modulo | Pow => Self::Numeric,
+            And | Or | Xor => Self::IntegerOrBool,
+            Shl | Shr => Self::Shift,
+            LogicalAnd | LogicalOr => Self::Bool,
+        }
+    }
+}
+
+struct Checker<'a> {
+    source: &'a str,
+    exprs: &'a mut Exprs,
+    // The types of variables, parameters and constants, and those of
+    // the values given to `break` for each label.
+    types: HashMap<DeclId, Type>,
+    // The return types of functions.
+    returns: HashMap<DeclId, Type>,
+    // The return type of the function being checked.
+    ret: Option<Type>,
+    // Set right before checking an expression or block whose value is
+    // discarded, like an expression statement.
+    statement: bool,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl Checker<'_> {
+    fn error(&mut self, span: SourceSpan, code: Code, message: impl Into<String>) {
+        let diagnostic = Diagnostic::error(span, message).with_code(code);
+        self.diagnostics.push(diagnostic);
+    }
+
+    // Checks that `found` can be used where `expected` is required.
+    fn expect(&mut self, expected: Type, found: Type, span: SourceSpan) -> Type {
+        match expected.unify(found) {
+            Some(ty) => ty,
+            None => {
+                let message = format!("mismatched types: expected `{expected}`, found `{found}`");
+                self.error(span, Code::TypeMismatch, message);
+                expected
+            }
+        }
+    }
+
+    fn lower(&self, typ: &TypeNode) -> Type {
+        match typ {
+            TypeNode::Named { name, args } if args.is_empty() => match &self.source[name.span] {
+                "bool" => Type::Bool,
+                "float" => Type::Float,
+                "str" => Type::Str,
+                "char" => Type::Char,
+                "int" => Type::Int(IntType::I64),
+                name => IntType::from_suffix(name).map_or(Type::Unknown, Type::Int),
+            },
+            TypeNode::Tuple(elems) if elems.is_empty() => Type::Unit,
+            _ => Type::Unknown,
+        }
+    }
+
+    // Records the signature of an item, so that it can be used before
+    // the item itself was checked.
+    fn declare_item(&mut self, item: &Item) {
+        match &item.kind {
+            ItemKind::Function(function) => {
+                let ret = function.ret.as_ref().map_or(Type::Unit, |t| self.lower(t));
+                if let Some(id) = function.name.decl {
+                    self.returns.insert(id, ret);
+                }
+            }
+            // A constant without a type takes the type of its value.
+            // Its value can therefore only refer to constants that
+            // were declared before it.
+            ItemKind::Const(c) => {
+                let declared = c.typ.as_ref().map(|t| self.lower(t));
+                let ty = match (declared, c.expr) {
+                    (Some(declared), Some(expr)) => {
+                        let found = self.expr(expr);
+                        self.expect(declared, found, self.exprs[expr].span)
+                    }
+                    (None, Some(expr)) => self.expr(expr),
+                    (declared, None) => declared.unwrap_or(Type::Unknown),
+                };
+                if let Some(id) = c.name.decl {
+                    self.types.insert(id, ty);
+                }
+            }
+            // Types are only known by name, aliases are not followed.
+            ItemKind::Struct(_) | ItemKind::TypeAlias(_) => {}
+            ItemKind::Module(module) => {
+                for item in module.items.iter().flatten() {
+                    self.declare_item(item);
+                }
+            }
+        }
+    }
+
+    fn item(&mut self, item: &Item) {
+        let function = match &item.kind {
+            ItemKind::Function(function) => function,
+            ItemKind::Module(module) => {
+                for item in module.items.iter().flatten() {
+                    self.item(item);
+                }
+                return;
+            }
+            _ => return,
+        };
+
+        for arg in &function.args {
+            if let Some(id) = arg.pattern.ident().and_then(|name| name.decl) {
+                let ty = self.lower(&arg.typ);
+                self.types.insert(id, ty);
+            }
+        }
+
+        let ret = function.ret.as_ref().map_or(Type::Unit, |t| self.lower(t));
+        let outer = self.ret.replace(ret);
+        let found = self.block(&function.block);
+        let span = match function.block.tail {
+            Some(tail) => self.exprs[tail].span,
+            None => function.name.span,
+        };
+        self.expect(ret, found, span);
+        self.ret = outer;
+    }
+
+    fn block(&mut self, block: &Block) -> Type {
+        let statement = mem::take(&mut self.statement);
+        for stmt in &block.stmts {
+            if let StatementKind::Item(item) = &stmt.node {
+                self.declare_item(item);
+            }
+        }
+
+        let mut diverges = false;
+        for stmt in &block.stmts {
+            match &stmt.node {
+                StatementKind::Item(item) => self.item(item),
+                StatementKind::Variable { pattern, expr, .. } => {
+                    let ty = self.expr(*expr);
+                    if let Some(id) = pattern.ident().and_then(|ident| ident.decl) {
+                        self.types.insert(id, ty);
+                    }
+                }
+                StatementKind::Expression(expr) => {
+                    self.statement = true;
+                    self.expr(*expr);
+                    diverges |= matches!(
+                        self.exprs[*expr].kind,
+                        ExpressionKind::Return(_)
+                            | ExpressionKind::Break { .. }
+                            | ExpressionKind::Continue { .. }
+                    );
+                }
+                StatementKind::For {
+                    binding,
+                    iter,
+                    body,
+                    ..
+                } => {
+                    self.expr(*iter);
+                    let elem = match self.exprs[*iter].kind {
+                        // A range without a start has nothing to count up from.
+                        ExpressionKind::Range {
+                            start: Some(start),
+                            end,
+                            ..
+                        } => {
+                            let start = self.exprs[start].ty;
+                            match end {
+                                Some(end) => start
+                                    .zip(self.exprs[end].ty)
+                                    .and_then(|(start, end)| start.unify(end)),
+                                None => start,
+                            }
+                            .unwrap_or(Type::Unknown)
+                        }
+                        _ => Type::Unknown,
+                    };
+                    if let Some(id) = binding.ident().and_then(|binding| binding.decl) {
+                        self.types.insert(id, elem);
+                    }
+                    self.statement = true;
+                    self.block(body);
+                }
+            }
+        }
+
+        match block.tail {
+            Some(tail) => {
+                self.statement = statement;
+                self.expr(tail)
+            }
+            // A block that always returns early can stand in for any
+            // value.
+            None if diverges => Type::Unknown,
+            None => Type::Unit,
+        }
+    }
+
+    fn expr(&mut self, id: ExprId) -> Type {
+        // The kind is taken out of the arena while checking it, which
+        // leaves the arena free to record the types of the operands.
+        let span = self.exprs[id].span;
+        let kind = mem::replace(&mut self.exprs[id].kind, ExpressionKind::Error);
+        let statement = mem::take(&mut self.statement);
+        let ty = self.kind(&kind, span, statement);
+
+        let expr = &mut self.exprs[id];
+        expr.kind = kind;
+        expr.ty = Some(ty);
+        ty
+    }
+
+    fn kind(&mut self, kind: &ExpressionKind, span: SourceSpan, statement: bool) -> Type {
+        match kind {
+            ExpressionKind::Ident(ident) => ident
+                .decl
+                .and_then(|id| self.types.get(&id))
+                .copied()
+                .unwrap_or(Type::Unknown),
+            ExpressionKind::Literal(literal) => match literal {
+                Literal::Int { typ: Some(typ), .. } => Type::Int(*typ),
+                Literal::Int { typ: None, .. } => Type::IntLiteral,
+                Literal::Float(_) => Type::Float,
+                Literal::Bool(_) => Type::Bool,
+                Literal::Str(_) => Type::Str,
+                Literal::Char(_) => Type::Char,
+                Literal::Unit => Type::Unit,
+            },
+            ExpressionKind::Array(elems) | ExpressionKind::Tuple(elems) => {
+                for elem in elems {
+                    self.expr(*elem);
+                }
+                Type::Unknown
+            }
+            ExpressionKind::ArrayRepeat { value, len } => {
+                self.expr(*value);
+                self.expr(*len);
+                Type::Unknown
+            }
+            ExpressionKind::Block { label, block } => {
+                self.statement = statement;
+                let ty = self.block(block);
+                // The value of the block must match that of any `break`
+                // out of it.
+                match label.as_ref().and_then(|label| label.decl) {
+                    Some(id) if !statement && self.types.contains_key(&id) => {
+                        let span = block.tail.map_or(block.span, |tail| self.exprs[tail].span);
+                        self.expect(self.types[&id], ty, span)
+                    }
+                    _ => ty,
+                }
+            }
+            ExpressionKind::Loop { body, .. } => {
+                self.statement = true;
+                self.block(body);
+                Type::Unknown
+            }
+            ExpressionKind::Index { cont, idx } => {
+                self.expr(*cont);
+                for idx in idx {
+                    self.expr(*idx);
+                }
+                Type::Unknown
+            }
+            ExpressionKind::Call { func, params } => {
+                self.expr(*func);
+                for param in params {
+                    self.expr(*param);
+                }
+                match &self.exprs[*func].kind {
+                    ExpressionKind::Ident(ident) => ident
+                        .decl
+                        .and_then(|id| self.returns.get(&id))
+                        .copied()
+                        .unwrap_or(Type::Unknown),
+                    _ => Type::Unknown,
+                }
+            }
+            // Neither fields nor methods are looked up yet.
+            ExpressionKind::Field { expr, .. } => {
+                self.expr(*expr);
+                Type::Unknown
+            }
+            ExpressionKind::MethodCall { receiver, args, .. } => {
+                self.expr(*receiver);
+                for arg in args {
+                    self.expr(*arg);
+                }
+                Type::Unknown
+            }
+            ExpressionKind::Cast { expr, typ } => {
+                self.expr(*expr);
+                self.lower(typ)
+            }
+            ExpressionKind::If {
+                cond,
+                then_block,
+                else_block,
+            } => {
+                let found = self.expr(*cond);
+                self.expect(Type::Bool, found, self.exprs[*cond].span);
+
+                self.statement = statement;
+                let then_ty = self.block(then_block);
+                match else_block {
+                    Some(else_block) => {
+                        self.statement = statement;
+                        let else_ty = self.expr(*else_block);
+                        // The branches of a statement may have any types,
+                        // as the value is discarded.
+                        let unified = then_ty.unify(else_ty);
+                        if statement {
+                            return unified.unwrap_or(Type::Unknown);
+                        }
+                        unified.unwrap_or_else(|| {
+                            let message = format!(
+                                "`if` and `else` have incompatible types: `{then_ty}` and `{else_ty}`"
+                            );
+                            self.error(self.exprs[*else_block].span, Code::TypeMismatch, message);
+                            Type::Unknown
+                        })
+                    }
+                    // Without an `else`, there is no value when the
+                    // condition is false.
+                    None if statement || then_ty.unify(Type::Unit).is_some() => Type::Unit,
+                    None => {
+                        let message = "`if` without `else` cannot be used as a value";
+                        self.error(span, Code::TypeMismatch, message);
+                        Type::Unknown
+                    }
+                }
+            }
+            ExpressionKind::Operator(op) => self.operator(op, span),
+            ExpressionKind::Range { start, end, .. } => {
+                let start_ty = start.map(|start| self.expr(start));
+                let end_ty = end.map(|end| self.expr(end));
+                if let (Some(start_ty), Some(end_ty), Some(end)) = (start_ty, end_ty, end) {
+                    self.expect(start_ty, end_ty, self.exprs[*end].span);
+                }
+                Type::Unknown
+            }
+            ExpressionKind::StructLiteral { fields, .. } => {
+                for field in fields {
+                    self.expr(field.expr);
+                }
+                Type::Unknown
+            }
+            ExpressionKind::Return(value) => {
+                let (found, span) = match value {
+                    Some(value) => (self.expr(*value), self.exprs[*value].span),
+                    None => (Type::Unit, span),
+                };
+                if let Some(ret) = self.ret {
+                    self.expect(ret, found, span);
+                }
+                Type::Unknown
+            }
+            ExpressionKind::Break { label, value } => {
+                let (found, span) = match value {
+                    Some(value) => (self.expr(*value), self.exprs[*value].span),
+                    None => (Type::Unit, span),
+                };
+                // The first `break` out of a block decides the type of
+                // its value.
+                if let Some(id) = label.as_ref().and_then(|label| label.decl) {
+                    let ty = match self.types.get(&id) {
+                        Some(&expected) => self.expect(expected, found, span),
+                        None => found,
+                    };
+                    self.types.insert(id, ty);
+                }
+                Type::Unknown
+            }
+            ExpressionKind::Continue { .. } | ExpressionKind::Error => Type::Unknown,
+        }
+    }
+
+    fn operator(&mut self, op: &OperatorExpression, span: SourceSpan) -> Type {
+        match op {
+            OperatorExpression::ArithmeticLogical { lhs, op, rhs } => {
+                let lhs = self.expr(*lhs);
+                let rhs = self.expr(*rhs);
+                self.binary(op.as_str(), Operands::from(*op), lhs, rhs, span)
+            }
+            OperatorExpression::CompoundAssignment { lhs, op, rhs } => {
+                let lhs = self.expr(*lhs);
+                let rhs = self.expr(*rhs);
+                self.binary(op.as_str(), Operands::from(*op), lhs, rhs, span);
+                Type::Unit
+            }
+            OperatorExpression::Comparison { lhs, op, rhs } => {
+                let lhs = self.expr(*lhs);
+                let rhs = self.expr(*rhs);
+                if lhs.unify(rhs).is_none() {
+                    let message = format!(
+                        "cannot compare `{lhs}` with `{rhs}` using `{}`",
+                        op.as_str()
+                    );
+                    self.error(span, Code::InvalidOperands, message);
+                }
+                Type::Bool
+            }
+            OperatorExpression::Boolean { lhs, rhs, .. } => {
+                for operand in [lhs, rhs] {
+                    let found = self.expr(*operand);
+                    self.expect(Type::Bool, found, self.exprs[*operand].span);
+                }
+                Type::Bool
+            }
+            OperatorExpression::Negation { op, expr } => {
+                let ty = self.expr(*expr);
+                let valid = match op {
+                    NegationOperator::Negation => {
+                        ty.is_numeric() && !matches!(ty, Type::Int(t) if !t.is_signed())
+                    }
+                    NegationOperator::LogicalNot => ty.unify(Type::Bool).is_some(),
+                    NegationOperator::BitwiseNot => ty.is_integer(),
+                };
+                if !valid {
+                    let message = format!("cannot apply unary `{}` to `{ty}`", op.as_str());
+                    self.error(span, Code::InvalidOperands, message);
+                    return Type::Unknown;
+                }
+                ty
+            }
+            OperatorExpression::Assignment { lhs, rhs } => {
+                let expected = self.expr(*lhs);
+                let found = self.expr(*rhs);
+                self.expect(expected, found, self.exprs[*rhs].span);
+                Type::Unit
+            }
+            OperatorExpression::AddressOf { expr } | OperatorExpression::Dereference { expr } => {
+                self.expr(*expr);
+                Type::Unknown
+            }
+            OperatorExpression::Increment { expr } | OperatorExpression::Decrement { expr } => {
+                let ty = self.expr(*expr);
+                if !ty.is_integer() {
+                    let op = match op {
+                        OperatorExpression::Increment { .. } => "++",
+                        _ => "--",
+                    };
+                    let message = format!("cannot apply `{op}` to `{ty}`");
+                    self.error(span, Code::InvalidOperands, message);
+                    return Type::Unknown;
+                }
+                ty
+            }
+        }
+    }
+
+    fn binary(
+        &mut self,
+        op: &str,
+        operands: Operands,
+        lhs: Type,
+        rhs: Type,
+        span: SourceSpan,
+    ) -> Type {
+        let ty = match operands {
+            Operands::Numeric => lhs.unify(rhs).filter(|t| t.is_numeric()),
+            Operands::IntegerOrBool => lhs
+                .unify(rhs)
+                .filter(|&t| t.is_integer() || t == Type::Bool),
+            Operands::Shift => Some(lhs).filter(|_| lhs.is_integer() && rhs.is_integer()),
+            Operands::Bool => lhs.unify(rhs).filter(|&t| t == Type::Bool),
+        };
+
+        ty.unwrap_or_else(|| {
+            let message = format!("cannot apply `{op}` to `{lhs}` and `{rhs}`");
+            self.error(span, Code::InvalidOperands, message);
+            Type::Unknown
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Parser, ast::symbol::Interner, sema::resolve};
+
+    // Resolves and type checks `source` and gets the messages of all
+    // errors.
+    fn errors(source: &str) -> Vec<String> {
+        let (mut ast, diagnostics) = Parser::new(source).parse();
+        assert!(diagnostics.is_empty(), "{source}: {diagnostics:?}");
+
+        let (_, diagnostics) = resolve(source, &mut Interner::new(), &mut ast);
+        assert!(diagnostics.is_empty(), "{source}: {diagnostics:?}");
+        typeck(source, &mut ast)
+            .iter()
+            .map(|d| d.message().to_owned())
+            .collect()
+    }
+
+    #[test]
+    fn diverging_branches() {
+        // The `return` branch takes the type of the other one.
+        let source = "fn f(c: bool): int {\n    let x = { if c { return 0 } else { 5 } }\n    x\n}";
+        assert!(errors(source).is_empty());
+        let source =
+            "fn f(c: bool): bool {\n    let x = if c { return false } else { 5 }\n    x\n}";
+        assert_eq!(
+            errors(source),
+            ["mismatched types: expected `bool`, found `{integer}`"]
+        );
+    }
+
+    #[test]
+    fn operands_and_conditions() {
+        assert!(errors("fn f(a: int): int {\n    a * 2 + (a - 1) / 3\n}").is_empty());
+        assert_eq!(
+            errors("fn f() {\n    let x = 1 + true\n}"),
+            ["cannot apply `+` to `{integer}` and `bool`"],
+        );
+        assert_eq!(
+            errors("fn f() {\n    if 1 {}\n}"),
+            ["mismatched types: expected `bool`, found `{integer}`"],
+        );
+    }
+
+    #[test]
+    fn unit() {
+        assert!(errors("fn f() {}\nfn g() {\n    let x = ()\n    let y = f()\n}").is_empty());
+        // Functions without a return type return `()`.
+        assert_eq!(
+            errors("fn f() {}\nfn g(): int {\n    f()\n}"),
+            ["mismatched types: expected `i64`, found `()`"],
+        );
+        assert_eq!(
+            errors("fn f(): bool {\n    ()\n}"),
+            ["mismatched types: expected `bool`, found `()`"],
+        );
+        assert_eq!(
+            errors("fn f(): bool {\n    (1)\n}"),
+            ["mismatched types: expected `bool`, found `{integer}`"],
+        );
+    }
+
+    #[test]
+    fn if_without_else() {
+        let value = ["`if` without `else` cannot be used as a value"];
+        assert_eq!(errors("fn f(c: bool) {\n    let x = if c { 1 }\n}"), value);
+        assert_eq!(errors("fn f(c: bool): int {\n    if c { 1 }\n}"), value);
+        assert!(errors("fn g() {}\nfn f(c: bool) {\n    if c { g() }\n}").is_empty());
+    }
+
+    #[test]
+    fn unsigned_negation() {
+        assert_eq!(
+            errors("const A: u8 = -1u8"),
+            ["cannot apply unary `-` to `u8`"],
+        );
+        assert_eq!(
+            errors("fn f(a: u8): u8 {\n    -a\n}"),
+            ["cannot apply unary `-` to `u8`"],
+        );
+        assert!(errors("fn f(a: i8): i8 {\n    -a + -1\n}").is_empty());
+    }
+}
@@ -0,0 +1,99 @@
+use crate::{
+    ast::{
+        Ast,
+        expr::{Expression, ExpressionKind, Exprs},
+        stmt::{Block, StatementKind},
+        visit::{Visitor, walk_block},
+    },
+    diagnostic::Diagnostic,
+};
+
+/// Warns about code that follows a `return`, `break` or `continue` in
+/// the same block.
+///
+/// Only one warning is emitted per block, pointing at the first
+/// statement that can never run. Exits nested in other expressions,
+/// such as the branches of an `if`, are not taken into account, even
+/// when every branch ends in one.
+pub fn check_unreachable(ast: &Ast) -> Vec<Diagnostic> {
+    let mut checker = Checker {
+        diagnostics: Vec::new(),
+    };
+    for item in &ast.items {
+        checker.visit_item(&ast.exprs, item);
+    }
+
+    checker.diagnostics
+}
+
+struct Checker {
+    diagnostics: Vec<Diagnostic>,
+}
+
+fn is_exit(expr: &Expression) -> bool {
+    matches!(
+        expr.kind,
+        ExpressionKind::Return(_) | ExpressionKind::Break { .. } | ExpressionKind::Continue { .. }
+    )
+}
+
+impl Visitor for Checker {
+    fn visit_block(&mut self, exprs: &Exprs, block: &Block) {
+        let exit = block.stmts.iter().position(|stmt| match &stmt.node {
+            StatementKind::Expression(expr) => is_exit(&exprs[*expr]),
+            _ => false,
+        });
+
+        if let Some(exit) = exit {
+            // Items are not executed in order, so they're never
+            // unreachable.
+            let next = block.stmts[exit + 1..]
+                .iter()
+                .filter(|stmt| !matches!(stmt.node, StatementKind::Item(_)))
+                .map(|stmt| (stmt.span, "statement"))
+                .next();
+            let next = next.or(block.tail.map(|tail| (exprs[tail].span, "expression")));
+
+            if let Some((span, what)) = next {
+                let exit = block.stmts[exit].span;
+                let diagnostic = Diagnostic::warning(span, format!("unreachable {what}"))
+                    .with_label(exit, "any code following this expression is unreachable");
+                self.diagnostics.push(diagnostic);
+            }
+        }
+
+        walk_block(self, exprs, block);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Parser;
+
+    // Checks `source`, which must parse, and gets the text of the spans
+    // of all warnings.
+    fn unreachable(source: &str) -> Vec<&str> {
+        let (ast, diagnostics) = Parser::new(source).parse();
+        assert!(diagnostics.is_empty(), "{source}: {diagnostics:?}");
+        check_unreachable(&ast)
+            .iter()
+            .map(|d| &source[d.span()])
+            .collect()
+    }
+
+    #[test]
+    fn code_after_exits() {
+        let source = "fn f() {\n    return\n    g()\n    h()\n}";
+        assert_eq!(unreachable(source), ["g()"]);
+
+        // A `return` in one branch leaves the code after the `if` alone.
+        let source = "fn f(c: bool) {\n    if c { return }\n    g()\n}";
+        assert!(unreachable(source).is_empty());
+
+        // The code after an `if` whose branches both return is never run
+        // either, but that takes more than looking at the block.
+        let source = "fn f(c: bool) {\n    if c { return } else { return }\n    g()\n}";
+        assert!(unreachable(source).is_empty());
+    }
+}
@@ -0,0 +1,96 @@
+use std::collections::HashSet;
+
+use super::{DeclarationKind, Declarations};
+use crate::{
+    ast::{
+        Ast, DeclId,
+        expr::{ExprId, ExpressionKind, Exprs},
+        visit::{Visitor, walk_expr},
+    },
+    diagnostic::Diagnostic,
+};
+
+/// Warns about variables and parameters that are never used.
+///
+/// Any use of a name counts, including assigning to it. A binding that
+/// is shadowed before it is used is reported as well. Names starting
+/// with `_` are never reported.
+///
+/// This must run after [`super::resolve`], which tells the uses of
+/// each binding apart.
+pub fn check_unused(source: &str, ast: &Ast, decls: &Declarations) -> Vec<Diagnostic> {
+    let mut uses = Uses(HashSet::new());
+    for item in &ast.items {
+        uses.visit_item(&ast.exprs, item);
+    }
+
+    let mut diagnostics = Vec::new();
+    for (id, decl) in decls.iter() {
+        let local = matches!(
+            decl.kind,
+            DeclarationKind::Variable { .. } | DeclarationKind::Parameter
+        );
+        let name = &source[decl.span];
+        if !local || name.starts_with('_') || uses.0.contains(&id) {
+            continue;
+        }
+
+        let message =
+            format!("unused variable `{name}`, rename it to `_{name}` if this is intended");
+        diagnostics.push(Diagnostic::warning(decl.span, message));
+    }
+
+    diagnostics
+}
+
+// Collects the declarations that are referred to anywhere.
+struct Uses(HashSet<DeclId>);
+
+impl Visitor for Uses {
+    fn visit_expr(&mut self, exprs: &Exprs, expr: ExprId) {
+        if let ExpressionKind::Ident(ident) = &exprs[expr].kind
+            && let Some(id) = ident.decl
+        {
+            self.0.insert(id);
+        }
+
+        walk_expr(self, exprs, expr);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Parser, ast::symbol::Interner, sema::resolve};
+
+    // Gets the spanned text and the message of each warning about an
+    // unused variable in `source`, which must be free of errors.
+    fn unused(source: &str) -> Vec<(&str, String)> {
+        let (mut ast, diagnostics) = Parser::new(source).parse();
+        assert!(diagnostics.is_empty(), "{source}: {diagnostics:?}");
+        let (decls, diagnostics) = resolve(source, &mut Interner::new(), &mut ast);
+        assert!(diagnostics.is_empty(), "{source}: {diagnostics:?}");
+        check_unused(source, &ast, &decls)
+            .iter()
+            .map(|d| (&source[d.span()], d.message().to_owned()))
+            .collect()
+    }
+
+    #[test]
+    fn unused_variables() {
+        assert_eq!(
+            unused("fn f() {\n    let x = 1\n}"),
+            [(
+                "x",
+                "unused variable `x`, rename it to `_x` if this is intended".to_owned()
+            )],
+        );
+        assert!(unused("fn f(): int {\n    let x = 1\n    x\n}").is_empty());
+        assert!(unused("fn f() {\n    let _unused = 1\n}").is_empty());
+
+        // Parameters count, and so do bindings shadowed before any use.
+        let source = "fn f(p: int): int {\n    let x = 1\n    let x = 2\n    x\n}";
+        let names: Vec<_> = unused(source).into_iter().map(|(name, _)| name).collect();
+        assert_eq!(names, ["p", "x"]);
+    }
+}